@@ -0,0 +1,108 @@
+use uuid::Uuid;
+use gtk::prelude::*;
+use relm::{connect, Relm, Widget, Update};
+use relm_derive::Msg;
+
+use std::collections::HashMap;
+
+use crate::description::Description;
+
+#[derive(Msg)]
+pub enum Msg {
+    Unarchive(Uuid)
+}
+
+pub struct Widgets {
+    container: gtk::Dialog,
+    archive_list: gtk::ListBox
+}
+
+pub struct Archive {
+    model: Model,
+    widgets: Widgets,
+}
+
+pub struct Model {
+    relm: Relm<Archive>,
+    win: libhandy::ApplicationWindow,
+    entries: Vec<(Uuid, Description)>,
+    rows: HashMap<Uuid, gtk::Box>
+}
+
+impl Update for Archive {
+    type Model = Model;
+    type ModelParam = (libhandy::ApplicationWindow, Vec<(Uuid, Description)>);
+    type Msg = Msg;
+
+    fn model(relm: &Relm<Self>, (win, entries): (libhandy::ApplicationWindow, Vec<(Uuid, Description)>)) -> Model {
+        Model {
+            relm: relm.clone(),
+            win, entries,
+            rows: Default::default()
+        }
+    }
+
+    fn update(&mut self, event: Msg) {
+        match event {
+            Msg::Unarchive(id) => {
+                if let Some(row) = self.model.rows.remove(&id) {
+                    if let Some(parent) = row.get_parent() {
+                        self.widgets.archive_list.remove(&parent);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Widget for Archive {
+    type Root = gtk::Dialog;
+
+    fn root(&self) -> Self::Root {
+        self.widgets.container.clone()
+    }
+
+    fn view(relm: &Relm<Self>, mut model: Self::Model) -> Self {
+        let glade_src = include_str!("../ui/archive.ui");
+        let builder = gtk::Builder::from_string(glade_src);
+        let container: gtk::Dialog = or_panic!(builder.get_object("archiveDialog"));
+        let archive_list: gtk::ListBox = or_panic!(builder.get_object("archiveList"));
+
+        for (id, description) in &model.entries {
+            let row = archive_row(relm, *id, description);
+            archive_list.add(&row);
+            model.rows.insert(*id, row);
+        }
+
+        container.set_transient_for(Some(&model.win));
+        container.connect_response(|dialog, _| dialog.close());
+        container.show_all();
+
+        Archive {
+            model,
+            widgets: Widgets {
+                container,
+                archive_list
+            },
+        }
+    }
+}
+
+fn archive_row(relm: &Relm<Archive>, id: Uuid, description: &Description) -> gtk::Box {
+    let row = gtk::Box::new(gtk::Orientation::Horizontal, 5);
+    row.set_margin_top(5);
+    row.set_margin_bottom(5);
+    row.set_margin_start(10);
+    row.set_margin_end(10);
+
+    let label = gtk::Label::new(Some(&description.label));
+    label.set_halign(gtk::Align::Start);
+    row.pack_start(&label, true, true, 0);
+
+    let unarchive_btn = gtk::Button::from_icon_name(Some("edit-undo-symbolic"), gtk::IconSize::Button);
+    unarchive_btn.set_tooltip_text(Some("Unarchive"));
+    row.pack_end(&unarchive_btn, false, true, 0);
+
+    connect!(relm, unarchive_btn, connect_clicked(_), Msg::Unarchive(id));
+    row
+}
@@ -0,0 +1,103 @@
+use uuid::Uuid;
+use chrono::NaiveDate;
+use serde::{ Serialize, Serializer, Deserialize, Deserializer };
+use serde::de::{self, SeqAccess, Visitor};
+
+use std::{env, fmt, fs};
+use std::path::{Path, PathBuf};
+
+/// A file kept with a cycle, like an invoice or a receipt.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Attachment {
+    pub id: Uuid,
+    pub cycle: Uuid,
+    pub name: String,
+    pub size: u64,
+    pub added: NaiveDate,
+    /// The start of the renewal period it belongs to, `None` for the cycle as a whole.
+    pub period: Option<NaiveDate>
+}
+
+impl Attachment {
+
+    pub fn new(cycle: Uuid, name: &str, size: u64, added: NaiveDate, period: Option<NaiveDate>) -> Attachment {
+        Attachment { id: Uuid::new_v4(), cycle, name: file_name(name), size, added, period }
+    }
+
+    /// Where a copy is written for the system viewer to open. Attachments
+    /// of an encrypted database are only in clear while they are open.
+    pub fn open_path(&self) -> PathBuf {
+        cache_dir().join(self.id.to_string()).join(&self.name)
+    }
+}
+
+/// Copies opened for viewing, cleared with `clear_opened`.
+fn cache_dir() -> PathBuf {
+    env::var("XDG_CACHE_HOME").map(PathBuf::from)
+        .or_else(|_| env::var("HOME").map(|home| Path::new(&home).join(".cache")))
+        .expect("HOME Environment Variable not set")
+        .join("cycles").join("attachments")
+}
+
+/// Removes the copies written for viewing, e.g. when the app quits.
+pub fn clear_opened() {
+    let _ = fs::remove_dir_all(cache_dir());
+}
+
+/// Just the last component of a path, so stored names never point elsewhere.
+fn file_name(name: &str) -> String {
+    Path::new(name).file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "attachment".into())
+}
+
+pub fn format_size(size: u64) -> String {
+    match size {
+        0..=1023 => format!("{} B", size),
+        1024..=1_048_575 => format!("{:.0} KB", size as f64 / 1024.),
+        _ => format!("{:.1} MB", size as f64 / 1_048_576.)
+    }
+}
+
+/// File contents, written as one CBOR byte string rather than an array of numbers.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Blob(pub Vec<u8>);
+
+impl Serialize for Blob {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Blob {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Blob, D::Error> {
+        deserializer.deserialize_byte_buf(BlobVisitor)
+    }
+}
+
+struct BlobVisitor;
+
+impl<'de> Visitor<'de> for BlobVisitor {
+    type Value = Blob;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a byte string")
+    }
+
+    fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<Blob, E> {
+        Ok(Blob(bytes.to_vec()))
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, bytes: Vec<u8>) -> Result<Blob, E> {
+        Ok(Blob(bytes))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Blob, A::Error> {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(byte) = seq.next_element()? {
+            bytes.push(byte);
+        }
+        Ok(Blob(bytes))
+    }
+}
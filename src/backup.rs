@@ -0,0 +1,168 @@
+use chrono::{Duration, NaiveDate};
+use serde::{ Serialize, Deserialize };
+
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::cache::{self, Database, Export, ExportError};
+use crate::crypto::Key;
+
+const PREFIX: &str = "cycles-";
+const EXTENSION: &str = "cbor";
+const SETTINGS_KEY: &str = "backup";
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly
+}
+
+impl Frequency {
+
+    fn days(&self) -> i64 {
+        match self {
+            Frequency::Daily => 1,
+            Frequency::Weekly => 7,
+            Frequency::Monthly => 30
+        }
+    }
+}
+
+/// How often backups are written and how many are kept. Only the frequency
+/// is stored in the database, the retention is a preference in GSettings.
+#[derive(Debug, PartialEq, Clone)]
+pub struct BackupSettings {
+    pub frequency: Frequency,
+    pub retention: usize
+}
+
+/// What `BackupSettings` keeps in the database. Older records also hold a
+/// retention, which is ignored.
+#[derive(Serialize, Deserialize)]
+struct Stored {
+    frequency: Frequency
+}
+
+impl Default for BackupSettings {
+    fn default() -> BackupSettings {
+        BackupSettings { frequency: Frequency::Daily, retention: 7 }
+    }
+}
+
+impl BackupSettings {
+
+    /// The stored frequency with the default retention, for the caller to
+    /// replace with the preference.
+    pub fn load(db: &Database) -> BackupSettings {
+        match db.setting::<Stored>(SETTINGS_KEY) {
+            Some(stored) => BackupSettings { frequency: stored.frequency, ..BackupSettings::default() },
+            None => BackupSettings::default()
+        }
+    }
+
+    pub fn save(&self, db: &Database) {
+        db.set_setting(SETTINGS_KEY, &Stored { frequency: self.frequency });
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Backup {
+    pub path: PathBuf,
+    pub date: NaiveDate,
+    /// How many cycles it holds, 0 when it is sealed with another key or can't be read.
+    pub entries: usize,
+    key: Option<Key>
+}
+
+impl Backup {
+
+    pub fn read(&self) -> Result<Export, ExportError> {
+        let file = File::open(&self.path).map_err(|_| ExportError::Unreadable)?;
+        cache::read_export(file, self.key.as_ref())
+    }
+}
+
+/// The rotating set of export files kept next to the database.
+pub struct Backups {
+    dir: PathBuf,
+    key: Option<Key>
+}
+
+impl Default for Backups {
+    fn default() -> Backups {
+        Backups::new(cache::data_dir().join("backups"))
+    }
+}
+
+impl Backups {
+
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Backups {
+        Backups { dir: dir.into(), key: None }
+    }
+
+    /// Reads backups of an encrypted database with its key.
+    pub fn with_key(self, key: Option<Key>) -> Backups {
+        Backups { key, ..self }
+    }
+
+    /// Every backup, newest first.
+    pub fn list(&self) -> Vec<Backup> {
+        let mut backups: Vec<Backup> = self.dated_files().into_iter()
+            .map(|(date, path)| {
+                let mut backup = Backup { path, date, entries: 0, key: self.key.clone() };
+                backup.entries = backup.read().map_or(0, |export| export.cycles.len());
+                backup
+            })
+            .collect();
+        backups.sort_by(|a, b| b.date.cmp(&a.date));
+        backups
+    }
+
+    /// Writes a backup for `today` if the newest one is older than the
+    /// configured frequency, then drops everything past the retention limit.
+    pub fn run_if_due(&self, db: &Database, settings: &BackupSettings, today: NaiveDate) -> io::Result<Option<PathBuf>> {
+        let latest = self.dated_files().into_iter().map(|(date, _)| date).max();
+        let due = match latest {
+            Some(date) => today - date >= Duration::days(settings.frequency.days()),
+            None => true
+        };
+        let written = if due { Some(self.write(db, today)?) } else { None };
+        self.rotate(settings.retention)?;
+        Ok(written)
+    }
+
+    pub fn write(&self, db: &Database, date: NaiveDate) -> io::Result<PathBuf> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.dir.join(format!("{}{}.{}", PREFIX, date.format("%Y-%m-%d"), EXTENSION));
+        db.export_to_writer(File::create(&path)?, true);
+        Ok(path)
+    }
+
+    /// Keeps the newest `retention` backups and deletes the rest.
+    pub fn rotate(&self, retention: usize) -> io::Result<()> {
+        let mut files = self.dated_files();
+        files.sort_by(|a, b| b.0.cmp(&a.0));
+        for (_, path) in files.into_iter().skip(retention.max(1)) {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    fn dated_files(&self) -> Vec<(NaiveDate, PathBuf)> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(_) => return vec![]
+        };
+        entries.flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().map_or(false, |ext| ext == EXTENSION))
+            .filter_map(|path| {
+                let stem = path.file_stem()?.to_str()?;
+                let date = NaiveDate::parse_from_str(stem.strip_prefix(PREFIX)?, "%Y-%m-%d").ok()?;
+                Some((date, path))
+            })
+            .collect()
+    }
+}
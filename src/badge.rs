@@ -2,33 +2,65 @@ use gtk::prelude::*;
 use relm::{connect, Relm, Widget, Update};
 use relm_derive::{widget, Msg};
 
+use std::str::FromStr;
+
+use crate::description::TagStyle;
+
 #[derive(Msg)]
 pub enum Msg {}
 
 pub struct Model {
     relm: Relm<Badge>,
-    text: String
+    text: String,
+    style: TagStyle
+}
+
+fn badge_styles(color: &str) -> String {
+    format!(
+        "box {{background-color: {color};color: {text};}}",
+        color=color,
+        text=text_color(color)
+    )
+}
+
+// Picks black or white text, whichever reads better on top of `color`.
+fn text_color(color: &str) -> &'static str {
+    match gdk::RGBA::from_str(color) {
+        Ok(rgba) if 0.299 * rgba.red + 0.587 * rgba.green + 0.114 * rgba.blue > 0.6 => "black",
+        _ => "white"
+    }
 }
 
 #[widget]
 impl Widget for Badge {
     fn init_view(&mut self) {
-        add_stylesheet!("../css/badge.css", self.widgets.label);
+        add_stylesheet!("../css/badge.css", self.widgets.container);
+        if let Some(color) = self.model.style.color.as_ref() {
+            add_stylesheet!(raw badge_styles(color).as_ref(), self.widgets.container);
+        }
     }
 
-    fn model(relm: &Relm<Self>, text: String) -> Model {
+    fn model(relm: &Relm<Self>, (text, style): (String, TagStyle)) -> Model {
         Model {
             relm: relm.clone(),
-            text
+            text, style
         }
     }
 
     fn update(&mut self, _: Msg) {}
 
     view! {
-        #[name="label"]
-        gtk::Label {
-            label: &self.model.text
+        #[name="container"]
+        gtk::Box {
+            spacing: 2,
+            gtk::Image {
+                no_show_all: true,
+                visible: self.model.style.icon.is_some(),
+                property_icon_name: self.model.style.icon.as_deref()
+            },
+            gtk::Label {
+                label: &self.model.text
+            }
         }
     }
 }
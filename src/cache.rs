@@ -1,22 +1,361 @@
 use uuid::Uuid;
-use sled::{Db, Tree};
+use sled::{Db, Tree, Transactional};
+use sled::transaction::TransactionResult;
+use chrono::{Utc, Duration, NaiveDate};
+use serde::{ Serialize, Deserialize };
 
-use std::env;
-use std::path::PathBuf;
+use std::{env, fmt};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
 
-use crate::{ Description, Interval };
+use crate::{ Description, Interval, TagStyle };
+use crate::attachment::{Attachment, Blob};
+use crate::check::{self, Issue, Problem, Quarantined, Report};
+use crate::crypto::{self, DecryptError, Key, Lock};
+use crate::fields::{self, FieldDefinition};
+use crate::order::Ordering;
+use crate::storage::{self, Backend, FileStore};
+use crate::sync::{Change, Conflict};
+use crate::tag_list;
+
+pub const TRASH_DAYS: i64 = 30;
+const LOCK_KEY: &str = "encryption";
+const DEVICE_KEY: &str = "sync-device";
+/// The cycles last written to the file backend, to tell hand deletions from new cycles.
+const FILE_IDS_KEY: &str = "file-cycles";
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Trashed {
+    pub deleted: NaiveDate,
+    pub description: Description
+}
+
+/// Where a cycle was before `merge` put an imported one in its place.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Slot {
+    Missing,
+    Live(Description),
+    Trashed(Trashed),
+    Archived(Description)
+}
+
+/// What `merge` changed, so `unmerge` can put it back.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Merged {
+    pub cycles: Vec<(Uuid, Slot)>,
+    order: Ordering,
+    fields: Vec<FieldDefinition>,
+    attachments: Vec<Uuid>
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Export {
+    pub cycles: Vec<(Uuid, Description)>,
+    #[serde(default)]
+    pub trash: Vec<(Uuid, Trashed)>,
+    #[serde(default)]
+    pub archive: Vec<(Uuid, Description)>,
+    #[serde(default)]
+    pub order: Ordering,
+    /// Custom field definitions, so the values in `cycles` keep their kinds.
+    #[serde(default)]
+    pub fields: Vec<FieldDefinition>,
+    #[serde(default)]
+    pub attachments: Vec<(Attachment, Blob)>
+}
+
+/// Bumped when the sealed layout changes, so older versions refuse files
+/// they can't read instead of calling them damaged.
+const SEALED_VERSION: u32 = 1;
+
+/// An export file sealed with the key of the encrypted database that wrote
+/// it, or with a password of its own. The salt lets the same passphrase open
+/// it elsewhere.
+#[derive(Serialize, Deserialize)]
+struct Sealed {
+    #[serde(default)]
+    version: u32,
+    salt: Vec<u8>,
+    sealed: Vec<u8>
+}
+
+impl Sealed {
+
+    fn new<T: Serialize>(key: &Key, salt: Vec<u8>, value: &T) -> Sealed {
+        let sealed = key.seal(&or_panic!(Res serde_cbor::to_vec(value)));
+        Sealed { version: SEALED_VERSION, salt, sealed }
+    }
+
+    fn open(&self, key: &Key) -> Result<Export, ExportError> {
+        if self.version > SEALED_VERSION {
+            return Err(ExportError::Unsupported(self.version));
+        }
+        let data = key.open(&self.sealed).map_err(|_| ExportError::Locked)?;
+        match serde_cbor::from_slice(&data).map_err(|_| ExportError::Damaged)? {
+            ExportFormat::Sealed(_) => Err(ExportError::Damaged),
+            format => plain(format)
+        }
+    }
+}
+
+// Older exports and exports of a selection are a bare list of cycles, full
+// exports use the `Export` map.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ExportFormat {
+    Sealed(Sealed),
+    Bundle(Export),
+    Entries(Vec<(Uuid, Description)>)
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ExportError {
+    /// Encrypted with a key or password we don't have.
+    Locked,
+    Damaged,
+    /// The file couldn't be opened.
+    Unreadable,
+    /// Sealed by a newer version of Cycles.
+    Unsupported(u32)
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExportError::Locked => write!(f, "The file is encrypted with a different passphrase"),
+            ExportError::Damaged => write!(f, "The file is not a Cycles export"),
+            ExportError::Unreadable => write!(f, "The file could not be read"),
+            ExportError::Unsupported(version) => write!(f, "The file needs a newer version of Cycles (format {})", version)
+        }
+    }
+}
 
 pub struct Database {
     db: Db,
-    cycles: Tree
+    cycles: Tree,
+    trash: Tree,
+    archive: Tree,
+    settings: Tree,
+    quarantine: Tree,
+    tags: Tree,
+    /// When each cycle last changed and on which device, including deleted ones.
+    changes: Tree,
+    /// Sync merges where both devices had changed a cycle, until the user picks one.
+    conflicts: Tree,
+    /// What is attached to each cycle, by attachment id.
+    attachments: Tree,
+    /// The attached files themselves, by attachment id.
+    blobs: Tree,
+    /// With the file backend the `cycles` tree is a working copy of this file.
+    mirror: Option<FileStore>,
+    /// Why the file backend couldn't be used, the cycles stay in sled meanwhile.
+    storage_error: Option<String>,
+    /// Encrypts the values of the `cycles`, `trash`, `archive`, `conflicts`,
+    /// `attachments` and `blobs` trees once unlocked.
+    key: Option<Key>
 }
 
 impl Database {
 
     pub fn new() -> Database {
-        let db = or_panic!(Res sled::open(&file_path()));
+        let mut database = Database::open_default();
+        // An encrypted database keeps its cycles in sled, see `can_encrypt`.
+        // So does one whose cycles haven't been migrated to the file yet.
+        if Backend::from_env() == Backend::File && !database.is_encrypted() {
+            match FileStore::open(storage::file_path()) {
+                Ok(store) => {
+                    database.attach(store);
+                },
+                Err(err) => database.storage_error = Some(err.to_string())
+            }
+        }
+        if !database.is_locked() {
+            database.purge_expired(Utc::now().date().naive_local());
+        }
+        database
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> Database {
+        Database::from_db(or_panic!(Res sled::open(path)))
+    }
+
+    /// The sled database in the data directory, without the file backend
+    /// attached or the trash emptied.
+    pub fn open_default() -> Database {
+        Database::open(&file_path())
+    }
+
+    /// A database that lives only as long as the returned value.
+    pub fn temporary() -> Database {
+        Database::from_db(or_panic!(Res sled::Config::new().temporary(true).open()))
+    }
+
+    fn from_db(db: Db) -> Database {
         let cycles = or_panic!(Res db.open_tree("cycles"));
-        Database { db, cycles }
+        let trash = or_panic!(Res db.open_tree("trash"));
+        let archive = or_panic!(Res db.open_tree("archive"));
+        let settings = or_panic!(Res db.open_tree("settings"));
+        let quarantine = or_panic!(Res db.open_tree("quarantine"));
+        let tags = or_panic!(Res db.open_tree("tags"));
+        let changes = or_panic!(Res db.open_tree("changes"));
+        let conflicts = or_panic!(Res db.open_tree("conflicts"));
+        let attachments = or_panic!(Res db.open_tree("attachments"));
+        let blobs = or_panic!(Res db.open_tree("blobs"));
+        Database { db, cycles, trash, archive, settings, quarantine, tags, changes, conflicts, attachments, blobs, mirror: None, storage_error: None, key: None }
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.lock().is_some()
+    }
+
+    /// Encrypted, and no passphrase entered yet. Records can't be read until `unlock`.
+    pub fn is_locked(&self) -> bool {
+        self.is_encrypted() && self.key.is_none()
+    }
+
+    /// Encryption only covers sled, the file backend is meant to stay readable.
+    pub fn storage_error(&self) -> Option<&str> {
+        self.storage_error.as_deref()
+    }
+
+    pub fn can_encrypt(&self) -> bool {
+        self.mirror.is_none()
+    }
+
+    pub fn key(&self) -> Option<&Key> {
+        self.key.as_ref()
+    }
+
+    /// Kept in clear, as the key is derived from it.
+    fn lock(&self) -> Option<Lock> {
+        or_panic!(Res self.settings.get(LOCK_KEY))
+            .map(|value| or_panic!(Res serde_cbor::from_slice(&value)))
+    }
+
+    pub fn check_passphrase(&self, passphrase: &str) -> bool {
+        self.lock().and_then(|lock| lock.unlock(passphrase)).is_some()
+    }
+
+    /// Derives the key from `passphrase`, returning whether it was the right one.
+    pub fn unlock(&mut self, passphrase: &str) -> bool {
+        match self.lock().and_then(|lock| lock.unlock(passphrase)) {
+            Some(key) => {
+                self.key = Some(key);
+                self.purge_expired(Utc::now().date().naive_local());
+                true
+            },
+            None => false
+        }
+    }
+
+    /// Re-encrypts every record, tag and setting under a new passphrase, or
+    /// stores them in clear again with `None`. Nothing changes if a single one
+    /// can't be decrypted. The database must be unlocked.
+    pub fn set_passphrase(&mut self, passphrase: Option<&str>) -> Result<(), DecryptError> {
+        assert!(!self.is_locked(), "set_passphrase on a locked database");
+        let sealed = [&self.cycles, &self.trash, &self.archive, &self.conflicts, &self.attachments, &self.blobs, &self.settings];
+        let plain = sealed.iter()
+            .map(|tree| tree.iter().flatten()
+                .filter(|(key, _)| &**key != LOCK_KEY.as_bytes())
+                .map(|(key, value)| Ok((key, self.plain(&value).ok_or(DecryptError)?)))
+                .collect::<Result<Vec<(sled::IVec, Vec<u8>)>, DecryptError>>())
+            .collect::<Result<Vec<_>, DecryptError>>()?;
+        let tags = self.tag_records();
+        if tags.len() != self.tags.len() {
+            return Err(DecryptError);
+        }
+        let (lock, key) = match passphrase {
+            Some(passphrase) => {
+                let (lock, key) = Lock::new(passphrase);
+                (Some(lock), Some(key))
+            },
+            None => (None, None)
+        };
+        let trees = (&self.cycles, &self.trash, &self.archive, &self.conflicts, &self.attachments, &self.blobs, &self.settings, &self.tags);
+        let result: TransactionResult<()> = trees.transaction(|(cycles, trash, archive, conflicts, attachments, blobs, settings, tags_tree)| {
+            for (tree, records) in [cycles, trash, archive, conflicts, attachments, blobs, settings].iter().zip(&plain) {
+                for (id, value) in records {
+                    tree.insert(id.clone(), seal(key.as_ref(), value))?;
+                }
+            }
+            for (old, name, style) in &tags {
+                tags_tree.remove(old.clone())?;
+                tags_tree.insert(Uuid::new_v4().as_bytes(), seal(key.as_ref(), &or_panic!(Res serde_cbor::to_vec(&(name, style)))))?;
+            }
+            match &lock {
+                Some(lock) => settings.insert(LOCK_KEY, or_panic!(Res serde_cbor::to_vec(lock)))?,
+                None => settings.remove(LOCK_KEY)?
+            };
+            Ok(())
+        });
+        or_panic!(Res result);
+        self.key = key;
+        Ok(())
+    }
+
+    fn encode<T: Serialize>(&self, value: &T) -> Vec<u8> {
+        seal(self.key.as_ref(), &or_panic!(Res serde_cbor::to_vec(value)))
+    }
+
+    fn decode<T: serde::de::DeserializeOwned>(&self, value: &[u8]) -> Option<T> {
+        decode(self.key.as_ref(), value)
+    }
+
+    /// The CBOR behind a stored value, `None` if it can't be decrypted.
+    fn plain(&self, value: &[u8]) -> Option<Vec<u8>> {
+        match self.key.as_ref() {
+            Some(key) => key.open(value).ok(),
+            None => Some(value.to_vec())
+        }
+    }
+
+    /// Loads the live cycles from `store` and writes every later change back to it.
+    /// The trash, archive and settings stay in sled. Cycles removed from the file
+    /// by hand since it was last written go to the trash, cycles only in sled are
+    /// kept and written to the file. Nothing is attached while the file has never
+    /// been written and sled still holds cycles, as they would be hidden until
+    /// migrated. Returns whether the store was attached.
+    pub fn attach(&mut self, store: FileStore) -> bool {
+        let entries = storage::Storage::entries(&store);
+        let written: Option<HashSet<Uuid>> = self.setting(FILE_IDS_KEY);
+        if entries.is_empty() && written.is_none() && !self.cycles.is_empty() {
+            return false;
+        }
+        let in_file: HashSet<Uuid> = entries.iter().map(|(id, _)| *id).collect();
+        let written = written.unwrap_or_default();
+        let mut changed = vec![];
+        for (id, description) in &entries {
+            if self.get(*id).as_ref() != Some(description) {
+                self.insert(*id, description);
+                changed.push(*id);
+            }
+        }
+        self.touch(&changed, false);
+        self.mirror = Some(store);
+        let deleted: Vec<Uuid> = self.entries()
+            .map(|(id, _)| id)
+            .filter(|id| !in_file.contains(id) && written.contains(id))
+            .collect();
+        if !deleted.is_empty() {
+            self.remove_many(&deleted);
+        } else if self.entries().any(|(id, _)| !in_file.contains(&id)) || written != in_file {
+            self.persist();
+        }
+        true
+    }
+
+    fn persist(&self) {
+        if let Some(store) = self.mirror.as_ref() {
+            let entries: Vec<(Uuid, Description)> = self.entries().collect();
+            let ids: HashSet<Uuid> = entries.iter().map(|(id, _)| *id).collect();
+            store.replace_all(entries);
+            self.set_setting(FILE_IDS_KEY, &ids);
+        }
+    }
+
+    fn insert(&self, id: Uuid, description: &Description) {
+        let data = self.encode(description);
+        or_panic!(Res self.cycles.insert(id.as_bytes(), data));
     }
 
     pub fn append(&self, description: Description) -> Uuid {
@@ -26,40 +365,768 @@ impl Database {
     }
 
     pub fn update(&self, id: Uuid, description: Description) {
-        let data = or_panic!(Res serde_cbor::to_vec(&description));
-        or_panic!(Res self.cycles.insert(id.as_bytes(), data));
+        self.insert(id, &description);
+        self.touch(&[id], false);
+        self.persist();
     }
 
+    /// A live cycle, `None` if it is missing or damaged. `check` reports damaged ones.
+    pub fn get(&self, id: Uuid) -> Option<Description> {
+        or_panic!(Res self.cycles.get(id.as_bytes()))
+            .and_then(|value| self.decode(&value))
+    }
+
+    /// Moves a cycle into the trash, where it is kept for `TRASH_DAYS`.
     pub fn remove(&self, id: Uuid) {
-        or_panic!(Res self.cycles.remove(id.as_bytes()));
+        self.remove_many(&[id]);
+    }
+
+    /// Moves several cycles into the trash at once, returning the ones that were live.
+    pub fn remove_many(&self, ids: &[Uuid]) -> Vec<(Uuid, Description)> {
+        let removed = self.move_to_trash(ids);
+        self.touch(&ids_of(&removed), true);
+        self.persist();
+        removed
+    }
+
+    /// Damaged cycles stay where they are for `repair` to quarantine.
+    fn move_to_trash(&self, ids: &[Uuid]) -> Vec<(Uuid, Description)> {
+        let deleted = Utc::now().date().naive_local();
+        let result: TransactionResult<Vec<(Uuid, Description)>> = (&self.cycles, &self.trash).transaction(|(cycles, trash)| {
+            let mut removed = vec![];
+            for id in ids {
+                let value = cycles.get(id.as_bytes())?;
+                if let Some(description) = value.and_then(|value| self.decode::<Description>(&value)) {
+                    cycles.remove(id.as_bytes())?;
+                    let data = self.encode(&Trashed { deleted, description: description.clone() });
+                    trash.insert(id.as_bytes(), data)?;
+                    removed.push((*id, description));
+                }
+            }
+            Ok(removed)
+        });
+        or_panic!(Res result)
     }
 
+    /// Applies `change` to each of `ids` in a single batch, returning the
+    /// previous value of every cycle it changed.
+    pub fn update_many<F: Fn(&mut Description) -> bool>(&self, ids: &[Uuid], change: F) -> Vec<(Uuid, Description)> {
+        let mut batch = sled::Batch::default();
+        let mut previous = vec![];
+        for id in ids {
+            if let Some(mut description) = self.get(*id) {
+                let before = description.clone();
+                if change(&mut description) {
+                    batch.insert(id.as_bytes(), self.encode(&description));
+                    previous.push((*id, before));
+                }
+            }
+        }
+        or_panic!(Res self.cycles.apply_batch(batch));
+        self.touch(&ids_of(&previous), false);
+        self.persist();
+        previous
+    }
+
+    /// Moves cycles out of the main list without deleting them. Damaged
+    /// ones stay in the list.
+    pub fn archive(&self, ids: &[Uuid]) -> Vec<(Uuid, Description)> {
+        let result: TransactionResult<Vec<(Uuid, Description)>> = (&self.cycles, &self.archive).transaction(|(cycles, archive)| {
+            let mut archived = vec![];
+            for id in ids {
+                let value = match cycles.get(id.as_bytes())? {
+                    Some(value) => value,
+                    None => continue
+                };
+                if let Some(description) = self.decode(&value) {
+                    cycles.remove(id.as_bytes())?;
+                    archive.insert(id.as_bytes(), value)?;
+                    archived.push((*id, description));
+                }
+            }
+            Ok(archived)
+        });
+        let archived = or_panic!(Res result);
+        self.track(&ids_of(&archived), false, true);
+        self.persist();
+        archived
+    }
+
+    /// Moves a cycle back out of the archive, returning it if it was there
+    /// and could be read.
+    pub fn unarchive(&self, id: Uuid) -> Option<Description> {
+        let result: TransactionResult<Option<Description>> = (&self.cycles, &self.archive).transaction(|(cycles, archive)| {
+            let value = match archive.get(id.as_bytes())? {
+                Some(value) => value,
+                None => return Ok(None)
+            };
+            let description = self.decode(&value);
+            if description.is_some() {
+                archive.remove(id.as_bytes())?;
+                cycles.insert(id.as_bytes(), value)?;
+            }
+            Ok(description)
+        });
+        let unarchived = or_panic!(Res result);
+        if unarchived.is_some() {
+            self.touch(&[id], false);
+        }
+        self.persist();
+        unarchived
+    }
+
+    pub fn get_archived(&self, id: Uuid) -> Option<Description> {
+        or_panic!(Res self.archive.get(id.as_bytes()))
+            .and_then(|value| self.decode(&value))
+    }
+
+    pub fn archived(&self) -> impl Iterator<Item=(Uuid, Description)> {
+        let cipher = self.key.clone();
+        self.archive.iter().flatten().filter_map(move |(key, value)| {
+            Some((
+                Uuid::from_slice(&key).ok()?,
+                decode(cipher.as_ref(), &value)?
+            ))
+        })
+    }
+
+    /// Removes a cycle for good, whether it is live or in the trash.
+    pub fn purge(&self, id: Uuid) {
+        let result: TransactionResult<()> = (&self.cycles, &self.trash).transaction(|(cycles, trash)| {
+            cycles.remove(id.as_bytes())?;
+            trash.remove(id.as_bytes())?;
+            Ok(())
+        });
+        or_panic!(Res result);
+        self.remove_attachments_of(&[id]);
+        self.touch(&[id], true);
+        self.persist();
+    }
+
+    /// Moves a cycle out of the trash, returning it if it was there and
+    /// could be read.
+    pub fn restore(&self, id: Uuid) -> Option<Description> {
+        let result: TransactionResult<Option<Description>> = (&self.cycles, &self.trash).transaction(|(cycles, trash)| {
+            let trashed = trash.get(id.as_bytes())?.and_then(|value| self.decode::<Trashed>(&value));
+            match trashed {
+                Some(trashed) => {
+                    trash.remove(id.as_bytes())?;
+                    cycles.insert(id.as_bytes(), self.encode(&trashed.description))?;
+                    Ok(Some(trashed.description))
+                },
+                None => Ok(None)
+            }
+        });
+        let restored = or_panic!(Res result);
+        if restored.is_some() {
+            self.touch(&[id], false);
+        }
+        self.persist();
+        restored
+    }
+
+    pub fn trash_insert(&self, id: Uuid, trashed: Trashed) {
+        let data = self.encode(&trashed);
+        or_panic!(Res self.trash.insert(id.as_bytes(), data));
+    }
+
+    /// Everything in the trash. Damaged records are skipped.
+    pub fn trash(&self) -> impl Iterator<Item=(Uuid, Trashed)> {
+        let cipher = self.key.clone();
+        self.trash.iter().flatten().filter_map(move |(key, value)| {
+            Some((
+                Uuid::from_slice(&key).ok()?,
+                decode(cipher.as_ref(), &value)?
+            ))
+        })
+    }
+
+    /// Drops everything that has been in the trash for longer than `TRASH_DAYS`.
+    pub fn purge_expired(&self, today: NaiveDate) -> usize {
+        let expired: Vec<Uuid> = self.trash()
+            .filter(|(_, trashed)| today - trashed.deleted > Duration::days(TRASH_DAYS))
+            .map(|(id, _)| id)
+            .collect();
+        for id in &expired {
+            or_panic!(Res self.trash.remove(id.as_bytes()));
+        }
+        self.remove_attachments_of(&expired);
+        expired.len()
+    }
+
+    /// Stores a copy of `data` with cycle `cycle`, optionally for the period starting on `period`.
+    pub fn add_attachment(&self, cycle: Uuid, name: &str, data: &[u8], period: Option<NaiveDate>) -> Attachment {
+        let attachment = Attachment::new(cycle, name, data.len() as u64, Utc::now().date().naive_local(), period);
+        self.insert_attachments(&[(attachment.clone(), Blob(data.to_vec()))]);
+        attachment
+    }
+
+    /// Adds attachments that aren't there yet, e.g. from an export.
+    pub fn insert_attachments(&self, entries: &[(Attachment, Blob)]) {
+        for (attachment, blob) in entries {
+            let id = attachment.id.as_bytes();
+            if or_panic!(Res self.attachments.contains_key(id)) {
+                continue;
+            }
+            or_panic!(Res self.blobs.insert(id, seal(self.key.as_ref(), &blob.0)));
+            or_panic!(Res self.attachments.insert(id, self.encode(attachment)));
+        }
+    }
+
+    /// The attachments of a cycle, newest period first and those for the whole cycle last.
+    pub fn attachments(&self, cycle: Uuid) -> Vec<Attachment> {
+        let mut attachments: Vec<Attachment> = self.all_attachments()
+            .filter(|attachment| attachment.cycle == cycle)
+            .collect();
+        attachments.sort_by(|a, b| b.period.cmp(&a.period).then_with(|| a.name.cmp(&b.name)));
+        attachments
+    }
+
+    fn all_attachments(&self) -> impl Iterator<Item=Attachment> + '_ {
+        self.attachments.iter().flatten()
+            .filter_map(move |(_, value)| self.decode(&value))
+    }
+
+    pub fn attachment_data(&self, id: Uuid) -> Option<Vec<u8>> {
+        or_panic!(Res self.blobs.get(id.as_bytes())).and_then(|value| self.plain(&value))
+    }
+
+    pub fn remove_attachment(&self, id: Uuid) {
+        let result: TransactionResult<()> = (&self.attachments, &self.blobs).transaction(|(attachments, blobs)| {
+            attachments.remove(id.as_bytes())?;
+            blobs.remove(id.as_bytes())?;
+            Ok(())
+        });
+        or_panic!(Res result);
+    }
+
+    fn remove_attachments_of(&self, cycles: &[Uuid]) {
+        let gone: Vec<Uuid> = self.all_attachments()
+            .filter(|attachment| cycles.contains(&attachment.cycle))
+            .map(|attachment| attachment.id)
+            .collect();
+        for id in gone {
+            self.remove_attachment(id);
+        }
+    }
+
+    /// A stored setting, sealed like the cycles. `None` while locked.
+    pub fn setting<T: serde::de::DeserializeOwned>(&self, key: &str) -> Option<T> {
+        or_panic!(Res self.settings.get(key))
+            .and_then(|value| self.decode(&value))
+    }
+
+    pub fn set_setting<T: Serialize>(&self, key: &str, value: &T) {
+        or_panic!(Res self.settings.insert(key, self.encode(value)));
+    }
+
+    /// The tag registry as sled key, name and style. Each entry holds its
+    /// name sealed next to the style, so an encrypted database doesn't list
+    /// tag names in clear. Entries from before that are keyed by name.
+    fn tag_records(&self) -> Vec<(sled::IVec, String, TagStyle)> {
+        self.tags.iter().flatten().filter_map(|(key, value)| {
+            match self.decode::<(String, TagStyle)>(&value) {
+                Some((name, style)) => Some((key, name, style)),
+                None => Some((
+                    key.clone(),
+                    String::from_utf8(key.to_vec()).ok()?,
+                    serde_cbor::from_slice(&value).ok()?
+                ))
+            }
+        }).collect()
+    }
+
+    fn tag_record(&self, name: &str) -> Option<(sled::IVec, TagStyle)> {
+        self.tag_records().into_iter()
+            .find(|(_, tag, _)| tag == name)
+            .map(|(key, _, style)| (key, style))
+    }
+
+    pub fn tag_styles(&self) -> HashMap<String, TagStyle> {
+        self.tag_records().into_iter()
+            .map(|(_, name, style)| (name, style))
+            .collect()
+    }
+
+    pub fn set_tag_style(&self, name: &str, style: &TagStyle) {
+        let key = match self.tag_record(name) {
+            Some((key, _)) => key,
+            None => Uuid::new_v4().as_bytes().into()
+        };
+        or_panic!(Res self.tags.insert(key, self.encode(&(name, style))));
+    }
+
+    /// How many live cycles use each tag.
+    pub fn tag_counts(&self) -> BTreeMap<String, usize> {
+        let mut counts = BTreeMap::new();
+        for (_, description) in self.entries() {
+            for tag in description.tags {
+                *counts.entry(tag).or_insert(0) += 1;
+            }
+        }
+        counts
+    }
+
+    /// Renames `from` to the normalized form of `to`, merging it into a
+    /// tag of that name if there is one. A blank name changes nothing.
+    /// Returns the cycles that changed.
+    pub fn rename_tag(&self, from: &str, to: &str) -> Vec<Uuid> {
+        let to = match tag_list::normalize(to) {
+            Some(to) => to,
+            None => return vec![]
+        };
+        let to = to.as_str();
+        let changed = self.change_tags(|description| description.rename_tag(from, to));
+        if let Some((key, style)) = self.tag_record(from) {
+            or_panic!(Res self.tags.remove(key));
+            if self.tag_record(to).is_none() {
+                self.set_tag_style(to, &style);
+            }
+        }
+        changed
+    }
+
+    /// Removes a tag from every cycle and from the registry.
+    pub fn delete_tag(&self, name: &str) -> Vec<Uuid> {
+        let changed = self.change_tags(|description| description.remove_tag(name));
+        if let Some((key, _)) = self.tag_record(name) {
+            or_panic!(Res self.tags.remove(key));
+        }
+        changed
+    }
+
+    fn change_tags<F: Fn(&mut Description) -> bool>(&self, change: F) -> Vec<Uuid> {
+        let mut batch = sled::Batch::default();
+        let mut changed = vec![];
+        for (id, mut description) in self.entries() {
+            if change(&mut description) {
+                batch.insert(id.as_bytes(), self.encode(&description));
+                changed.push(id);
+            }
+        }
+        or_panic!(Res self.cycles.apply_batch(batch));
+        self.touch(&changed, false);
+        self.persist();
+        changed
+    }
+
+    /// Swaps everything for the contents of `export` in one transaction, so a
+    /// failure leaves the database as it was. Every restored cycle gets a
+    /// fresh change, so sync doesn't take it back to an older tombstone.
+    pub fn replace(&self, export: &Export) {
+        let stale: Vec<Vec<sled::IVec>> = [&self.cycles, &self.trash, &self.archive, &self.attachments, &self.blobs].iter()
+            .map(|tree| tree.iter().keys().flatten().collect())
+            .collect();
+        let (modified, device) = (Utc::now().timestamp_millis(), self.device());
+        let stamp = |deleted, archived| or_panic!(Res serde_cbor::to_vec(&Change { modified, device, deleted, archived }));
+        let live: Vec<(Uuid, Vec<u8>)> = export.cycles.iter().map(|(id, value)| (*id, self.encode(value))).collect();
+        let trashed: Vec<(Uuid, Vec<u8>)> = export.trash.iter().map(|(id, value)| (*id, self.encode(value))).collect();
+        let archived: Vec<(Uuid, Vec<u8>)> = export.archive.iter().map(|(id, value)| (*id, self.encode(value))).collect();
+        let attached: Vec<(Uuid, Vec<u8>, Vec<u8>)> = export.attachments.iter()
+            .map(|(attachment, blob)| (attachment.id, self.encode(attachment), seal(self.key.as_ref(), &blob.0)))
+            .collect();
+        let stamps: Vec<(Uuid, Vec<u8>)> = ids_of(&live).into_iter().map(|id| (id, stamp(false, false)))
+            .chain(ids_of(&trashed).into_iter().map(|id| (id, stamp(true, false))))
+            .chain(ids_of(&archived).into_iter().map(|id| (id, stamp(false, true))))
+            .collect();
+        let trees = (&self.cycles, &self.trash, &self.archive, &self.attachments, &self.blobs, &self.changes);
+        let result: TransactionResult<()> = trees.transaction(|(cycles, trash, archive, attachments, blobs, changes)| {
+            for (tree, keys) in [cycles, trash, archive, attachments, blobs].iter().zip(&stale) {
+                for key in keys {
+                    tree.remove(key)?;
+                }
+            }
+            for (tree, entries) in [(cycles, &live), (trash, &trashed), (archive, &archived), (changes, &stamps)].iter() {
+                for (id, value) in entries.iter() {
+                    tree.insert(id.as_bytes(), value.as_slice())?;
+                }
+            }
+            for (id, attachment, blob) in &attached {
+                attachments.insert(id.as_bytes(), attachment.as_slice())?;
+                blobs.insert(id.as_bytes(), blob.as_slice())?;
+            }
+            Ok(())
+        });
+        or_panic!(Res result);
+        export.order.save(self);
+        // Backups from before custom fields hold no definitions, keep the current ones.
+        fields::merge_definitions(self, &export.fields);
+        self.persist();
+    }
+
+    fn archive_insert(&self, entries: &[(Uuid, Description)]) {
+        for (key, value) in entries {
+            or_panic!(Res self.archive.insert(key.as_bytes(), self.encode(value)));
+        }
+    }
+
+    /// This device's id in sync files, picked the first time it is needed.
+    pub fn device(&self) -> Uuid {
+        match self.setting(DEVICE_KEY) {
+            Some(device) => device,
+            None => {
+                let device = Uuid::new_v4();
+                self.set_setting(DEVICE_KEY, &device);
+                device
+            }
+        }
+    }
+
+    /// Records that `ids` changed on this device just now.
+    fn touch(&self, ids: &[Uuid], deleted: bool) {
+        self.track(ids, deleted, false);
+    }
+
+    fn track(&self, ids: &[Uuid], deleted: bool, archived: bool) {
+        if ids.is_empty() {
+            return;
+        }
+        let change = Change { modified: Utc::now().timestamp_millis(), device: self.device(), deleted, archived };
+        for id in ids {
+            self.set_change(*id, &change);
+        }
+    }
+
+    pub fn change(&self, id: Uuid) -> Option<Change> {
+        or_panic!(Res self.changes.get(id.as_bytes()))
+            .and_then(|value| serde_cbor::from_slice(&value).ok())
+    }
+
+    pub(crate) fn set_change(&self, id: Uuid, change: &Change) {
+        or_panic!(Res self.changes.insert(id.as_bytes(), or_panic!(Res serde_cbor::to_vec(change))));
+    }
+
+    /// The last change of every cycle, tombstones included.
+    pub fn changes(&self) -> impl Iterator<Item=(Uuid, Change)> {
+        self.changes.iter().flatten().filter_map(|(key, value)| {
+            Some((
+                Uuid::from_slice(&key).ok()?,
+                serde_cbor::from_slice(&value).ok()?
+            ))
+        })
+    }
+
+    /// Puts another device's version of a cycle in place without counting it
+    /// as a local change. `None` moves the cycle into the trash.
+    pub(crate) fn apply_synced(&self, id: Uuid, description: Option<&Description>, archived: bool) {
+        match description {
+            Some(description) if archived => {
+                let data = self.encode(description);
+                let result: TransactionResult<()> = (&self.cycles, &self.trash, &self.archive).transaction(|(cycles, trash, archive)| {
+                    cycles.remove(id.as_bytes())?;
+                    trash.remove(id.as_bytes())?;
+                    archive.insert(id.as_bytes(), data.clone())?;
+                    Ok(())
+                });
+                or_panic!(Res result);
+            },
+            Some(description) => {
+                or_panic!(Res self.trash.remove(id.as_bytes()));
+                or_panic!(Res self.archive.remove(id.as_bytes()));
+                self.insert(id, description);
+            },
+            None => {
+                self.move_to_trash(&[id]);
+            }
+        }
+        self.persist();
+    }
+
+    pub fn conflicts(&self) -> impl Iterator<Item=Conflict> {
+        let cipher = self.key.clone();
+        self.conflicts.iter().flatten().filter_map(move |(_, value)| decode(cipher.as_ref(), &value))
+    }
+
+    pub(crate) fn add_conflict(&self, conflict: &Conflict) {
+        or_panic!(Res self.conflicts.insert(conflict.id.as_bytes(), self.encode(conflict)));
+    }
+
+    pub(crate) fn remove_conflict(&self, id: Uuid) -> Option<Conflict> {
+        or_panic!(Res self.conflicts.remove(id.as_bytes()))
+            .and_then(|value| self.decode(&value))
+    }
+
+    /// Settles a sync conflict. Taking the other version makes it a new local
+    /// change, so it wins on the other device too. Returns the live version.
+    pub fn resolve_conflict(&self, id: Uuid, take_other: bool) -> Option<Description> {
+        let conflict = self.remove_conflict(id)?;
+        if !take_other {
+            return self.get(id);
+        }
+        match conflict.other {
+            Some(description) => {
+                self.update(id, description.clone());
+                Some(description)
+            },
+            None => {
+                self.remove(id);
+                None
+            }
+        }
+    }
+
+    /// Every live cycle. Damaged records are skipped, `check` reports them.
     pub fn entries(&self) -> impl Iterator<Item=(Uuid, Description)> {
-        self.cycles.iter().map(|x|x.map(|(key, value)| {
-            (
-                or_panic!(Res Uuid::from_slice(&key)),
-                or_panic!(Res serde_cbor::from_slice(&value))
-            )
-        })).flatten()
+        let cipher = self.key.clone();
+        self.cycles.iter().flatten().filter_map(move |(key, value)| {
+            Some((
+                Uuid::from_slice(&key).ok()?,
+                decode(cipher.as_ref(), &value)?
+            ))
+        })
     }
 
-    pub fn export_to_writer<W: std::io::Write>(&self, w: W) {
-        let entries: Vec<(Uuid, Description)> = self.entries().collect();
-        or_panic!(Res serde_cbor::to_writer(w, &entries));
+    /// Scans the `cycles` tree for records that can't be used.
+    pub fn check(&self) -> Report {
+        let mut report = Report::default();
+        for (key, value) in self.cycles.iter().flatten() {
+            report.checked += 1;
+            let issue = match self.plain(&value) {
+                Some(value) => check::inspect(&key, &value),
+                None => Some(Issue { key: key.to_vec(), label: None, problems: vec![Problem::Undecryptable] })
+            };
+            if let Some(issue) = issue {
+                report.issues.push(issue);
+            }
+        }
+        report
     }
 
-    pub fn import_from_reader<R: std::io::Read>(&self, r: R) -> Vec<(Uuid, Description)> {
-        let data = or_panic!(Res serde_cbor::from_reader::<Vec<(Uuid, Description)>, R>(r));
-        for (key, value) in &data {
-            self.update(key.clone(), value.clone());
+    /// Moves every record `check` complains about into the `quarantine` tree.
+    pub fn repair(&self) -> Report {
+        let report = self.check();
+        for issue in &report.issues {
+            let result: TransactionResult<()> = (&self.cycles, &self.quarantine).transaction(|(cycles, quarantine)| {
+                if let Some(value) = cycles.remove(issue.key.as_slice())? {
+                    let record = Quarantined {
+                        key: issue.key.clone(),
+                        value: value.to_vec(),
+                        problems: issue.problems.clone()
+                    };
+                    quarantine.insert(issue.key.as_slice(), or_panic!(Res serde_cbor::to_vec(&record)))?;
+                }
+                Ok(())
+            });
+            or_panic!(Res result);
         }
+        self.persist();
+        report
+    }
+
+    pub fn quarantined(&self) -> impl Iterator<Item=Quarantined> {
+        self.quarantine.iter().flatten()
+            .map(|(_, value)| or_panic!(Res serde_cbor::from_slice(&value)))
+    }
+
+    /// Everything a full export or backup holds.
+    pub fn bundle(&self, include_trash: bool) -> Export {
+        Export {
+            cycles: self.entries().collect(),
+            trash: if include_trash { self.trash().collect() } else { vec![] },
+            archive: self.archived().collect(),
+            order: Ordering::load(self),
+            fields: fields::definitions(self),
+            attachments: self.all_attachments()
+                .filter_map(|attachment| {
+                    let data = self.attachment_data(attachment.id)?;
+                    Some((attachment, Blob(data)))
+                })
+                .collect()
+        }
+    }
+
+    /// Writes a full export, sealed when the database is encrypted.
+    pub fn export_to_writer<W: std::io::Write>(&self, w: W, include_trash: bool) {
+        self.write_export(w, &self.bundle(include_trash), false);
+    }
+
+    /// Writes a full export in clear, even from an encrypted database.
+    pub fn export_plain_to_writer<W: std::io::Write>(&self, w: W, include_trash: bool) {
+        self.write_export(w, &self.bundle(include_trash), true);
+    }
+
+    /// Writes a full export sealed with its own `password`, readable with
+    /// `read_protected_export` whether or not the database is encrypted.
+    pub fn export_protected_to_writer<W: std::io::Write>(&self, w: W, include_trash: bool, password: &str) {
+        let salt = crypto::random_bytes(crypto::SALT_LEN);
+        let key = Key::derive(password, &salt);
+        let sealed = Sealed::new(&key, salt, &self.bundle(include_trash));
+        or_panic!(Res serde_cbor::to_writer(w, &sealed));
+    }
+
+    /// Writes just the given cycles as a plain list, sealed when the database is encrypted.
+    pub fn export_selected_to_writer<W: std::io::Write>(&self, w: W, ids: &[Uuid]) {
+        let entries: Vec<(Uuid, Description)> = ids.iter()
+            .filter_map(|id| Some((*id, self.get(*id)?)))
+            .collect();
+        self.write_export(w, &entries, false);
+    }
+
+    fn write_export<W: std::io::Write, T: Serialize>(&self, w: W, value: &T, plaintext: bool) {
+        match (self.key.as_ref(), self.lock()) {
+            (Some(key), Some(lock)) if !plaintext => {
+                or_panic!(Res serde_cbor::to_writer(w, &Sealed::new(key, lock.salt, value)));
+            },
+            _ => or_panic!(Res serde_cbor::to_writer(w, value))
+        }
+    }
+
+    pub fn import_from_reader<R: std::io::Read>(&self, r: R) -> Export {
+        let data = or_panic!(Res read_export(r, self.key.as_ref()));
+        self.merge(&data);
         data
     }
+
+    /// Adds the contents of `export`. An imported cycle takes the place of
+    /// the one with its id, whether that was live, in the trash or archived.
+    /// Returns what was replaced, for `unmerge`.
+    pub fn merge(&self, export: &Export) -> Merged {
+        let ids: Vec<Uuid> = ids_of(&export.cycles).into_iter()
+            .chain(ids_of(&export.trash))
+            .chain(ids_of(&export.archive))
+            .collect();
+        let merged = Merged {
+            cycles: ids.iter().map(|id| (*id, self.slot(*id))).collect(),
+            order: Ordering::load(self),
+            fields: fields::definitions(self),
+            attachments: export.attachments.iter()
+                .map(|(attachment, _)| attachment.id)
+                .filter(|id| !or_panic!(Res self.attachments.contains_key(id.as_bytes())))
+                .collect()
+        };
+        let result: TransactionResult<()> = (&self.cycles, &self.trash, &self.archive).transaction(|(cycles, trash, archive)| {
+            for id in &ids {
+                cycles.remove(id.as_bytes())?;
+                trash.remove(id.as_bytes())?;
+                archive.remove(id.as_bytes())?;
+            }
+            for (id, description) in &export.cycles {
+                cycles.insert(id.as_bytes(), self.encode(description))?;
+            }
+            for (id, trashed) in &export.trash {
+                trash.insert(id.as_bytes(), self.encode(trashed))?;
+            }
+            for (id, description) in &export.archive {
+                archive.insert(id.as_bytes(), self.encode(description))?;
+            }
+            Ok(())
+        });
+        or_panic!(Res result);
+        let mut order = Ordering::load(self);
+        order.merge(&export.order);
+        order.save(self);
+        fields::merge_definitions(self, &export.fields);
+        self.insert_attachments(&export.attachments);
+        self.touch(&ids_of(&export.cycles), false);
+        self.touch(&ids_of(&export.trash), true);
+        self.track(&ids_of(&export.archive), false, true);
+        self.persist();
+        merged
+    }
+
+    /// Reverts a `merge`: cycles go back where they were, those that weren't
+    /// there before are purged, and the order, field definitions and
+    /// attachments are put back too.
+    pub fn unmerge(&self, merged: &Merged) {
+        let result: TransactionResult<()> = (&self.cycles, &self.trash, &self.archive).transaction(|(cycles, trash, archive)| {
+            for (id, slot) in &merged.cycles {
+                cycles.remove(id.as_bytes())?;
+                trash.remove(id.as_bytes())?;
+                archive.remove(id.as_bytes())?;
+                match slot {
+                    Slot::Missing => None,
+                    Slot::Live(description) => cycles.insert(id.as_bytes(), self.encode(description))?,
+                    Slot::Trashed(trashed) => trash.insert(id.as_bytes(), self.encode(trashed))?,
+                    Slot::Archived(description) => archive.insert(id.as_bytes(), self.encode(description))?
+                };
+            }
+            Ok(())
+        });
+        or_panic!(Res result);
+        for id in &merged.attachments {
+            self.remove_attachment(*id);
+        }
+        merged.order.save(self);
+        fields::set_definitions(self, &merged.fields);
+        let (mut live, mut deleted, mut archived) = (vec![], vec![], vec![]);
+        for (id, slot) in &merged.cycles {
+            match slot {
+                Slot::Live(_) => live.push(*id),
+                Slot::Missing | Slot::Trashed(_) => deleted.push(*id),
+                Slot::Archived(_) => archived.push(*id)
+            }
+        }
+        self.touch(&live, false);
+        self.touch(&deleted, true);
+        self.track(&archived, false, true);
+        self.persist();
+    }
+
+    fn slot(&self, id: Uuid) -> Slot {
+        if let Some(description) = self.get(id) {
+            return Slot::Live(description);
+        }
+        let trashed = or_panic!(Res self.trash.get(id.as_bytes()))
+            .and_then(|value| self.decode(&value));
+        if let Some(trashed) = trashed {
+            return Slot::Trashed(trashed);
+        }
+        match self.get_archived(id) {
+            Some(description) => Slot::Archived(description),
+            None => Slot::Missing
+        }
+    }
+}
+
+fn ids_of<T>(entries: &[(Uuid, T)]) -> Vec<Uuid> {
+    entries.iter().map(|(id, _)| *id).collect()
+}
+
+/// Reads any export file, opening sealed ones with `key`.
+pub fn read_export<R: std::io::Read>(r: R, key: Option<&Key>) -> Result<Export, ExportError> {
+    match serde_cbor::from_reader::<ExportFormat, R>(r).map_err(|_| ExportError::Damaged)? {
+        ExportFormat::Sealed(sealed) => sealed.open(key.ok_or(ExportError::Locked)?),
+        format => plain(format)
+    }
 }
 
-fn file_path() -> PathBuf {
+/// Reads an export file protected with `password`. Plain exports are read as is.
+pub fn read_protected_export<R: std::io::Read>(r: R, password: &str) -> Result<Export, ExportError> {
+    match serde_cbor::from_reader::<ExportFormat, R>(r).map_err(|_| ExportError::Damaged)? {
+        ExportFormat::Sealed(sealed) => sealed.open(&Key::derive(password, &sealed.salt)),
+        format => plain(format)
+    }
+}
+
+fn plain(format: ExportFormat) -> Result<Export, ExportError> {
+    match format {
+        ExportFormat::Sealed(_) => Err(ExportError::Locked),
+        ExportFormat::Bundle(export) => Ok(export),
+        ExportFormat::Entries(cycles) => Ok(Export { cycles, ..Export::default() })
+    }
+}
+
+pub fn data_dir() -> PathBuf {
     env::var("HOME")
         .map(PathBuf::from)
-        .map(|mut x|{x.push(".local/share/cycles/database");x})
+        .map(|mut x|{x.push(".local/share/cycles");x})
         .expect("HOME Environment Variable not set")
 }
+
+pub(crate) fn file_path() -> PathBuf {
+    data_dir().join("database")
+}
+
+fn seal(key: Option<&Key>, data: &[u8]) -> Vec<u8> {
+    match key {
+        Some(key) => key.seal(data),
+        None => data.to_vec()
+    }
+}
+
+fn decode<T: serde::de::DeserializeOwned>(key: Option<&Key>, value: &[u8]) -> Option<T> {
+    match key {
+        Some(key) => serde_cbor::from_slice(&key.open(value).ok()?).ok(),
+        None => serde_cbor::from_slice(value).ok()
+    }
+}
@@ -0,0 +1,401 @@
+use uuid::Uuid;
+use serde::{ Serialize, Deserialize };
+
+use std::{env, fmt};
+use std::collections::{BTreeMap, HashSet};
+
+use crate::cache::Database;
+use crate::description::Description;
+use crate::ical;
+use crate::sync::{Conflict, Summary};
+
+const URL_VAR: &str = "CYCLES_CALDAV_URL";
+const USER_VAR: &str = "CYCLES_CALDAV_USER";
+const PASSWORD_VAR: &str = "CYCLES_CALDAV_PASSWORD";
+
+const CALENDAR_QUERY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:prop>
+    <d:getetag/>
+    <c:calendar-data/>
+  </d:prop>
+  <c:filter>
+    <c:comp-filter name="VCALENDAR"/>
+  </c:filter>
+</c:calendar-query>
+"#;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Request {
+    pub method: &'static str,
+    pub url: String,
+    pub headers: Vec<(&'static str, String)>,
+    pub body: String
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Response {
+    pub status: u16,
+    pub etag: Option<String>,
+    pub body: String
+}
+
+/// Sends requests to the server. Error statuses come back as responses,
+/// only failing to reach the server is an `Err`.
+pub trait Transport {
+    fn send(&self, request: Request) -> Result<Response, CalDavError>;
+}
+
+impl<T: Transport + ?Sized> Transport for &T {
+    fn send(&self, request: Request) -> Result<Response, CalDavError> {
+        (**self).send(request)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CalDavError {
+    /// Events are sent in clear, so an encrypted database doesn't sync.
+    Encrypted,
+    Transport(String),
+    Status(u16)
+}
+
+impl fmt::Display for CalDavError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CalDavError::Encrypted => write!(f, "CalDAV sync isn't available for an encrypted database"),
+            CalDavError::Transport(err) => write!(f, "Could not reach the CalDAV server: {}", err),
+            CalDavError::Status(status) => write!(f, "The CalDAV server answered with status {}", status)
+        }
+    }
+}
+
+/// The collection to mirror cycles into, set with `CYCLES_CALDAV_URL`,
+/// `CYCLES_CALDAV_USER` and `CYCLES_CALDAV_PASSWORD`. Use a calendar of
+/// its own, every event in it is read back as a cycle.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Account {
+    pub url: String,
+    pub username: Option<String>,
+    pub password: Option<String>
+}
+
+impl Account {
+
+    pub fn from_env() -> Option<Account> {
+        let url = env::var(URL_VAR).ok().filter(|url| !url.is_empty())?;
+        Some(Account {
+            url,
+            username: env::var(USER_VAR).ok(),
+            password: env::var(PASSWORD_VAR).ok()
+        })
+    }
+}
+
+/// One event in the collection.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Remote {
+    pub href: String,
+    /// `None` when the server didn't say.
+    pub etag: Option<String>,
+    pub id: Uuid,
+    pub description: Description
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Put {
+    /// With the new etag, if the server sent one.
+    Stored(Option<String>),
+    /// The `If-Match` or `If-None-Match` precondition failed, someone else
+    /// changed the event first.
+    Changed
+}
+
+/// Which version of an event a `put` may replace.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Replacing<'a> {
+    /// None, the event must not exist yet.
+    Nothing,
+    /// Only the version with this etag.
+    Version(&'a str),
+    /// Whatever is there, for events the server gave no etag for.
+    Anything
+}
+
+impl<'a> Replacing<'a> {
+
+    fn from_etag(etag: Option<&'a str>) -> Replacing<'a> {
+        etag.map_or(Replacing::Anything, Replacing::Version)
+    }
+}
+
+pub struct Collection<T: Transport> {
+    url: String,
+    transport: T
+}
+
+impl<T: Transport> Collection<T> {
+
+    pub fn new(url: &str, transport: T) -> Collection<T> {
+        let url = if url.ends_with('/') { url.to_string() } else { format!("{}/", url) };
+        Collection { url, transport }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn href(&self, id: Uuid) -> String {
+        format!("{}{}.ics", self.url, id)
+    }
+
+    /// Every event or task in the collection that reads as a cycle.
+    pub fn list(&self) -> Result<Vec<Remote>, CalDavError> {
+        let response = self.transport.send(Request {
+            method: "REPORT",
+            url: self.url.clone(),
+            headers: vec![
+                ("Depth", "1".to_string()),
+                ("Content-Type", "application/xml; charset=utf-8".to_string())
+            ],
+            body: CALENDAR_QUERY.to_string()
+        })?;
+        if response.status != 207 {
+            return Err(CalDavError::Status(response.status));
+        }
+        Ok(elements(&response.body, "response").into_iter().filter_map(|element| {
+            let href = self.resolve(&text(elements(element, "href").first()?));
+            let etag = elements(element, "getetag").first().map(|etag| text(etag)).filter(|etag| !etag.is_empty());
+            let (id, description) = ical::from_ical(&text(elements(element, "calendar-data").first()?))?;
+            Some(Remote { href, etag, id, description })
+        }).collect())
+    }
+
+    /// Listed hrefs may be relative to the server, requests need full URLs.
+    fn resolve(&self, href: &str) -> String {
+        if href.contains("://") {
+            return href.to_string();
+        }
+        match (href.starts_with('/'), self.url.find("://")) {
+            (true, Some(scheme)) => {
+                let host = self.url[scheme + 3..].find('/').map_or(self.url.len(), |at| scheme + 3 + at);
+                format!("{}{}", &self.url[..host], href)
+            },
+            _ => format!("{}{}", self.url, href)
+        }
+    }
+
+    /// Uploads a cycle to `href`, replacing only what `replacing` allows.
+    pub fn put(&self, href: &str, id: Uuid, description: &Description, replacing: Replacing) -> Result<Put, CalDavError> {
+        let mut headers = vec![("Content-Type", "text/calendar; charset=utf-8".to_string())];
+        match replacing {
+            Replacing::Nothing => headers.push(("If-None-Match", "*".to_string())),
+            Replacing::Version(etag) => headers.push(("If-Match", etag.to_string())),
+            Replacing::Anything => {}
+        }
+        let response = self.transport.send(Request {
+            method: "PUT",
+            url: href.to_string(),
+            headers,
+            body: ical::to_ical(id, description)
+        })?;
+        match response.status {
+            200..=299 => Ok(Put::Stored(response.etag)),
+            412 => Ok(Put::Changed),
+            status => Err(CalDavError::Status(status))
+        }
+    }
+
+    /// Deletes the event at `href` if it is still at `etag`, or whatever is
+    /// there without one. Returns `false` if it changed.
+    pub fn delete(&self, href: &str, etag: Option<&str>) -> Result<bool, CalDavError> {
+        let response = self.transport.send(Request {
+            method: "DELETE",
+            url: href.to_string(),
+            headers: etag.map(|etag| ("If-Match", etag.to_string())).into_iter().collect(),
+            body: String::new()
+        })?;
+        match response.status {
+            200..=299 | 404 => Ok(true),
+            412 => Ok(false),
+            status => Err(CalDavError::Status(status))
+        }
+    }
+}
+
+/// What the server held for a cycle the last time we synced it.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+struct Synced {
+    /// Where the event lives, empty for state saved before hrefs were kept.
+    #[serde(default)]
+    href: String,
+    /// `None` when the server didn't send one, the next upload then
+    /// replaces the event unconditionally.
+    etag: Option<String>,
+    /// The local change time it matched.
+    modified: i64
+}
+
+impl Synced {
+
+    fn etag(&self) -> Option<&str> {
+        self.etag.as_deref().filter(|etag| !etag.is_empty())
+    }
+
+    fn href<T: Transport>(&self, collection: &Collection<T>, id: Uuid) -> String {
+        if self.href.is_empty() { collection.href(id) } else { self.href.clone() }
+    }
+}
+
+/// Pulls events changed on the server and pushes cycles changed here.
+/// When both changed, the local version is kept and the server's lands in
+/// the conflict list.
+pub fn sync<T: Transport>(db: &Database, collection: &Collection<T>) -> Result<Summary, CalDavError> {
+    if db.is_encrypted() {
+        return Err(CalDavError::Encrypted);
+    }
+    let state_key = format!("caldav-{}", collection.url());
+    let mut state: BTreeMap<Uuid, Synced> = db.setting(&state_key).unwrap_or_default();
+    let modified = |id: Uuid| db.change(id).map_or(0, |change| change.modified);
+    let mut summary = Summary { devices: 1, ..Summary::default() };
+
+    let remote = collection.list()?;
+    let on_server: HashSet<Uuid> = remote.iter().map(|item| item.id).collect();
+    for item in remote {
+        // An upload the server sent no etag for takes the listed one, as
+        // if it were the server's answer.
+        if let Some(known) = state.get_mut(&item.id).filter(|known| known.etag().is_none()) {
+            known.etag = item.etag.clone();
+        }
+        let known = state.get(&item.id).cloned();
+        if item.etag.is_some() && known.as_ref().map_or(false, |known| known.etag == item.etag) {
+            continue;
+        }
+        let local = db.get(item.id);
+        let synced = |etag| Synced { href: item.href.clone(), etag, modified: modified(item.id) };
+        if local.as_ref() == Some(&item.description) {
+            state.insert(item.id, synced(item.etag.clone()));
+            continue;
+        }
+        let changed_here = match known.as_ref() {
+            Some(known) => modified(item.id) > known.modified,
+            None => local.is_some()
+        };
+        if !changed_here {
+            db.update(item.id, item.description.clone());
+            summary.changed.push(item.id);
+            state.insert(item.id, synced(item.etag.clone()));
+            continue;
+        }
+        db.add_conflict(&Conflict { id: item.id, kept: local.clone(), other: Some(item.description.clone()) });
+        summary.conflicts += 1;
+        let etag = match local {
+            Some(description) => {
+                let replacing = Replacing::from_etag(item.etag.as_deref());
+                match collection.put(&item.href, item.id, &description, replacing)? {
+                    Put::Stored(etag) => etag,
+                    Put::Changed => {
+                        summary.skipped += 1;
+                        continue;
+                    }
+                }
+            },
+            None => {
+                if collection.delete(&item.href, item.etag.as_deref())? {
+                    state.remove(&item.id);
+                } else {
+                    summary.skipped += 1;
+                }
+                continue;
+            }
+        };
+        state.insert(item.id, synced(etag));
+    }
+
+    // Gone from the server: delete here too, unless it changed here since.
+    let removed: Vec<(Uuid, Synced)> = state.iter()
+        .filter(|(id, _)| !on_server.contains(id))
+        .map(|(id, known)| (*id, known.clone()))
+        .collect();
+    for (id, known) in removed {
+        state.remove(&id);
+        if modified(id) <= known.modified && db.get(id).is_some() {
+            db.remove(id);
+            summary.changed.push(id);
+        }
+    }
+
+    let live: Vec<(Uuid, Description)> = db.entries().collect();
+    let live_ids: HashSet<Uuid> = live.iter().map(|(id, _)| *id).collect();
+    for (id, description) in live {
+        let (href, replacing) = match state.get(&id) {
+            Some(known) if modified(id) <= known.modified => continue,
+            Some(known) => (known.href(collection, id), Replacing::from_etag(known.etag())),
+            None => (collection.href(id), Replacing::Nothing)
+        };
+        match collection.put(&href, id, &description, replacing)? {
+            Put::Stored(etag) => {
+                state.insert(id, Synced { href, etag, modified: modified(id) });
+            },
+            Put::Changed => summary.skipped += 1
+        }
+    }
+
+    let deleted: Vec<(Uuid, Synced)> = state.iter()
+        .filter(|(id, known)| !live_ids.contains(id) && modified(**id) > known.modified)
+        .map(|(id, known)| (*id, known.clone()))
+        .collect();
+    for (id, known) in deleted {
+        if collection.delete(&known.href(collection, id), known.etag())? {
+            state.remove(&id);
+        } else {
+            summary.skipped += 1;
+        }
+    }
+
+    db.set_setting(&state_key, &state);
+    Ok(summary)
+}
+
+/// The contents of every element called `name`, whatever its namespace prefix.
+fn elements<'a>(xml: &'a str, name: &str) -> Vec<&'a str> {
+    let mut found = vec![];
+    let mut rest = xml;
+    while let Some(open) = rest.find('<') {
+        let close = match rest[open..].find('>') {
+            Some(close) => open + close,
+            None => break
+        };
+        let tag = &rest[open + 1..close];
+        let qualified = tag.split_whitespace().next().unwrap_or_default();
+        let local = qualified.rsplit(':').next().unwrap_or_default();
+        rest = &rest[close + 1..];
+        if local != name || tag.starts_with('/') || tag.ends_with('/') {
+            continue;
+        }
+        let end_tag = format!("</{}>", qualified);
+        match rest.find(&end_tag) {
+            Some(end) => {
+                found.push(&rest[..end]);
+                rest = &rest[end + end_tag.len()..];
+            },
+            None => break
+        }
+    }
+    found
+}
+
+/// The text inside an element, with CDATA and entities undone.
+fn text(content: &str) -> String {
+    let content = content.trim();
+    if let Some(data) = content.strip_prefix("<![CDATA[").and_then(|data| data.strip_suffix("]]>")) {
+        return data.to_string();
+    }
+    content.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#13;", "\r")
+        .replace("&#10;", "\n")
+        .replace("&amp;", "&")
+}
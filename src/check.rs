@@ -0,0 +1,106 @@
+use uuid::Uuid;
+use serde::{ Serialize, Deserialize };
+
+use std::fmt;
+
+use crate::Description;
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum Problem {
+    InvalidKey,
+    Undecodable(String),
+    Undecryptable,
+    InvalidMultiplier(i64),
+    EmptyLabel
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Problem::InvalidKey => write!(f, "key is not a valid UUID"),
+            Problem::Undecodable(err) => write!(f, "value could not be decoded: {}", err),
+            Problem::Undecryptable => write!(f, "value could not be decrypted"),
+            Problem::InvalidMultiplier(value) => write!(f, "interval multiplier {} is not positive", value),
+            Problem::EmptyLabel => write!(f, "label is empty")
+        }
+    }
+}
+
+/// A damaged record in the `cycles` tree.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Issue {
+    pub key: Vec<u8>,
+    pub label: Option<String>,
+    pub problems: Vec<Problem>
+}
+
+impl Issue {
+
+    pub fn name(&self) -> String {
+        match (&self.label, Uuid::from_slice(&self.key)) {
+            (Some(label), _) if !label.trim().is_empty() => label.clone(),
+            (_, Ok(id)) => id.to_string(),
+            (_, Err(_)) => self.key.iter().map(|byte| format!("{:02x}", byte)).collect()
+        }
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let problems: Vec<String> = self.problems.iter().map(|x| x.to_string()).collect();
+        write!(f, "{}: {}", self.name(), problems.join(", "))
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Report {
+    pub checked: usize,
+    pub issues: Vec<Issue>
+}
+
+impl Report {
+
+    pub fn is_clean(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    pub fn summary(&self) -> String {
+        format!("Checked {} cycles, found {} problems", self.checked, self.issues.len())
+    }
+}
+
+/// A record moved out of `cycles` by a repair, kept byte for byte.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Quarantined {
+    pub key: Vec<u8>,
+    pub value: Vec<u8>,
+    pub problems: Vec<Problem>
+}
+
+/// Checks one raw `cycles` record, returning `None` if it is healthy.
+pub fn inspect(key: &[u8], value: &[u8]) -> Option<Issue> {
+    let mut problems = vec![];
+    if Uuid::from_slice(key).is_err() {
+        problems.push(Problem::InvalidKey);
+    }
+    let label = match serde_cbor::from_slice::<Description>(value) {
+        Ok(description) => {
+            if description.interval_multiplier <= 0 {
+                problems.push(Problem::InvalidMultiplier(description.interval_multiplier));
+            }
+            if description.label.trim().is_empty() {
+                problems.push(Problem::EmptyLabel);
+            }
+            Some(description.label)
+        },
+        Err(err) => {
+            problems.push(Problem::Undecodable(err.to_string()));
+            None
+        }
+    };
+    if problems.is_empty() {
+        None
+    } else {
+        Some(Issue { key: key.to_vec(), label, problems })
+    }
+}
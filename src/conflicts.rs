@@ -0,0 +1,132 @@
+use uuid::Uuid;
+use gtk::prelude::*;
+use relm::{connect, Relm, Widget, Update};
+use relm_derive::Msg;
+
+use std::collections::HashMap;
+
+use crate::description::Description;
+use crate::sync::Conflict;
+
+#[derive(Msg)]
+pub enum Msg {
+    Keep(Uuid),
+    UseOther(Uuid)
+}
+
+pub struct Widgets {
+    container: gtk::Dialog,
+    conflicts_list: gtk::ListBox
+}
+
+/// Lists sync conflicts so the user can keep the merged version or take the other one.
+pub struct Conflicts {
+    model: Model,
+    widgets: Widgets,
+}
+
+pub struct Model {
+    relm: Relm<Conflicts>,
+    win: libhandy::ApplicationWindow,
+    entries: Vec<Conflict>,
+    rows: HashMap<Uuid, gtk::Box>
+}
+
+impl Update for Conflicts {
+    type Model = Model;
+    type ModelParam = (libhandy::ApplicationWindow, Vec<Conflict>);
+    type Msg = Msg;
+
+    fn model(relm: &Relm<Self>, (win, entries): (libhandy::ApplicationWindow, Vec<Conflict>)) -> Model {
+        Model {
+            relm: relm.clone(),
+            win, entries,
+            rows: Default::default()
+        }
+    }
+
+    fn update(&mut self, event: Msg) {
+        match event {
+            Msg::Keep(id) | Msg::UseOther(id) => {
+                if let Some(row) = self.model.rows.remove(&id) {
+                    if let Some(parent) = row.get_parent() {
+                        self.widgets.conflicts_list.remove(&parent);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Widget for Conflicts {
+    type Root = gtk::Dialog;
+
+    fn root(&self) -> Self::Root {
+        self.widgets.container.clone()
+    }
+
+    fn view(relm: &Relm<Self>, mut model: Self::Model) -> Self {
+        let glade_src = include_str!("../ui/conflicts.ui");
+        let builder = gtk::Builder::from_string(glade_src);
+        let container: gtk::Dialog = or_panic!(builder.get_object("conflictsDialog"));
+        let conflicts_list: gtk::ListBox = or_panic!(builder.get_object("conflictsList"));
+
+        for conflict in &model.entries {
+            let row = conflict_row(relm, conflict);
+            conflicts_list.add(&row);
+            model.rows.insert(conflict.id, row);
+        }
+
+        container.set_transient_for(Some(&model.win));
+        container.connect_response(|dialog, _| dialog.close());
+        container.show_all();
+
+        Conflicts {
+            model,
+            widgets: Widgets {
+                container,
+                conflicts_list
+            },
+        }
+    }
+}
+
+fn version_text(prefix: &str, description: Option<&Description>) -> String {
+    match description {
+        Some(description) => match description.price {
+            Some(price) => format!("{}: {}, {:.2}", prefix, description.label, price),
+            None => format!("{}: {}", prefix, description.label)
+        },
+        None => format!("{}: deleted", prefix)
+    }
+}
+
+fn conflict_row(relm: &Relm<Conflicts>, conflict: &Conflict) -> gtk::Box {
+    let row = gtk::Box::new(gtk::Orientation::Horizontal, 5);
+    row.set_margin_top(5);
+    row.set_margin_bottom(5);
+    row.set_margin_start(10);
+    row.set_margin_end(10);
+
+    let labels = gtk::Box::new(gtk::Orientation::Vertical, 2);
+    let label = gtk::Label::new(Some(conflict.label()));
+    label.set_halign(gtk::Align::Start);
+    let kept = gtk::Label::new(Some(&version_text("Kept", conflict.kept.as_ref())));
+    kept.set_halign(gtk::Align::Start);
+    let other = gtk::Label::new(Some(&version_text("Other", conflict.other.as_ref())));
+    other.set_halign(gtk::Align::Start);
+    labels.pack_start(&label, false, true, 0);
+    labels.pack_start(&kept, false, true, 0);
+    labels.pack_start(&other, false, true, 0);
+    row.pack_start(&labels, true, true, 0);
+
+    let keep_btn = gtk::Button::with_label("Keep");
+    let other_btn = gtk::Button::with_label("Use Other");
+    row.pack_end(&other_btn, false, true, 0);
+    row.pack_end(&keep_btn, false, true, 0);
+
+    let id = conflict.id;
+    connect!(relm, keep_btn, connect_clicked(_), Msg::Keep(id));
+    connect!(relm, other_btn, connect_clicked(_), Msg::UseOther(id));
+    row
+}
@@ -0,0 +1,91 @@
+use argon2::Argon2;
+use chacha20poly1305::{Key as CipherKey, XChaCha20Poly1305, XNonce};
+use chacha20poly1305::aead::{Aead, KeyInit};
+use serde::{ Serialize, Deserialize };
+
+use std::fmt;
+
+pub const SALT_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 24;
+/// Sealed into every `Lock` so a passphrase can be checked without touching records.
+const CHECK: &[u8] = b"cycles";
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct DecryptError;
+
+impl fmt::Display for DecryptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "wrong passphrase or damaged data")
+    }
+}
+
+/// A symmetric key derived from a passphrase with Argon2.
+#[derive(Clone, PartialEq)]
+pub struct Key([u8; KEY_LEN]);
+
+impl fmt::Debug for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Key(..)")
+    }
+}
+
+impl Key {
+
+    pub fn derive(passphrase: &str, salt: &[u8]) -> Key {
+        let mut key = [0; KEY_LEN];
+        or_panic!(Res Argon2::default().hash_password_into(passphrase.as_bytes(), salt, &mut key));
+        Key(key)
+    }
+
+    /// Encrypts `plaintext` with XChaCha20-Poly1305, prefixing a fresh nonce.
+    pub fn seal(&self, plaintext: &[u8]) -> Vec<u8> {
+        let nonce = random_bytes(NONCE_LEN);
+        let cipher = XChaCha20Poly1305::new(CipherKey::from_slice(&self.0));
+        let mut sealed = nonce.clone();
+        sealed.extend(or_panic!(Res cipher.encrypt(XNonce::from_slice(&nonce), plaintext)));
+        sealed
+    }
+
+    pub fn open(&self, sealed: &[u8]) -> Result<Vec<u8>, DecryptError> {
+        if sealed.len() < NONCE_LEN {
+            return Err(DecryptError);
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let cipher = XChaCha20Poly1305::new(CipherKey::from_slice(&self.0));
+        cipher.decrypt(XNonce::from_slice(nonce), ciphertext).map_err(|_| DecryptError)
+    }
+}
+
+/// What the database keeps about its passphrase: the salt and a sealed
+/// check value, never the passphrase or key.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Lock {
+    pub salt: Vec<u8>,
+    check: Vec<u8>
+}
+
+impl Lock {
+
+    pub fn new(passphrase: &str) -> (Lock, Key) {
+        let salt = random_bytes(SALT_LEN);
+        let key = Key::derive(passphrase, &salt);
+        let check = key.seal(CHECK);
+        (Lock { salt, check }, key)
+    }
+
+    /// The key for `passphrase`, or `None` if it is the wrong one.
+    pub fn unlock(&self, passphrase: &str) -> Option<Key> {
+        let key = Key::derive(passphrase, &self.salt);
+        match key.open(&self.check) {
+            Ok(check) if check == CHECK => Some(key),
+            _ => None
+        }
+    }
+}
+
+pub fn random_bytes(len: usize) -> Vec<u8> {
+    let mut bytes = vec![0; len];
+    or_panic!(Res getrandom::getrandom(&mut bytes));
+    bytes
+}
@@ -4,8 +4,13 @@ use relm::{connect, init, Component, Relm, Widget, Update};
 use relm_derive::Msg;
 
 use crate::badge::Badge;
-use crate::form::Form;
-use crate::description::{Description, Interval};
+use crate::form::{Form, Host, Msg as FormMsg};
+use crate::description::{Description, Interval, TagStyle};
+use crate::preferences::Preferences;
+
+use std::collections::HashMap;
+
+const ROW_TARGET: &str = "CYCLES_ROW";
 
 fn level_styles(color: &str) -> String {
     format!(
@@ -14,13 +19,61 @@ fn level_styles(color: &str) -> String {
     )
 }
 
+fn render_progress(description: &Description, preferences: &Preferences, start_end: &gtk::Label, days_remaining: &gtk::Label, level: &gtk::LevelBar) {
+    let args = description.render_arguments(None);
+    start_end.set_text(&format!("{} - {}",
+        preferences.format_date(args.start_date),
+        preferences.format_date(args.end_date)
+    ));
+    days_remaining.set_text(&args.remaining_text());
+    let context = days_remaining.get_style_context();
+    if preferences.is_due_soon(description, None) {
+        context.add_class("dueSoon");
+    } else {
+        context.remove_class("dueSoon");
+    }
+    level.set_min_value(0.);
+    level.set_max_value(1.);
+    level.set_value(args.progress);
+}
+
+pub fn tag_badges(container: &gtk::FlowBox, tags: &[String], styles: &HashMap<String, TagStyle>) -> Vec<(String, Component<Badge>)> {
+    let mut badges = vec![];
+    for tag in tags {
+        let style = styles.get(tag).cloned().unwrap_or_default();
+        let element = or_panic!(Res init::<Badge>((tag.clone(), style)));
+        container.add(element.widget());
+        badges.push((tag.clone(), element));
+    }
+    container.show_all();
+    badges
+}
+
+pub fn clear_badges(container: &gtk::FlowBox, badges: &[(String, Component<Badge>)]) {
+    for (_, element) in badges {
+        if let Some(child) = element.widget().get_parent() {
+            container.remove(&child);
+        }
+    }
+}
+
 #[derive(Msg)]
 pub enum Msg {
     Updated((Uuid, Description)),
+    Render(Description),
+    TagStyles(HashMap<String, TagStyle>),
     Delete(Uuid),
     ConfirmDelete,
     CancelDelete,
-    OpenEdit
+    OpenEdit,
+    OpenDuplicate,
+    Duplicated(Description),
+    SelectionMode(bool),
+    Selected(Uuid, bool),
+    Pinned(bool),
+    Pin(Uuid, bool),
+    Dropped(Uuid, Uuid),
+    Preferences(Preferences)
 }
 
 pub struct Widgets {
@@ -32,7 +85,10 @@ pub struct Widgets {
     days_remaining: gtk::Label,
     cycle_level: gtk::LevelBar,
     edit_btn: gtk::Button,
+    duplicate_btn: gtk::Button,
     delete_btn: gtk::Button,
+    select_check: gtk::CheckButton,
+    pin_btn: gtk::ToggleButton,
     delete_popover: gtk::Popover,
     no_btn: gtk::Button,
     yes_btn: gtk::Button,
@@ -47,25 +103,30 @@ pub struct Model {
     id: Uuid,
     relm: Relm<Cycle>,
     tags: Vec<(String, Component<Badge>)>,
+    tag_styles: HashMap<String, TagStyle>,
+    suggestions: gtk::ListStore,
+    host: Host,
+    preferences: Preferences,
     description: Description,
-    edit_popover: Option<Component<Form>>
+    editor: Option<Component<Form>>
 }
 
 impl Update for Cycle {
     // Specify the model used for this widget.
     type Model = Model;
     // Specify the model parameter used to init the model.
-    type ModelParam = (Uuid, Description);
+    type ModelParam = (Uuid, Description, HashMap<String, TagStyle>, gtk::ListStore, Host, Preferences);
     // Specify the type of the messages sent to the update function.
     type Msg = Msg;
 
-    fn model(relm: &Relm<Self>, (id, description): (Uuid, Description)) -> Model {
+    fn model(relm: &Relm<Self>, (id, description, tag_styles, suggestions, host, preferences): (Uuid, Description, HashMap<String, TagStyle>, gtk::ListStore, Host, Preferences)) -> Model {
         Model {
             id,
             tags: vec![],
+            tag_styles, suggestions, host, preferences,
             relm: relm.clone(),
             description,
-            edit_popover: None
+            editor: None
         }
     }
 
@@ -82,44 +143,63 @@ impl Update for Cycle {
             },
             Msg::OpenEdit => {
                 let desc = Some(self.model.description.clone());
-                let elem = self.widgets.edit_btn.clone();
-                let element = or_panic!(Res init::<Form>((elem, desc)));
+                let element = or_panic!(Res init::<Form>((self.model.host.clone(), desc, self.model.suggestions.clone(), vec![], self.model.preferences.clone())));
                 let stream = self.model.relm.stream().clone();
                 let id = self.model.id;
                 element.stream().observe(move |msg| {
-                    if let crate::form::Msg::Created(a) = msg {
+                    if let FormMsg::Created(a) = msg {
                         stream.emit(Msg::Updated((id, a.clone())));
                     }
                 });
-                self.model.edit_popover = Some(element);
+                self.model.editor = Some(element);
             },
-            Msg::Updated((_, description)) => {
+            Msg::OpenDuplicate => {
+                let desc = Description {
+                    label: format!("{} (copy)", self.model.description.label),
+                    ..self.model.description.clone()
+                };
+                let element = or_panic!(Res init::<Form>((self.model.host.clone(), Some(desc), self.model.suggestions.clone(), vec![], self.model.preferences.clone())));
+                element.stream().emit(FormMsg::Title("Duplicate Cycle".into()));
+                let stream = self.model.relm.stream().clone();
+                element.stream().observe(move |msg| {
+                    if let FormMsg::Created(a) = msg {
+                        stream.emit(Msg::Duplicated(a.clone()));
+                    }
+                });
+                self.model.editor = Some(element);
+            },
+            Msg::Duplicated(_) | Msg::Selected(..) | Msg::Pin(..) | Msg::Dropped(..) => {},
+            Msg::Preferences(preferences) => {
+                self.model.preferences = preferences;
+                render_progress(&self.model.description, &self.model.preferences, &self.widgets.start_end, &self.widgets.days_remaining, &self.widgets.cycle_level);
+            },
+            Msg::Pinned(pinned) => {
+                self.widgets.pin_btn.set_active(pinned);
+            },
+            Msg::SelectionMode(selecting) => {
+                if !selecting {
+                    self.widgets.select_check.set_active(false);
+                }
+                self.widgets.select_check.set_visible(selecting);
+                self.widgets.edit_btn.set_visible(!selecting);
+                self.widgets.duplicate_btn.set_visible(!selecting);
+                self.widgets.delete_btn.set_visible(!selecting);
+                self.widgets.pin_btn.set_visible(!selecting);
+            },
+            Msg::TagStyles(styles) => {
+                self.model.tag_styles = styles;
+                clear_badges(&self.widgets.tags_container, &self.model.tags);
+                self.model.tags = tag_badges(&self.widgets.tags_container, &self.model.description.tags, &self.model.tag_styles);
+            },
+            Msg::Updated((_, description)) | Msg::Render(description) => {
                 if let Some(color) = description.color.as_ref() {
                     add_stylesheet!(raw level_styles(color).as_ref(), self.widgets.cycle_level);
                 }
-                for (_, element) in &self.model.tags {
-                    self.widgets.tags_container.remove(element.widget());
-                }
-                self.model.tags = vec![];
-                for tag in &description.tags {
-                    let element = or_panic!(Res init::<crate::badge::Badge>(tag.clone()));
-                    self.widgets.tags_container.add(element.widget());
-                    self.model.tags.push((tag.clone(), element));
-                }
-                self.widgets.tags_container.show_all();
+                clear_badges(&self.widgets.tags_container, &self.model.tags);
+                self.model.tags = tag_badges(&self.widgets.tags_container, &description.tags, &self.model.tag_styles);
                 self.widgets.label.set_text(&description.label);
-                if let Some(desc) = &description.description {
-                    self.widgets.description.set_text(&desc);
-                }
-                let args = self.model.description.render_arguments(None);
-                self.widgets.start_end.set_text(&format!("{} - {}",
-                    args.start_date.format("%m/%d"),
-                    args.end_date.format("%m/%d")
-                ));
-                self.widgets.days_remaining.set_text(&format!("{}", args.remaining_days));
-                self.widgets.cycle_level.set_min_value(0.);
-                self.widgets.cycle_level.set_value(args.bar_value);
-                self.widgets.cycle_level.set_max_value(args.bar_length);
+                self.widgets.description.set_text(description.description.as_deref().unwrap_or(""));
+                render_progress(&description, &self.model.preferences, &self.widgets.start_end, &self.widgets.days_remaining, &self.widgets.cycle_level);
                 self.model.description = description;
             }
         }
@@ -146,42 +226,50 @@ impl Widget for Cycle {
         let cycle_level: gtk::LevelBar = or_panic!(builder.get_object("cycleLevel"));
         let delete_popover: gtk::Popover = or_panic!(builder.get_object("deletePopover"));
         let edit_btn: gtk::Button = or_panic!(builder.get_object("editButton"));
+        let duplicate_btn: gtk::Button = or_panic!(builder.get_object("duplicateButton"));
         let delete_btn: gtk::Button = or_panic!(builder.get_object("deleteButton"));
         let no_btn: gtk::Button = or_panic!(builder.get_object("deleteNoButton"));
         let yes_btn: gtk::Button = or_panic!(builder.get_object("deleteYesButton"));
         let tags_container: gtk::FlowBox = or_panic!(builder.get_object("tagsContainer"));
+        let select_check: gtk::CheckButton = or_panic!(builder.get_object("selectCheck"));
+        let pin_btn: gtk::ToggleButton = or_panic!(builder.get_object("pinButton"));
+        let drag_handle: gtk::EventBox = or_panic!(builder.get_object("dragHandle"));
 
         let id = model.id;
 
         connect!(_relm, delete_btn, connect_clicked(_), Msg::ConfirmDelete);
         connect!(_relm, edit_btn, connect_clicked(_), Msg::OpenEdit);
+        connect!(_relm, duplicate_btn, connect_clicked(_), Msg::OpenDuplicate);
         connect!(_relm, no_btn, connect_clicked(_), Msg::CancelDelete);
         connect!(_relm, yes_btn, connect_clicked(_), Msg::Delete(id));
+        connect!(_relm, select_check, connect_toggled(check), Msg::Selected(id, check.get_active()));
+        connect!(_relm, pin_btn, connect_toggled(btn), Msg::Pin(id, btn.get_active()));
+
+        let targets = [gtk::TargetEntry::new(ROW_TARGET, gtk::TargetFlags::SAME_APP, 0)];
+        drag_handle.drag_source_set(gdk::ModifierType::BUTTON1_MASK, &targets, gdk::DragAction::MOVE);
+        drag_handle.connect_drag_data_get(move |_, _, data, _, _| {
+            data.set(&data.get_target(), 8, id.to_string().as_bytes());
+        });
+        container.drag_dest_set(gtk::DestDefaults::ALL, &targets, gdk::DragAction::MOVE);
+        connect!(_relm, container, connect_drag_data_received(_, _, _, _, data, _, _), {
+            String::from_utf8(data.get_data()).ok()
+                .and_then(|source| Uuid::parse_str(&source).ok())
+                .map(|source| Msg::Dropped(source, id))
+        });
 
         if let Some(color) = model.description.color.as_ref() {
             add_stylesheet!(raw level_styles(color).as_ref(), cycle_level);
         }
 
-        for tag in &model.description.tags {
-            let element = or_panic!(Res init::<crate::badge::Badge>(tag.clone()));
-            tags_container.add(element.widget());
-            model.tags.push((tag.clone(), element));
-        }
+        model.tags = tag_badges(&tags_container, &model.description.tags, &model.tag_styles);
         add_stylesheet!("../css/cycle.css", container);
 
         if let Some(desc) = model.description.description.as_ref() {
             description.set_text(&desc);
         }
         label.set_text(model.description.label.as_ref());
-        let args = model.description.render_arguments(None);
-        start_end.set_text(&format!("{} - {}",
-            args.start_date.format("%m/%d"),
-            args.end_date.format("%m/%d")
-        ));
-        days_remaining.set_text(&format!("{}", args.remaining_days));
-        cycle_level.set_min_value(0.);
-        cycle_level.set_value(args.bar_value);
-        cycle_level.set_max_value(args.bar_length);
+        add_stylesheet!(raw b"label.dueSoon { color: @warning_color; }", days_remaining);
+        render_progress(&model.description, &model.preferences, &start_end, &days_remaining, &cycle_level);
         container.show_all();
 
         Cycle {
@@ -195,7 +283,10 @@ impl Widget for Cycle {
                 days_remaining,
                 cycle_level,
                 edit_btn,
+                duplicate_btn,
                 delete_btn,
+                select_check,
+                pin_btn,
                 delete_popover,
                 no_btn,
                 yes_btn,
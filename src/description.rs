@@ -1,10 +1,13 @@
-use chrono::{Utc, NaiveTime, Datelike, Duration, NaiveDateTime, NaiveDate};
+use chrono::{Utc, Datelike, Duration, NaiveDate};
 use serde::{ Serialize, Deserialize };
 
 use std::{env, io};
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::path::PathBuf;
 
+use crate::fields::FieldValue;
+
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum Interval {
     Weekly,
@@ -20,62 +23,183 @@ pub struct Description {
     pub tags: Vec<String>,
     pub start: NaiveDate,
     pub interval: Interval,
-    pub interval_multiplier: i64
+    pub interval_multiplier: i64,
+    #[serde(default)]
+    pub price: Option<f64>,
+    /// Longer, multi-line notes, unlike the one-line `description`.
+    #[serde(default)]
+    pub notes: Option<String>,
+    /// Where the subscription is managed or cancelled.
+    #[serde(default)]
+    pub url: Option<String>,
+    /// The email or username the subscription is under.
+    #[serde(default)]
+    pub account: Option<String>,
+    #[serde(default)]
+    pub payment: Option<String>,
+    /// Values for the user-defined fields, by field name.
+    #[serde(default)]
+    pub fields: BTreeMap<String, FieldValue>
+}
+
+/// How a tag is drawn wherever it shows up, kept in the tag registry.
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub struct TagStyle {
+    pub color: Option<String>,
+    pub icon: Option<String>
+}
+
+/// Where a cycle currently sits relative to its start date.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Phase {
+    /// The start date is still in the future.
+    Pending,
+    /// The cycle has started and is somewhere inside a period.
+    Running
 }
 
+/// Everything a `Cycle` row needs to draw its current period.
+///
+/// Periods are half-open: a period covers `start_date` up to, but not
+/// including, `end_date`. On the renewal day the new period has just begun,
+/// so `progress` is `0.0` and `remaining_days` is the full period length.
 #[derive(Debug, PartialEq)]
 pub struct RenderArgs {
-    pub start_time: Option<NaiveTime>,
+    pub phase: Phase,
     pub start_date: NaiveDate,
     pub end_date: NaiveDate,
+    /// Days until `end_date`, or until the first start while `Pending`.
     pub remaining_days: i64,
-    pub bar_length: f64,
-    pub bar_value: f64
+    /// Fraction of the current period that has elapsed, in `0.0..1.0`.
+    pub progress: f64
+}
+
+impl RenderArgs {
+
+    pub fn remaining_text(&self) -> String {
+        match (self.phase, self.remaining_days) {
+            (Phase::Pending, 1) => "starts tomorrow".into(),
+            (Phase::Pending, days) => format!("starts in {} days", days),
+            (Phase::Running, 1) => "1 day left".into(),
+            (Phase::Running, days) => format!("{} days left", days)
+        }
+    }
 }
 
 impl Description {
 
-    pub fn render_arguments(&self, _time: Option<NaiveDate>) -> RenderArgs {
-        let time = _time.unwrap_or_else(|| Utc::now().date().naive_local());
-        match self.interval {
-            Interval::Weekly => {
-                 let total_interval_days = self.interval_multiplier*7;
-                 let total_days = (time - self.start).num_days().abs();
-                 let remaining_days = total_interval_days - (total_days % total_interval_days);
-                 let bar_length = (total_interval_days - 1) as f64;
-                 let bar_value = (total_interval_days - remaining_days) as f64;
-                 let start_date = self.start + Duration::days(total_interval_days*(total_days / total_interval_days));
-                 let end_date = start_date + Duration::days(total_interval_days);
-                 RenderArgs { start_time: None, start_date, end_date, remaining_days, bar_value, bar_length }
-            },
-            Interval::Monthly => {
-                let mut new = self.clone();
-                new.interval = Interval::Weekly;
-                new.interval_multiplier = 4;
-                new.render_arguments(_time)
-            }
-            Interval::Yearly => {
-                let mut new = self.clone();
-                new.interval = Interval::Weekly;
-                new.interval_multiplier = 52;
-                new.render_arguments(_time)
-                // let target_month = self.start.month();
-                // let target_day = self.start.day();
-                // let total_days = (time - self.start).num_days() as i32;
-                // let total_years = total_days as i32/ 365;
-                // let start_date = NaiveDate::from_ymd(self.start.year() + total_years, target_month, target_day);
-                // let days_since_start = total_days - total_years * 365;
-                // let days_to_go = self.interval_multiplier*365 - days_since_start as i64;
-                // RenderArgs {
-                //     start_date,
-                //     start_time: None,
-                //     end_date: NaiveDate::from_ymd(start_date.year()+1, target_month, target_day),
-                //     remaining_days: days_to_go as i64,
-                //     bar_length: self.interval_multiplier as f64 * 365.,
-                //     bar_value: days_since_start as f64
-                // }
+    pub fn render_arguments(&self, time: Option<NaiveDate>) -> RenderArgs {
+        let time = time.unwrap_or_else(|| Utc::now().date().naive_local());
+        if time < self.start {
+            return RenderArgs {
+                phase: Phase::Pending,
+                start_date: self.start,
+                end_date: self.period_start(1),
+                remaining_days: (self.start - time).num_days(),
+                progress: 0.
+            };
+        }
+        let period = self.period_containing(time);
+        let start_date = self.period_start(period);
+        let end_date = self.period_start(period + 1);
+        let length = (end_date - start_date).num_days();
+        let elapsed = (time - start_date).num_days();
+        RenderArgs {
+            phase: Phase::Running,
+            start_date, end_date,
+            remaining_days: length - elapsed,
+            progress: elapsed as f64 / length as f64
+        }
+    }
+
+    /// What the cycle costs per month, spreading weekly and yearly prices out.
+    pub fn monthly_cost(&self) -> Option<f64> {
+        let periods = match self.interval {
+            Interval::Weekly => 52. / 12.,
+            Interval::Monthly => 1.,
+            Interval::Yearly => 1. / 12.
+        };
+        self.price.map(|price| price * periods / self.interval_multiplier.max(1) as f64)
+    }
+
+    /// Renames a tag in place, dropping it instead if `to` is already present.
+    pub fn rename_tag(&mut self, from: &str, to: &str) -> bool {
+        if from == to || !self.tags.iter().any(|tag| tag == from) {
+            return false;
+        }
+        if self.tags.iter().any(|tag| tag == to) {
+            self.tags.retain(|tag| tag != from);
+        } else {
+            for tag in self.tags.iter_mut().filter(|tag| *tag == from) {
+                *tag = to.to_string();
             }
         }
+        true
     }
 
+    pub fn remove_tag(&mut self, name: &str) -> bool {
+        let before = self.tags.len();
+        self.tags.retain(|tag| tag != name);
+        before != self.tags.len()
+    }
+
+    /// The first day of the `n`th period, where period `0` begins on `start`.
+    pub fn period_start(&self, n: i64) -> NaiveDate {
+        let step = self.interval_multiplier.max(1) * n;
+        match self.interval {
+            Interval::Weekly => self.start + Duration::days(7 * step),
+            Interval::Monthly => add_months(self.start, step),
+            Interval::Yearly => add_months(self.start, 12 * step)
+        }
+    }
+
+    /// The next `count` renewals after `time`, starting with the first start while pending.
+    pub fn upcoming_renewals(&self, time: NaiveDate, count: usize) -> Vec<NaiveDate> {
+        let first = if time < self.start { 0 } else { self.period_containing(time) + 1 };
+        (first..first + count as i64).map(|n| self.period_start(n)).collect()
+    }
+
+    /// Every renewal up to and including `time`, newest first.
+    pub fn past_renewals(&self, time: NaiveDate) -> Vec<NaiveDate> {
+        if time < self.start {
+            return vec![];
+        }
+        (0..=self.period_containing(time)).rev().map(|n| self.period_start(n)).collect()
+    }
+
+    fn period_containing(&self, time: NaiveDate) -> i64 {
+        let step = self.interval_multiplier.max(1);
+        let mut period = match self.interval {
+            Interval::Weekly => (time - self.start).num_days() / (7 * step),
+            Interval::Monthly => months_between(self.start, time) / step,
+            Interval::Yearly => months_between(self.start, time) / (12 * step)
+        };
+        while period > 0 && self.period_start(period) > time {
+            period -= 1;
+        }
+        while self.period_start(period + 1) <= time {
+            period += 1;
+        }
+        period
+    }
+
+}
+
+fn months_between(from: NaiveDate, to: NaiveDate) -> i64 {
+    (to.year() - from.year()) as i64 * 12 + to.month() as i64 - from.month() as i64
+}
+
+/// Adds calendar months, clamping to the last day of shorter months so a
+/// cycle starting on the 31st renews on the 28th/29th/30th when it has to.
+fn add_months(date: NaiveDate, months: i64) -> NaiveDate {
+    let total = date.year() as i64 * 12 + date.month0() as i64 + months;
+    let year = total.div_euclid(12) as i32;
+    let month = total.rem_euclid(12) as u32 + 1;
+    let mut day = date.day();
+    loop {
+        if let Some(date) = NaiveDate::from_ymd_opt(year, month, day) {
+            return date;
+        }
+        day -= 1;
+    }
 }
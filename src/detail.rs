@@ -0,0 +1,297 @@
+use gtk::prelude::*;
+use relm::{connect, Component, Relm, Widget, Update};
+use uuid::Uuid;
+use relm_derive::Msg;
+use chrono::Utc;
+
+use std::collections::HashMap;
+
+use crate::attachment::{self, Attachment};
+use crate::badge::Badge;
+use crate::cycle::{tag_badges, clear_badges};
+use crate::description::{Description, Interval, TagStyle};
+use crate::preferences::Preferences;
+
+const UPCOMING: usize = 5;
+const HISTORY: usize = 10;
+
+#[derive(Msg)]
+pub enum Msg {
+    Show(Description, HashMap<String, TagStyle>, Preferences),
+    Clear,
+    Folded(bool),
+    Back,
+    SaveTemplate,
+    Manage,
+    OpenUrl(String),
+    Attachments(Vec<Attachment>),
+    Attach,
+    OpenAttachment(Attachment),
+    RemoveAttachment(Uuid)
+}
+
+pub struct Widgets {
+    container: gtk::Box,
+    header: libhandy::HeaderBar,
+    back_btn: gtk::Button,
+    save_template_btn: gtk::Button,
+    stack: gtk::Stack,
+    label: gtk::Label,
+    description: gtk::Label,
+    tags_container: gtk::FlowBox,
+    price: gtk::Label,
+    account: gtk::Label,
+    notes: gtk::Label,
+    fields: gtk::Label,
+    manage_btn: gtk::Button,
+    upcoming: gtk::Label,
+    history: gtk::Label,
+    attachments: gtk::ListBox
+}
+
+/// The right-hand pane showing everything about the selected cycle.
+pub struct Detail {
+    model: Model,
+    widgets: Widgets,
+}
+
+pub struct Model {
+    relm: Relm<Detail>,
+    tags: Vec<(String, Component<Badge>)>,
+    url: Option<String>,
+    preferences: Preferences
+}
+
+impl Update for Detail {
+    type Model = Model;
+    type ModelParam = ();
+    type Msg = Msg;
+
+    fn model(relm: &Relm<Self>, _: ()) -> Model {
+        Model {
+            relm: relm.clone(),
+            tags: vec![],
+            url: None,
+            preferences: Preferences::default()
+        }
+    }
+
+    fn update(&mut self, event: Msg) {
+        match event {
+            Msg::Show(description, styles, preferences) => {
+                let today = Utc::now().date().naive_local();
+                self.widgets.header.set_title(Some(&description.label));
+                self.widgets.label.set_text(&description.label);
+                self.widgets.description.set_text(description.description.as_deref().unwrap_or(""));
+                self.widgets.description.set_visible(description.description.is_some());
+                clear_badges(&self.widgets.tags_container, &self.model.tags);
+                self.model.tags = tag_badges(&self.widgets.tags_container, &description.tags, &styles);
+                self.widgets.price.set_text(&price_text(&description, &preferences));
+                let account = account_text(&description);
+                self.widgets.account.set_text(account.as_deref().unwrap_or(""));
+                self.widgets.account.set_visible(account.is_some());
+                self.widgets.notes.set_text(description.notes.as_deref().unwrap_or(""));
+                self.widgets.notes.set_visible(description.notes.is_some());
+                let fields: Vec<String> = description.fields.iter()
+                    .map(|(name, value)| format!("{}: {}", name, value))
+                    .collect();
+                self.widgets.fields.set_text(&fields.join("\n"));
+                self.widgets.fields.set_visible(!fields.is_empty());
+                self.widgets.manage_btn.set_visible(description.url.is_some());
+                self.widgets.manage_btn.set_tooltip_text(description.url.as_deref());
+                self.model.url = description.url.clone();
+
+                let upcoming: Vec<String> = description.upcoming_renewals(today, UPCOMING).iter()
+                    .map(|date| preferences.format_date(*date))
+                    .collect();
+                self.widgets.upcoming.set_text(&upcoming.join("\n"));
+                self.widgets.history.set_text(&history_text(&description, &preferences, today));
+                self.widgets.stack.set_visible_child_name("cycle");
+                self.widgets.save_template_btn.show();
+                self.model.preferences = preferences;
+            },
+            Msg::Clear => {
+                self.widgets.header.set_title(Some("Details"));
+                clear_badges(&self.widgets.tags_container, &self.model.tags);
+                self.model.tags = vec![];
+                self.model.url = None;
+                self.clear_attachments();
+                self.widgets.stack.set_visible_child_name("empty");
+                self.widgets.save_template_btn.hide();
+            },
+            Msg::Folded(folded) => {
+                self.widgets.back_btn.set_visible(folded);
+            },
+            Msg::Manage => {
+                if let Some(url) = self.model.url.clone() {
+                    self.model.relm.stream().emit(Msg::OpenUrl(url));
+                }
+            },
+            Msg::Attachments(entries) => {
+                self.clear_attachments();
+                for entry in &entries {
+                    self.widgets.attachments.add(&attachment_row(&self.model.relm, entry, &self.model.preferences));
+                }
+                self.widgets.attachments.show_all();
+            },
+            Msg::Back | Msg::SaveTemplate | Msg::OpenUrl(_) | Msg::Attach |
+                Msg::OpenAttachment(_) | Msg::RemoveAttachment(_) => {}
+        }
+    }
+}
+
+impl Detail {
+
+    fn clear_attachments(&self) {
+        for row in self.widgets.attachments.get_children() {
+            self.widgets.attachments.remove(&row);
+        }
+    }
+}
+
+impl Widget for Detail {
+    type Root = gtk::Box;
+
+    fn root(&self) -> Self::Root {
+        self.widgets.container.clone()
+    }
+
+    fn view(relm: &Relm<Self>, model: Self::Model) -> Self {
+        let glade_src = include_str!("../ui/detail.ui");
+        let builder = gtk::Builder::from_string(glade_src);
+        let container: gtk::Box = or_panic!(builder.get_object("detailPage"));
+        let header: libhandy::HeaderBar = or_panic!(builder.get_object("detailHeader"));
+        let back_btn: gtk::Button = or_panic!(builder.get_object("detailBackButton"));
+        let save_template_btn: gtk::Button = or_panic!(builder.get_object("saveTemplateButton"));
+        let stack: gtk::Stack = or_panic!(builder.get_object("detailStack"));
+        let label: gtk::Label = or_panic!(builder.get_object("detailLabel"));
+        let description: gtk::Label = or_panic!(builder.get_object("detailDescription"));
+        let tags_container: gtk::FlowBox = or_panic!(builder.get_object("detailTags"));
+        let price: gtk::Label = or_panic!(builder.get_object("detailPrice"));
+        let account: gtk::Label = or_panic!(builder.get_object("detailAccount"));
+        let notes: gtk::Label = or_panic!(builder.get_object("detailNotes"));
+        let fields: gtk::Label = or_panic!(builder.get_object("detailFields"));
+        let manage_btn: gtk::Button = or_panic!(builder.get_object("detailManageButton"));
+        let upcoming: gtk::Label = or_panic!(builder.get_object("detailUpcoming"));
+        let history: gtk::Label = or_panic!(builder.get_object("detailHistory"));
+        let attach_btn: gtk::Button = or_panic!(builder.get_object("detailAttachButton"));
+        let attachments: gtk::ListBox = or_panic!(builder.get_object("detailAttachments"));
+
+        let placeholder = gtk::Label::new(Some("No invoices or receipts yet"));
+        placeholder.set_margin_top(12);
+        placeholder.set_margin_bottom(12);
+        placeholder.get_style_context().add_class("dim-label");
+        placeholder.show();
+        attachments.set_placeholder(Some(&placeholder));
+
+        connect!(relm, back_btn, connect_clicked(_), Msg::Back);
+        connect!(relm, save_template_btn, connect_clicked(_), Msg::SaveTemplate);
+        connect!(relm, manage_btn, connect_clicked(_), Msg::Manage);
+        connect!(relm, attach_btn, connect_clicked(_), Msg::Attach);
+        container.show_all();
+
+        Detail {
+            model,
+            widgets: Widgets {
+                container,
+                header,
+                back_btn,
+                save_template_btn,
+                stack,
+                label,
+                description,
+                tags_container,
+                price,
+                account,
+                notes,
+                fields,
+                manage_btn,
+                upcoming,
+                history,
+                attachments
+            },
+        }
+    }
+}
+
+fn interval_text(description: &Description) -> String {
+    let unit = match description.interval {
+        Interval::Weekly => "week",
+        Interval::Monthly => "month",
+        Interval::Yearly => "year"
+    };
+    match description.interval_multiplier.max(1) {
+        1 => format!("every {}", unit),
+        n => format!("every {} {}s", n, unit)
+    }
+}
+
+fn price_text(description: &Description, preferences: &Preferences) -> String {
+    match (description.price, description.monthly_cost()) {
+        (Some(price), Some(monthly)) => format!("{} {} ({} a month)",
+            preferences.format_price(price), interval_text(description), preferences.format_price(monthly)),
+        _ => format!("Renews {}", interval_text(description))
+    }
+}
+
+/// The account and payment method on one line, e.g. "me@example.com · Visa".
+fn account_text(description: &Description) -> Option<String> {
+    let parts: Vec<&str> = description.account.iter().chain(description.payment.iter())
+        .map(|part| part.as_str())
+        .collect();
+    if parts.is_empty() { None } else { Some(parts.join(" · ")) }
+}
+
+fn history_text(description: &Description, preferences: &Preferences, today: chrono::NaiveDate) -> String {
+    let renewals = description.past_renewals(today);
+    if renewals.is_empty() {
+        return format!("Starts on {}", preferences.format_date(description.start));
+    }
+    let mut lines: Vec<String> = renewals.iter().take(HISTORY)
+        .map(|date| preferences.format_date(*date))
+        .collect();
+    if renewals.len() > HISTORY {
+        lines.push(format!("and {} earlier", renewals.len() - HISTORY));
+    }
+    if let Some(price) = description.price {
+        lines.push(format!("{} paid over {} renewals", preferences.format_price(price * renewals.len() as f64), renewals.len()));
+    }
+    lines.join("\n")
+}
+
+fn attachment_row(relm: &Relm<Detail>, entry: &Attachment, preferences: &Preferences) -> gtk::Box {
+    let row = gtk::Box::new(gtk::Orientation::Horizontal, 5);
+    row.set_margin_top(5);
+    row.set_margin_bottom(5);
+    row.set_margin_start(10);
+    row.set_margin_end(10);
+
+    let labels = gtk::Box::new(gtk::Orientation::Vertical, 2);
+    let name = gtk::Label::new(Some(&entry.name));
+    name.set_halign(gtk::Align::Start);
+    let period = match entry.period {
+        Some(start) => format!("Period from {}", preferences.format_date(start)),
+        None => "Whole subscription".to_string()
+    };
+    let info = gtk::Label::new(Some(&format!("{} · {}", period, attachment::format_size(entry.size))));
+    info.set_halign(gtk::Align::Start);
+    info.get_style_context().add_class("dim-label");
+    labels.pack_start(&name, false, true, 0);
+    labels.pack_start(&info, false, true, 0);
+    row.pack_start(&labels, true, true, 0);
+
+    let open_btn = gtk::Button::from_icon_name(Some("document-open-symbolic"), gtk::IconSize::Button);
+    open_btn.set_tooltip_text(Some("Open"));
+    open_btn.set_valign(gtk::Align::Center);
+    let remove_btn = gtk::Button::from_icon_name(Some("user-trash-symbolic"), gtk::IconSize::Button);
+    remove_btn.set_tooltip_text(Some("Remove"));
+    remove_btn.set_valign(gtk::Align::Center);
+    row.pack_end(&remove_btn, false, true, 0);
+    row.pack_end(&open_btn, false, true, 0);
+
+    let opened = entry.clone();
+    let id = entry.id;
+    connect!(relm, open_btn, connect_clicked(_), Msg::OpenAttachment(opened.clone()));
+    connect!(relm, remove_btn, connect_clicked(_), Msg::RemoveAttachment(id));
+    row
+}
@@ -0,0 +1,118 @@
+use gtk::prelude::*;
+use relm::{connect, Relm, Widget, Update};
+use relm_derive::Msg;
+
+use crate::check::{Issue, Report};
+
+#[derive(Msg)]
+pub enum Msg {
+    Repair,
+    Report(Report)
+}
+
+pub struct Widgets {
+    container: gtk::Dialog,
+    summary: gtk::Label,
+    repair_btn: gtk::Button,
+    issues_list: gtk::ListBox
+}
+
+pub struct Diagnostics {
+    model: Model,
+    widgets: Widgets,
+}
+
+pub struct Model {
+    relm: Relm<Diagnostics>,
+    win: libhandy::ApplicationWindow,
+    report: Report
+}
+
+impl Update for Diagnostics {
+    type Model = Model;
+    type ModelParam = (libhandy::ApplicationWindow, Report);
+    type Msg = Msg;
+
+    fn model(relm: &Relm<Self>, (win, report): (libhandy::ApplicationWindow, Report)) -> Model {
+        Model {
+            relm: relm.clone(),
+            win, report
+        }
+    }
+
+    fn update(&mut self, event: Msg) {
+        match event {
+            Msg::Repair => {},
+            Msg::Report(report) => {
+                for row in self.widgets.issues_list.get_children() {
+                    self.widgets.issues_list.remove(&row);
+                }
+                render_report(&report, &self.widgets.summary, &self.widgets.repair_btn, &self.widgets.issues_list);
+                self.model.report = report;
+            }
+        }
+    }
+}
+
+impl Widget for Diagnostics {
+    type Root = gtk::Dialog;
+
+    fn root(&self) -> Self::Root {
+        self.widgets.container.clone()
+    }
+
+    fn view(relm: &Relm<Self>, model: Self::Model) -> Self {
+        let glade_src = include_str!("../ui/diagnostics.ui");
+        let builder = gtk::Builder::from_string(glade_src);
+        let container: gtk::Dialog = or_panic!(builder.get_object("diagnosticsDialog"));
+        let summary: gtk::Label = or_panic!(builder.get_object("summaryLabel"));
+        let repair_btn: gtk::Button = or_panic!(builder.get_object("repairButton"));
+        let issues_list: gtk::ListBox = or_panic!(builder.get_object("issuesList"));
+
+        render_report(&model.report, &summary, &repair_btn, &issues_list);
+
+        connect!(relm, repair_btn, connect_clicked(_), Msg::Repair);
+
+        container.set_transient_for(Some(&model.win));
+        container.connect_response(|dialog, _| dialog.close());
+        container.show_all();
+
+        Diagnostics {
+            model,
+            widgets: Widgets {
+                container,
+                summary,
+                repair_btn,
+                issues_list
+            },
+        }
+    }
+}
+
+fn render_report(report: &Report, summary: &gtk::Label, repair_btn: &gtk::Button, issues_list: &gtk::ListBox) {
+    summary.set_text(&report.summary());
+    repair_btn.set_sensitive(!report.is_clean());
+    for issue in &report.issues {
+        issues_list.add(&issue_row(issue));
+    }
+    issues_list.show_all();
+}
+
+fn issue_row(issue: &Issue) -> gtk::Box {
+    let row = gtk::Box::new(gtk::Orientation::Vertical, 2);
+    row.set_margin_top(5);
+    row.set_margin_bottom(5);
+    row.set_margin_start(10);
+    row.set_margin_end(10);
+
+    let name = gtk::Label::new(Some(&issue.name()));
+    name.set_halign(gtk::Align::Start);
+    row.pack_start(&name, false, true, 0);
+    for problem in &issue.problems {
+        let label = gtk::Label::new(Some(&problem.to_string()));
+        label.set_halign(gtk::Align::Start);
+        label.set_line_wrap(true);
+        row.pack_start(&label, false, true, 0);
+    }
+    row
+}
@@ -0,0 +1,117 @@
+use chrono::NaiveDate;
+use serde::{ Serialize, Deserialize };
+
+use std::fmt;
+
+use crate::cache::Database;
+
+const SETTINGS_KEY: &str = "custom-fields";
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub enum FieldKind {
+    Text,
+    Number,
+    Date,
+    Url
+}
+
+impl FieldKind {
+
+    pub const ALL: [FieldKind; 4] = [FieldKind::Text, FieldKind::Number, FieldKind::Date, FieldKind::Url];
+
+    pub fn nick(self) -> &'static str {
+        match self {
+            FieldKind::Text => "text",
+            FieldKind::Number => "number",
+            FieldKind::Date => "date",
+            FieldKind::Url => "url"
+        }
+    }
+
+    pub fn from_nick(nick: &str) -> FieldKind {
+        FieldKind::ALL.iter().copied().find(|kind| kind.nick() == nick).unwrap_or(FieldKind::Text)
+    }
+
+    /// What to show in an empty entry for this kind.
+    pub fn hint(self) -> &'static str {
+        match self {
+            FieldKind::Text => "",
+            FieldKind::Number => "0",
+            FieldKind::Date => "YYYY-MM-DD",
+            FieldKind::Url => "https://"
+        }
+    }
+}
+
+/// A field the user added to every cycle, e.g. a contract number.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct FieldDefinition {
+    pub name: String,
+    pub kind: FieldKind
+}
+
+/// Written as `{ type, value }` so the TOML file backend can hold it.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value")]
+pub enum FieldValue {
+    Text(String),
+    Number(f64),
+    Date(NaiveDate),
+    Url(String)
+}
+
+impl FieldValue {
+
+    /// Reads what was typed for a field of `kind`, `None` if it doesn't fit.
+    pub fn parse(kind: FieldKind, text: &str) -> Option<FieldValue> {
+        let text = text.trim();
+        match kind {
+            FieldKind::Text => Some(FieldValue::Text(text.to_string())),
+            FieldKind::Number => text.parse().ok().filter(|number: &f64| number.is_finite()).map(FieldValue::Number),
+            FieldKind::Date => NaiveDate::parse_from_str(text, DATE_FORMAT).ok().map(FieldValue::Date),
+            FieldKind::Url if text.contains(char::is_whitespace) => None,
+            FieldKind::Url => Some(FieldValue::Url(text.to_string()))
+        }
+    }
+
+    pub fn kind(&self) -> FieldKind {
+        match self {
+            FieldValue::Text(_) => FieldKind::Text,
+            FieldValue::Number(_) => FieldKind::Number,
+            FieldValue::Date(_) => FieldKind::Date,
+            FieldValue::Url(_) => FieldKind::Url
+        }
+    }
+}
+
+/// Written the way `parse` reads it back.
+impl fmt::Display for FieldValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FieldValue::Text(text) | FieldValue::Url(text) => write!(f, "{}", text),
+            FieldValue::Number(number) => write!(f, "{}", number),
+            FieldValue::Date(date) => write!(f, "{}", date.format(DATE_FORMAT))
+        }
+    }
+}
+
+/// The fields defined in preferences, in the order they were added.
+pub fn definitions(db: &Database) -> Vec<FieldDefinition> {
+    db.setting(SETTINGS_KEY).unwrap_or_default()
+}
+
+pub fn set_definitions(db: &Database, definitions: &[FieldDefinition]) {
+    db.set_setting(SETTINGS_KEY, &definitions.to_vec());
+}
+
+/// Adds the definitions from `other` whose names aren't taken yet, e.g. after an import.
+pub fn merge_definitions(db: &Database, other: &[FieldDefinition]) {
+    let mut current = definitions(db);
+    for definition in other {
+        if !current.iter().any(|existing| existing.name == definition.name) {
+            current.push(definition.clone());
+        }
+    }
+    set_definitions(db, &current);
+}
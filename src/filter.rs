@@ -0,0 +1,71 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::Description;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TagMode {
+    /// A cycle has to carry every selected tag.
+    All,
+    /// A cycle has to carry at least one selected tag.
+    Any
+}
+
+/// What the main list is narrowed down to: the search text and the tag chips.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Filter {
+    pub query: String,
+    pub tags: BTreeSet<String>,
+    pub mode: TagMode
+}
+
+impl Default for Filter {
+    fn default() -> Filter {
+        Filter { query: String::new(), tags: BTreeSet::new(), mode: TagMode::All }
+    }
+}
+
+impl Filter {
+
+    pub fn matches(&self, description: &Description) -> bool {
+        self.matches_query(description) && self.matches_tags(description)
+    }
+
+    fn matches_query(&self, description: &Description) -> bool {
+        let query = self.query.to_lowercase();
+        query.is_empty() ||
+            description.label.to_lowercase().contains(&query) ||
+            description.description.as_ref().map_or(false, |x| x.to_lowercase().contains(&query)) ||
+            description.tags.iter().any(|tag| tag.to_lowercase().contains(&query)) ||
+            description.fields.values().any(|value| value.to_string().to_lowercase().contains(&query))
+    }
+
+    fn matches_tags(&self, description: &Description) -> bool {
+        if self.tags.is_empty() {
+            return true;
+        }
+        let mut selected = self.tags.iter().map(|tag| description.tags.contains(tag));
+        match self.mode {
+            TagMode::All => selected.all(|x| x),
+            TagMode::Any => selected.any(|x| x)
+        }
+    }
+}
+
+/// How many cycles use a tag and what they add up to per month.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct TagTotal {
+    pub count: usize,
+    pub monthly: f64
+}
+
+pub fn tag_totals<'a, I: IntoIterator<Item=&'a Description>>(descriptions: I) -> BTreeMap<String, TagTotal> {
+    let mut totals: BTreeMap<String, TagTotal> = BTreeMap::new();
+    for description in descriptions {
+        for tag in &description.tags {
+            let total = totals.entry(tag.clone()).or_default();
+            total.count += 1;
+            total.monthly += description.monthly_cost().unwrap_or(0.);
+        }
+    }
+    totals
+}
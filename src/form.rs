@@ -4,24 +4,59 @@ use relm_derive::Msg;
 use chrono::Datelike;
 
 use crate::description::{Description, Interval};
+use crate::fields::{FieldDefinition, FieldKind};
+use crate::preferences::Preferences;
+use crate::validation::{FormError, FormInput};
+use crate::template::Template;
+
+/// Below this window width the editor is pushed onto the deck instead of opening a dialog.
+const NARROW_WIDTH: i32 = 720;
+
+/// Where the editor page gets shown.
+#[derive(Clone)]
+pub struct Host {
+    pub window: libhandy::ApplicationWindow,
+    pub deck: libhandy::Deck,
+    pub main_page: gtk::Widget
+}
+
+impl Host {
+
+    fn is_narrow(&self) -> bool {
+        self.window.get_allocated_width() < NARROW_WIDTH
+    }
+}
 
 #[derive(Msg)]
 pub enum Msg {
     Created(Description),
     CreateClicked,
+    Validate,
+    Close,
+    TemplateChosen,
+    Title(String),
     ColorSet(gdk::RGBA),
     TagsChanged(Vec<String>)
 }
 
 pub struct Widgets {
-    container: gtk::Popover,
+    container: gtk::Box,
+    header: libhandy::HeaderBar,
     create_btn: gtk::Button,
     label_entry: gtk::Entry,
     description_entry: gtk::Entry,
     cycle_interval: gtk::ComboBox,
     interval_multiplier: gtk::SpinButton,
+    price_entry: gtk::SpinButton,
     datepicker: gtk::Calendar,
     color_entry: gtk::ColorButton,
+    url_entry: gtk::Entry,
+    account_entry: gtk::Entry,
+    payment_entry: gtk::Entry,
+    notes_view: gtk::TextView,
+    field_entries: Vec<(FieldDefinition, gtk::Entry)>,
+    error_label: gtk::Label,
+    template_select: gtk::ComboBoxText,
 }
 
 pub struct Form {
@@ -31,25 +66,31 @@ pub struct Form {
 
 pub struct Model {
     relm: Relm<Form>,
-    btn: gtk::Button,
+    host: Host,
+    dialog: Option<gtk::Dialog>,
     description: Option<Description>,
     color: Option<String>,
     tag_data: Vec<String>,
+    suggestions: gtk::ListStore,
+    templates: Vec<Template>,
+    preferences: Preferences,
     tags: Option<Component<crate::tags::Tags>>
 }
 
 impl Update for Form {
     type Model = Model;
-    type ModelParam = (gtk::Button, Option<Description>);
+    type ModelParam = (Host, Option<Description>, gtk::ListStore, Vec<Template>, Preferences);
     type Msg = Msg;
 
-    fn model(relm: &Relm<Self>, (btn, description): (gtk::Button, Option<Description>)) -> Model {
+    fn model(relm: &Relm<Self>, (host, description, suggestions, templates, preferences): (Host, Option<Description>, gtk::ListStore, Vec<Template>, Preferences)) -> Model {
+        let color = description.as_ref().and_then(|desc| desc.color.clone());
+        let tag_data = description.as_ref().map(|desc| desc.tags.clone()).unwrap_or_default();
         Model {
             relm: relm.clone(),
-            btn, description,
-            color: None,
-            tags: None,
-            tag_data: vec![]
+            host, description,
+            dialog: None,
+            color, tag_data, suggestions, templates, preferences,
+            tags: None
         }
     }
 
@@ -59,39 +100,168 @@ impl Update for Form {
                 self.model.tag_data = tags;
             },
             Msg::Created(_) => {
-                self.widgets.container.hide();
+                self.model.relm.stream().emit(Msg::Close);
+            },
+            Msg::Close => {
+                use libhandy::DeckExt;
+                match self.model.dialog.take() {
+                    Some(dialog) => dialog.close(),
+                    None => {
+                        self.model.host.deck.set_visible_child(&self.model.host.main_page);
+                        self.model.host.deck.remove(&self.widgets.container);
+                    }
+                }
             },
             Msg::ColorSet(color) => {
                 self.model.color = Some(color.to_string());
             },
+            Msg::Title(title) => {
+                self.widgets.header.set_title(Some(&title));
+            },
+            Msg::TemplateChosen => {
+                let template = self.widgets.template_select.get_active()
+                    .and_then(|index| self.model.templates.get(index as usize))
+                    .cloned();
+                if let Some(template) = template {
+                    self.apply_template(&template);
+                }
+            },
+            Msg::Validate => {
+                self.validate();
+            },
             Msg::CreateClicked => {
-                let label: String = self.widgets.label_entry.get_text().into();
-                let _description: String = self.widgets.description_entry.get_text().into();
-                let description = if !_description.is_empty() { Some(_description) } else { None };
-                let interval = match self.widgets.cycle_interval.get_active() {
-                    Some(0) => Interval::Weekly,
-                    Some(1) => Interval::Monthly,
-                    Some(2) => Interval::Yearly,
-                    _ => unreachable!()
-                };
-                let interval_multiplier = self.widgets.interval_multiplier.get_value_as_int() as i64;
-                let (y, m, d) = self.widgets.datepicker.get_date();
-                let start = chrono::NaiveDate::from_ymd(y as i32, m + 1, d);
-                let description = Description {
-                    tags: self.model.tag_data.clone(),
-                    label, interval,
-                    description,
-                    interval_multiplier, start,
-                    color: self.model.color.clone()
-                };
-                self.model.relm.stream().emit(Msg::Created(description));
+                if let Some(description) = self.validate() {
+                    self.model.relm.stream().emit(Msg::Created(description));
+                }
+            }
+        }
+    }
+}
+
+impl Form {
+
+    fn input(&self) -> FormInput {
+        let interval = match self.widgets.cycle_interval.get_active() {
+            Some(0) => Some(Interval::Weekly),
+            Some(1) => Some(Interval::Monthly),
+            Some(2) => Some(Interval::Yearly),
+            _ => None
+        };
+        let (y, m, d) = self.widgets.datepicker.get_date();
+        FormInput {
+            label: self.widgets.label_entry.get_text().into(),
+            description: self.widgets.description_entry.get_text().into(),
+            color: self.model.color.clone(),
+            tags: self.model.tag_data.clone(),
+            interval,
+            interval_multiplier: self.widgets.interval_multiplier.get_value_as_int() as i64,
+            start: (y as i32, m + 1, d),
+            price: self.widgets.price_entry.get_value(),
+            notes: text_of(&self.widgets.notes_view),
+            url: self.widgets.url_entry.get_text().into(),
+            account: self.widgets.account_entry.get_text().into(),
+            payment: self.widgets.payment_entry.get_text().into(),
+            fields: self.widgets.field_entries.iter()
+                .map(|(definition, entry)| (definition.clone(), entry.get_text().into()))
+                .collect()
+        }
+    }
+
+    fn apply_template(&mut self, template: &Template) {
+        self.widgets.label_entry.set_text(&template.label);
+        self.widgets.cycle_interval.set_active(Some(interval_index(&template.interval)));
+        self.widgets.interval_multiplier.set_value(template.interval_multiplier as f64);
+        self.widgets.price_entry.set_value(template.price.unwrap_or(0.));
+        if let Some(color) = template.color.as_ref() {
+            use std::str::FromStr;
+            if let Ok(rgba) = gdk::RGBA::from_str(color) {
+                self.widgets.color_entry.set_rgba(&rgba);
+                self.model.color = Some(color.clone());
+            }
+        }
+        if let Some(tags) = self.model.tags.as_ref() {
+            tags.stream().emit(crate::tags::Msg::SetTags(template.tags.clone()));
+        }
+    }
+
+    /// Marks the invalid fields and only lets the form be saved once it is valid.
+    fn validate(&self) -> Option<Description> {
+        let result = self.input().validate().map(|mut description| {
+            // Values of fields no longer defined in preferences are kept as they were.
+            if let Some(original) = self.model.description.as_ref() {
+                for (name, value) in &original.fields {
+                    if !self.model.preferences.fields.iter().any(|definition| &definition.name == name) {
+                        description.fields.insert(name.clone(), value.clone());
+                    }
+                }
             }
+            description
+        });
+        let errors = result.as_ref().err().cloned().unwrap_or_default();
+        let has = |check: fn(&FormError) -> bool| errors.iter().any(check);
+        mark_invalid(&self.widgets.label_entry, has(|e| *e == FormError::EmptyLabel));
+        mark_invalid(&self.widgets.cycle_interval, has(|e| *e == FormError::MissingInterval));
+        mark_invalid(&self.widgets.interval_multiplier, has(|e| matches!(e, FormError::InvalidMultiplier(_))));
+        mark_invalid(&self.widgets.datepicker, has(|e| matches!(e, FormError::InvalidDate(..))));
+        mark_invalid(&self.widgets.price_entry, has(|e| matches!(e, FormError::NegativePrice(_))));
+        mark_invalid(&self.widgets.url_entry, has(|e| matches!(e, FormError::InvalidUrl(_))));
+        for (definition, entry) in &self.widgets.field_entries {
+            mark_invalid(entry, errors.iter().any(|e| matches!(e, FormError::InvalidField(name, _) if *name == definition.name)));
         }
+
+        let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+        self.widgets.error_label.set_text(&messages.join("\n"));
+        self.widgets.error_label.set_visible(!errors.is_empty());
+        self.widgets.create_btn.set_sensitive(errors.is_empty());
+        result.ok()
+    }
+}
+
+fn interval_index(interval: &Interval) -> u32 {
+    match interval {
+        Interval::Weekly => 0,
+        Interval::Monthly => 1,
+        Interval::Yearly => 2
+    }
+}
+
+fn field_entry(definition: &FieldDefinition) -> gtk::Entry {
+    let entry = gtk::Entry::new();
+    entry.set_size_request(-1, 44);
+    entry.set_margin_start(5);
+    entry.set_margin_end(5);
+    entry.set_margin_top(5);
+    match definition.kind.hint() {
+        "" => entry.set_placeholder_text(Some(&definition.name)),
+        hint => entry.set_placeholder_text(Some(&format!("{} ({})", definition.name, hint)))
+    }
+    entry.set_tooltip_text(Some(&definition.name));
+    entry.set_input_purpose(match definition.kind {
+        FieldKind::Text | FieldKind::Date => gtk::InputPurpose::FreeForm,
+        FieldKind::Number => gtk::InputPurpose::Number,
+        FieldKind::Url => gtk::InputPurpose::Url
+    });
+    entry
+}
+
+fn text_of(view: &gtk::TextView) -> String {
+    view.get_buffer()
+        .and_then(|buffer| buffer.get_text(&buffer.get_start_iter(), &buffer.get_end_iter(), false))
+        .map(|text| text.to_string())
+        .unwrap_or_default()
+}
+
+fn mark_invalid<W: IsA<gtk::Widget>>(widget: &W, invalid: bool) {
+    let context = widget.get_style_context();
+    if invalid {
+        context.add_class("error");
+    } else {
+        context.remove_class("error");
     }
 }
 
 impl Widget for Form {
-    type Root = gtk::Popover;
+    type Root = gtk::Box;
 
     fn root(&self) -> Self::Root {
         self.widgets.container.clone()
@@ -100,7 +270,9 @@ impl Widget for Form {
     fn view(_relm: &Relm<Self>, mut model: Self::Model) -> Self {
         let glade_src = include_str!("../ui/form.ui");
         let builder = gtk::Builder::from_string(glade_src);
-        let container: gtk::Popover = or_panic!(builder.get_object("createPopover"));
+        let container: gtk::Box = or_panic!(builder.get_object("editorPage"));
+        let header: libhandy::HeaderBar = or_panic!(builder.get_object("editorHeader"));
+        let back_btn: gtk::Button = or_panic!(builder.get_object("backButton"));
         let create_container: gtk::Box = or_panic!(builder.get_object("createContainer"));
         let create_btn: gtk::Button = or_panic!(builder.get_object("createButton"));
         let label_entry: gtk::Entry = or_panic!(builder.get_object("labelEntry"));
@@ -109,8 +281,19 @@ impl Widget for Form {
         let cycle_interval: gtk::ComboBox = or_panic!(builder.get_object("intervalSelect"));
         let interval_multiplier: gtk::SpinButton = or_panic!(builder.get_object("intervalMultiplier"));
         let datepicker: gtk::Calendar = or_panic!(builder.get_object("startDate"));
+        let price_entry: gtk::SpinButton = or_panic!(builder.get_object("priceEntry"));
+        let price_label: gtk::Label = or_panic!(builder.get_object("priceLabel"));
+        let url_entry: gtk::Entry = or_panic!(builder.get_object("urlEntry"));
+        let account_entry: gtk::Entry = or_panic!(builder.get_object("accountEntry"));
+        let payment_entry: gtk::Entry = or_panic!(builder.get_object("paymentEntry"));
+        let notes_view: gtk::TextView = or_panic!(builder.get_object("notesView"));
+        let fields_expander: gtk::Expander = or_panic!(builder.get_object("fieldsExpander"));
+        let fields_box: gtk::Box = or_panic!(builder.get_object("fieldsBox"));
+        let error_label: gtk::Label = or_panic!(builder.get_object("errorLabel"));
+        let template_row: gtk::Box = or_panic!(builder.get_object("templateRow"));
+        let template_select: gtk::ComboBoxText = or_panic!(builder.get_object("templateSelect"));
 
-        let tags_entry = relm::init::<crate::tags::Tags>(vec![]).expect("Failed to create tags_entry");
+        let tags_entry = relm::init::<crate::tags::Tags>((model.tag_data.clone(), model.suggestions.clone())).expect("Failed to create tags_entry");
         let stream = _relm.stream().clone();
         tags_entry.stream().observe(move |msg| {
             if let crate::tags::Msg::Changed(a) = msg {
@@ -121,14 +304,45 @@ impl Widget for Form {
 
         connect!(_relm, color_entry, connect_color_set(x), Msg::ColorSet(x.get_rgba()));
         connect!(_relm, create_btn, connect_clicked(_), Msg::CreateClicked);
+        connect!(_relm, back_btn, connect_clicked(_), Msg::Close);
+        connect!(_relm, template_select, connect_changed(_), Msg::TemplateChosen);
+        connect!(_relm, label_entry, connect_changed(_), Msg::Validate);
+        connect!(_relm, cycle_interval, connect_changed(_), Msg::Validate);
+        connect!(_relm, interval_multiplier, connect_value_changed(_), Msg::Validate);
+        connect!(_relm, price_entry, connect_value_changed(_), Msg::Validate);
+        connect!(_relm, datepicker, connect_day_selected(_), Msg::Validate);
+        connect!(_relm, url_entry, connect_changed(_), Msg::Validate);
+
+        let mut field_entries = vec![];
+        for definition in &model.preferences.fields {
+            let entry = field_entry(definition);
+            let value = model.description.as_ref().and_then(|desc| desc.fields.get(&definition.name));
+            if let Some(value) = value {
+                entry.set_text(&value.to_string());
+            }
+            connect!(_relm, entry, connect_changed(_), Msg::Validate);
+            fields_box.pack_start(&entry, false, true, 0);
+            field_entries.push((definition.clone(), entry));
+        }
+        if field_entries.is_empty() {
+            fields_expander.set_no_show_all(true);
+            fields_expander.hide();
+        }
 
         if let Some(desc) = model.description.as_ref() {
+            header.set_title(Some("Edit Cycle"));
             label_entry.set_text(&desc.label);
-            tags_entry.stream().emit(crate::tags::Msg::SetTags(desc.tags.clone()));
             if let Some(description) = &desc.description {
                 description_entry.set_text(&description);
             }
             interval_multiplier.set_value(desc.interval_multiplier as f64);
+            price_entry.set_value(desc.price.unwrap_or(0.));
+            url_entry.set_text(desc.url.as_deref().unwrap_or_default());
+            account_entry.set_text(desc.account.as_deref().unwrap_or_default());
+            payment_entry.set_text(desc.payment.as_deref().unwrap_or_default());
+            if let (Some(notes), Some(buffer)) = (desc.notes.as_ref(), notes_view.get_buffer()) {
+                buffer.set_text(notes);
+            }
             datepicker.set_property_day(desc.start.day() as i32);
             datepicker.set_property_month(desc.start.month() as i32 - 1);
             datepicker.set_property_year(desc.start.year() as i32);
@@ -137,29 +351,63 @@ impl Widget for Form {
                 color_entry.set_rgba(&or_panic!(Res gdk::RGBA::from_str(color)))
             }
 
-            match desc.interval {
-                Interval::Weekly => cycle_interval.set_active(Some(0)),
-                Interval::Monthly => cycle_interval.set_active(Some(1)),
-                Interval::Yearly => cycle_interval.set_active(Some(2))
-            }
+            cycle_interval.set_active(Some(interval_index(&desc.interval)));
+        } else {
+            cycle_interval.set_active(Some(interval_index(&model.preferences.default_interval)));
+        }
+        if !model.preferences.currency.trim().is_empty() {
+            price_label.set_text(&format!("Price ({})", model.preferences.currency.trim()));
+        }
+
+        if model.description.is_some() || model.templates.is_empty() {
+            template_row.set_no_show_all(true);
+            template_row.hide();
+        }
+        for template in &model.templates {
+            template_select.append_text(&template.label);
         }
 
         model.tags = Some(tags_entry);
-        container.set_relative_to(Some(&model.btn));
         container.show_all();
+        if model.host.is_narrow() {
+            use libhandy::DeckExt;
+            model.host.deck.add(&container);
+            model.host.deck.set_visible_child(&container);
+        } else {
+            let dialog = gtk::Dialog::new();
+            dialog.set_transient_for(Some(&model.host.window));
+            dialog.set_modal(true);
+            dialog.set_default_size(480, 640);
+            container.remove(&header);
+            dialog.set_titlebar(Some(&header));
+            dialog.get_content_area().pack_start(&container, true, true, 0);
+            dialog.show_all();
+            model.dialog = Some(dialog);
+        }
 
-        Form {
+        let form = Form {
             model,
             widgets: Widgets {
                 container,
+                header,
                 create_btn,
                 description_entry,
                 label_entry,
                 cycle_interval,
                 interval_multiplier,
+                price_entry,
                 datepicker,
                 color_entry,
+                url_entry,
+                account_entry,
+                payment_entry,
+                notes_view,
+                field_entries,
+                error_label,
+                template_select,
             },
-        }
+        };
+        form.validate();
+        form
     }
 }
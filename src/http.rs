@@ -0,0 +1,47 @@
+use std::time::Duration;
+
+use crate::caldav::{Account, CalDavError, Request, Response, Transport};
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Talks to a real server over HTTP(S).
+pub struct HttpTransport {
+    agent: ureq::Agent,
+    authorization: Option<String>
+}
+
+impl HttpTransport {
+
+    pub fn new(account: &Account) -> HttpTransport {
+        let authorization = account.username.as_ref().map(|username| {
+            let credentials = format!("{}:{}", username, account.password.as_deref().unwrap_or_default());
+            format!("Basic {}", base64::encode(credentials))
+        });
+        HttpTransport {
+            agent: ureq::AgentBuilder::new().timeout(TIMEOUT).build(),
+            authorization
+        }
+    }
+}
+
+impl Transport for HttpTransport {
+
+    fn send(&self, request: Request) -> Result<Response, CalDavError> {
+        let mut call = self.agent.request(request.method, &request.url);
+        for (name, value) in &request.headers {
+            call = call.set(name, value);
+        }
+        if let Some(authorization) = self.authorization.as_ref() {
+            call = call.set("Authorization", authorization);
+        }
+        let response = match call.send_string(&request.body) {
+            Ok(response) => response,
+            Err(ureq::Error::Status(_, response)) => response,
+            Err(err) => return Err(CalDavError::Transport(err.to_string()))
+        };
+        let status = response.status();
+        let etag = response.header("ETag").map(String::from);
+        let body = response.into_string().map_err(|err| CalDavError::Transport(err.to_string()))?;
+        Ok(Response { status, etag, body })
+    }
+}
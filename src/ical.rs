@@ -0,0 +1,271 @@
+use uuid::Uuid;
+use chrono::{NaiveDate, Utc};
+
+use crate::description::{Description, Interval};
+use crate::fields::{FieldKind, FieldValue};
+
+const PRODID: &str = "-//perjoratic//Cycles//EN";
+/// Lines longer than this many bytes are folded, RFC 5545 3.1.
+const LINE_LIMIT: usize = 75;
+
+/// Writes a cycle as an all-day recurring VEVENT. Price, color, notes,
+/// account, payment and custom fields have no iCalendar property, so they
+/// go in `X-CYCLES-` ones other clients ignore.
+pub fn to_ical(id: Uuid, description: &Description) -> String {
+    let freq = match description.interval {
+        Interval::Weekly => "WEEKLY",
+        Interval::Monthly => "MONTHLY",
+        Interval::Yearly => "YEARLY"
+    };
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:{}", PRODID),
+        "BEGIN:VEVENT".to_string(),
+        format!("UID:{}", id),
+        format!("DTSTAMP:{}", Utc::now().format("%Y%m%dT%H%M%SZ")),
+        format!("DTSTART;VALUE=DATE:{}", description.start.format("%Y%m%d")),
+        format!("RRULE:FREQ={};INTERVAL={}", freq, description.interval_multiplier),
+        format!("SUMMARY:{}", escape(&description.label))
+    ];
+    if let Some(text) = description.description.as_ref() {
+        lines.push(format!("DESCRIPTION:{}", escape(text)));
+    }
+    if !description.tags.is_empty() {
+        let tags: Vec<String> = description.tags.iter().map(|tag| escape(tag)).collect();
+        lines.push(format!("CATEGORIES:{}", tags.join(",")));
+    }
+    if let Some(price) = description.price {
+        lines.push(format!("X-CYCLES-PRICE:{}", price));
+    }
+    if let Some(color) = description.color.as_ref() {
+        lines.push(format!("X-CYCLES-COLOR:{}", escape(color)));
+    }
+    if let Some(url) = description.url.as_ref() {
+        lines.push(format!("URL:{}", url));
+    }
+    let extra = [
+        ("X-CYCLES-NOTES", &description.notes),
+        ("X-CYCLES-ACCOUNT", &description.account),
+        ("X-CYCLES-PAYMENT", &description.payment)
+    ];
+    for (name, value) in extra.iter() {
+        if let Some(value) = value.as_ref() {
+            lines.push(format!("{}:{}", name, escape(value)));
+        }
+    }
+    for (name, value) in &description.fields {
+        lines.push(format!("X-CYCLES-FIELD;X-NAME=\"{}\";X-KIND={}:{}",
+            encode_param(name), value.kind().nick(), escape(&value.to_string())));
+    }
+    lines.push("END:VEVENT".to_string());
+    lines.push("END:VCALENDAR".to_string());
+    lines.iter().map(|line| fold(line)).collect::<Vec<_>>().join("\r\n") + "\r\n"
+}
+
+/// Reads the first VEVENT or VTODO back into a cycle. Events from other
+/// clients without a start date or a UUID UID are skipped.
+pub fn from_ical(data: &str) -> Option<(Uuid, Description)> {
+    let mut id = None;
+    let mut start = None;
+    let mut interval = (Interval::Monthly, 1);
+    let mut description = Description {
+        label: String::new(),
+        description: None,
+        color: None,
+        tags: vec![],
+        start: NaiveDate::from_ymd(1970, 1, 1),
+        interval: Interval::Monthly,
+        interval_multiplier: 1,
+        price: None,
+        notes: None,
+        url: None,
+        account: None,
+        payment: None,
+        fields: Default::default()
+    };
+    let mut inside = false;
+    for line in unfold(data) {
+        let (name, value) = match find_unquoted(&line, ':') {
+            Some(at) => (&line[..at], &line[at + 1..]),
+            None => continue
+        };
+        let (name, params) = match find_unquoted(name, ';') {
+            Some(at) => (&name[..at], &name[at + 1..]),
+            None => (name, "")
+        };
+        match (name.to_ascii_uppercase().as_str(), inside) {
+            ("BEGIN", false) if value == "VEVENT" || value == "VTODO" => inside = true,
+            ("END", true) if value == "VEVENT" || value == "VTODO" => break,
+            ("UID", true) => id = Uuid::parse_str(value.trim()).ok(),
+            ("SUMMARY", true) => description.label = unescape(value),
+            ("DESCRIPTION", true) => description.description = Some(unescape(value)),
+            ("CATEGORIES", true) => description.tags = split_list(value),
+            ("X-CYCLES-PRICE", true) => description.price = value.trim().parse().ok(),
+            ("X-CYCLES-COLOR", true) => description.color = Some(unescape(value)),
+            ("URL", true) => description.url = Some(value.trim().to_string()),
+            ("X-CYCLES-NOTES", true) => description.notes = Some(unescape(value)),
+            ("X-CYCLES-ACCOUNT", true) => description.account = Some(unescape(value)),
+            ("X-CYCLES-PAYMENT", true) => description.payment = Some(unescape(value)),
+            ("X-CYCLES-FIELD", true) => {
+                let kind = FieldKind::from_nick(&param(params, "X-KIND").unwrap_or_default());
+                if let (Some(name), Some(value)) = (param(params, "X-NAME"), FieldValue::parse(kind, &unescape(value))) {
+                    description.fields.insert(name, value);
+                }
+            },
+            ("DTSTART", true) => start = parse_date(value, params),
+            ("RRULE", true) => interval = parse_rule(value),
+            _ => {}
+        }
+    }
+    description.start = start?;
+    description.interval = interval.0;
+    description.interval_multiplier = interval.1;
+    Some((id?, description))
+}
+
+/// Where `needle` first shows up outside a quoted parameter value.
+fn find_unquoted(text: &str, needle: char) -> Option<usize> {
+    let mut quoted = false;
+    for (at, c) in text.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c == needle && !quoted => return Some(at),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The value of parameter `name` in `params`, without its quotes.
+fn param(params: &str, name: &str) -> Option<String> {
+    let mut rest = params;
+    loop {
+        let end = find_unquoted(rest, ';').unwrap_or(rest.len());
+        if let Some((key, value)) = rest[..end].split_once('=') {
+            if key.eq_ignore_ascii_case(name) {
+                return Some(decode_param(value.trim_matches('"')));
+            }
+        }
+        if end == rest.len() {
+            return None;
+        }
+        rest = &rest[end + 1..];
+    }
+}
+
+/// Parameter values can't hold quotes or line breaks, RFC 6868 writes
+/// them as `^'` and `^n`, and `^` itself as `^^`.
+fn encode_param(value: &str) -> String {
+    value.replace('^', "^^").replace('"', "^'").replace('\n', "^n")
+}
+
+fn decode_param(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('^', Some('^')) => out.push('^'),
+            ('^', Some('\'')) => out.push('"'),
+            ('^', Some('n')) => out.push('\n'),
+            _ => {
+                out.push(c);
+                continue;
+            }
+        }
+        chars.next();
+    }
+    out
+}
+
+fn parse_date(value: &str, _params: &str) -> Option<NaiveDate> {
+    // Both DATE and DATE-TIME values start with the date.
+    NaiveDate::parse_from_str(value.trim().get(..8)?, "%Y%m%d").ok()
+}
+
+fn parse_rule(value: &str) -> (Interval, i64) {
+    let mut interval = Interval::Monthly;
+    let mut multiplier = 1;
+    for part in value.split(';') {
+        match part.split_once('=') {
+            Some(("FREQ", "WEEKLY")) => interval = Interval::Weekly,
+            Some(("FREQ", "MONTHLY")) => interval = Interval::Monthly,
+            Some(("FREQ", "YEARLY")) => interval = Interval::Yearly,
+            // A daily rule has no cycle equivalent, the closest is every week.
+            Some(("FREQ", "DAILY")) => interval = Interval::Weekly,
+            Some(("INTERVAL", count)) => multiplier = count.parse().unwrap_or(1).max(1),
+            _ => {}
+        }
+    }
+    (interval, multiplier)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n') | Some('N') => out.push('\n'),
+                Some(other) => out.push(other),
+                None => {}
+            },
+            c => out.push(c)
+        }
+    }
+    out
+}
+
+/// Splits a comma separated value, leaving escaped commas alone.
+fn split_list(value: &str) -> Vec<String> {
+    let mut items = vec![];
+    let mut current = String::new();
+    let mut escaped = false;
+    for c in value.chars() {
+        match c {
+            ',' if !escaped => items.push(std::mem::take(&mut current)),
+            '\\' if !escaped => {
+                escaped = true;
+                current.push(c);
+                continue;
+            },
+            c => current.push(c)
+        }
+        escaped = false;
+    }
+    items.push(current);
+    items.iter().map(|item| unescape(item)).filter(|item| !item.is_empty()).collect()
+}
+
+fn fold(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > LINE_LIMIT {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out
+}
+
+fn unfold(data: &str) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+    for line in data.split('\n') {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        match (line.strip_prefix(' ').or_else(|| line.strip_prefix('\t')), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(line.to_string())
+        }
+    }
+    lines
+}
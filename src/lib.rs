@@ -1,7 +1,10 @@
 #![allow(non_snake_case, dead_code, unused_imports)]
 #[macro_use]
 mod macros;
+mod crypto;
 mod cache;
+mod backup;
+mod check;
 mod cycle;
 mod form;
 mod menu;
@@ -11,6 +14,48 @@ mod window;
 mod badge;
 mod tag;
 mod tags;
+mod tag_list;
+mod filter;
+mod validation;
+mod template;
+mod fields;
+mod attachment;
+mod order;
+mod trash;
+mod archive;
+mod restore;
+mod diagnostics;
+mod tag_manager;
+mod detail;
+mod undo;
+mod preferences;
+mod preferences_window;
+mod storage;
+mod unlock;
+mod passphrase;
+mod sync;
+mod conflicts;
+mod ical;
+mod caldav;
+mod http;
 
 pub use self::window::Win as Window;
-pub use self::description::{ Interval, Description, RenderArgs };
+pub use self::description::{ Interval, Description, RenderArgs, Phase, TagStyle };
+pub use self::undo::{ Action, UndoStack };
+pub use self::crypto::{ DecryptError, Key, Lock };
+pub use self::cache::{ Database, Export, ExportError, Merged, Slot, Trashed, read_export, read_protected_export };
+pub use self::backup::{ Backup, Backups, BackupSettings, Frequency };
+pub use self::check::{ Issue, Problem, Quarantined, Report };
+pub use self::tag_list::{ TagList, normalize as normalize_tag };
+pub use self::filter::{ Filter, TagMode, TagTotal, tag_totals };
+pub use self::validation::{ FormError, FormInput };
+pub use self::template::Template;
+pub use self::attachment::{ Attachment, Blob, format_size };
+pub use self::fields::{ FieldDefinition, FieldKind, FieldValue, definitions as field_definitions, set_definitions as set_field_definitions };
+pub use self::order::Ordering;
+pub use self::preferences::{ Preferences, SortOrder, Theme };
+pub use self::storage::{ Storage, StorageError, Backend, FileStore, MemoryStore, migrate };
+pub use self::sync::{ Change, Conflict, Record, Summary, SyncError, SyncFile, merge as merge_sync, run as run_sync };
+pub use self::ical::{ to_ical, from_ical };
+pub use self::caldav::{ Account, CalDavError, Collection, Put, Remote, Replacing, Request, Response, Transport, sync as sync_caldav };
+pub use self::http::HttpTransport;
@@ -1,6 +1,110 @@
 #![allow(non_snake_case)]
 use relm::Widget;
 
+use std::process;
+
+const PASSPHRASE_VAR: &str = "CYCLES_PASSPHRASE";
+const USAGE: &str = "Usage: Cycles [--check | --repair | --migrate <sled|file> <sled|file> | --sync <folder> | --caldav]";
+
 fn main() {
-    Cycles::Window::run(()).expect("Win::run failed");
+    match std::env::args().nth(1).as_deref() {
+        None => Cycles::Window::run(()).expect("Win::run failed"),
+        Some("--check") => {
+            let report = open_database().check();
+            print_report(&report);
+            if !report.is_clean() {
+                process::exit(1);
+            }
+        },
+        Some("--repair") => {
+            let report = open_database().repair();
+            print_report(&report);
+            if !report.is_clean() {
+                println!("Moved {} records to quarantine", report.issues.len());
+            }
+        },
+        Some("--migrate") => {
+            let backend = |n| std::env::args().nth(n).as_deref().and_then(Cycles::Backend::parse);
+            let (from, to) = match (backend(2), backend(3)) {
+                (Some(from), Some(to)) if from != to => (from, to),
+                _ => {
+                    eprintln!("{}", USAGE);
+                    process::exit(2);
+                }
+            };
+            if Cycles::Database::open_default().is_encrypted() {
+                eprintln!("The database is encrypted, remove the passphrase before migrating");
+                process::exit(1);
+            }
+            let open = |backend: Cycles::Backend| backend.open().unwrap_or_else(|err| {
+                eprintln!("{}", err);
+                process::exit(1);
+            });
+            let count = Cycles::migrate(&*open(from), &*open(to));
+            println!("Copied {} cycles from {} to {}", count, from.name(), to.name());
+        },
+        Some("--sync") => {
+            let folder = match std::env::args().nth(2) {
+                Some(folder) => std::path::PathBuf::from(folder),
+                None => {
+                    eprintln!("{}", USAGE);
+                    process::exit(2);
+                }
+            };
+            match Cycles::run_sync(&Cycles::Database::new(), &folder) {
+                Ok(summary) => {
+                    println!("Synced with {} devices, {} cycles changed, {} conflicts",
+                        summary.devices, summary.changed.len(), summary.conflicts);
+                },
+                Err(err) => {
+                    eprintln!("{}", err);
+                    process::exit(1);
+                }
+            }
+        },
+        Some("--caldav") => {
+            let account = match Cycles::Account::from_env() {
+                Some(account) => account,
+                None => {
+                    eprintln!("Set CYCLES_CALDAV_URL to the collection to sync with");
+                    process::exit(2);
+                }
+            };
+            let collection = Cycles::Collection::new(&account.url, Cycles::HttpTransport::new(&account));
+            match Cycles::sync_caldav(&Cycles::Database::new(), &collection) {
+                Ok(summary) => {
+                    println!("Synced with {}, {} cycles changed, {} conflicts",
+                        account.url, summary.changed.len(), summary.conflicts);
+                },
+                Err(err) => {
+                    eprintln!("{}", err);
+                    process::exit(1);
+                }
+            }
+        },
+        Some(_) => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    }
+}
+
+/// Opens the database, unlocking it with `CYCLES_PASSPHRASE` when it is encrypted.
+fn open_database() -> Cycles::Database {
+    let mut db = Cycles::Database::new();
+    if db.is_locked() {
+        let passphrase = std::env::var(PASSPHRASE_VAR).unwrap_or_default();
+        if !db.unlock(&passphrase) {
+            eprintln!("The database is encrypted, set {} to its passphrase", PASSPHRASE_VAR);
+            process::exit(2);
+        }
+    }
+    db
+}
+
+fn print_report(report: &Cycles::Report) {
+    for issue in &report.issues {
+        println!("{}", issue);
+    }
+    println!("{}", report.summary());
 }
@@ -7,7 +7,15 @@ pub enum Msg {
     Quit,
     About,
     Import,
-    Export
+    Export,
+    Trash,
+    Archive,
+    Backups,
+    Diagnostics,
+    Tags,
+    Preferences,
+    Sync,
+    SyncFolder
 }
 
 pub struct Widgets {
@@ -15,7 +23,15 @@ pub struct Widgets {
     about_btn: gtk::Button,
     quit_btn: gtk::Button,
     import_btn: gtk::Button,
-    export_btn: gtk::Button
+    export_btn: gtk::Button,
+    trash_btn: gtk::Button,
+    archive_btn: gtk::Button,
+    backups_btn: gtk::Button,
+    diagnostics_btn: gtk::Button,
+    tags_btn: gtk::Button,
+    preferences_btn: gtk::Button,
+    sync_btn: gtk::Button,
+    sync_folder_btn: gtk::Button
 }
 
 pub struct Menu {
@@ -48,7 +64,15 @@ impl Update for Menu {
             Msg::Quit => {},
             Msg::About => {},
             Msg::Import => {},
-            Msg::Export => {}
+            Msg::Export => {},
+            Msg::Trash => {},
+            Msg::Archive => {},
+            Msg::Backups => {},
+            Msg::Diagnostics => {},
+            Msg::Tags => {},
+            Msg::Preferences => {},
+            Msg::Sync => {},
+            Msg::SyncFolder => {}
         }
     }
 }
@@ -70,11 +94,27 @@ impl Widget for Menu {
         let about_btn: gtk::Button = or_panic!(builder.get_object("aboutButton"));
         let export_btn: gtk::Button = or_panic!(builder.get_object("exportButton"));
         let import_btn: gtk::Button = or_panic!(builder.get_object("importButton"));
+        let trash_btn: gtk::Button = or_panic!(builder.get_object("trashButton"));
+        let archive_btn: gtk::Button = or_panic!(builder.get_object("archiveButton"));
+        let backups_btn: gtk::Button = or_panic!(builder.get_object("backupsButton"));
+        let diagnostics_btn: gtk::Button = or_panic!(builder.get_object("diagnosticsButton"));
+        let tags_btn: gtk::Button = or_panic!(builder.get_object("tagsButton"));
+        let preferences_btn: gtk::Button = or_panic!(builder.get_object("preferencesButton"));
+        let sync_btn: gtk::Button = or_panic!(builder.get_object("syncButton"));
+        let sync_folder_btn: gtk::Button = or_panic!(builder.get_object("syncFolderButton"));
 
         connect!(_relm, quit_btn, connect_clicked(_), Msg::Quit);
         connect!(_relm, about_btn, connect_clicked(_), Msg::About);
         connect!(_relm, import_btn, connect_clicked(_), Msg::Import);
         connect!(_relm, export_btn, connect_clicked(_), Msg::Export);
+        connect!(_relm, trash_btn, connect_clicked(_), Msg::Trash);
+        connect!(_relm, archive_btn, connect_clicked(_), Msg::Archive);
+        connect!(_relm, backups_btn, connect_clicked(_), Msg::Backups);
+        connect!(_relm, diagnostics_btn, connect_clicked(_), Msg::Diagnostics);
+        connect!(_relm, tags_btn, connect_clicked(_), Msg::Tags);
+        connect!(_relm, preferences_btn, connect_clicked(_), Msg::Preferences);
+        connect!(_relm, sync_btn, connect_clicked(_), Msg::Sync);
+        connect!(_relm, sync_folder_btn, connect_clicked(_), Msg::SyncFolder);
         container.set_relative_to(Some(&model.btn));
         container.show_all();
 
@@ -85,7 +125,15 @@ impl Widget for Menu {
                 quit_btn,
                 about_btn,
                 import_btn,
-                export_btn
+                export_btn,
+                trash_btn,
+                archive_btn,
+                backups_btn,
+                diagnostics_btn,
+                tags_btn,
+                preferences_btn,
+                sync_btn,
+                sync_folder_btn
             },
         }
     }
@@ -0,0 +1,94 @@
+use uuid::Uuid;
+use serde::{ Serialize, Deserialize };
+
+use std::collections::HashSet;
+
+use crate::cache::Database;
+
+const SETTINGS_KEY: &str = "order";
+
+/// The manual order of the main list: pinned cycles first, then the rest.
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Ordering {
+    pub pinned: Vec<Uuid>,
+    pub order: Vec<Uuid>
+}
+
+impl Ordering {
+
+    pub fn load(db: &Database) -> Ordering {
+        db.setting(SETTINGS_KEY).unwrap_or_default()
+    }
+
+    pub fn save(&self, db: &Database) {
+        db.set_setting(SETTINGS_KEY, self);
+    }
+
+    pub fn is_pinned(&self, id: Uuid) -> bool {
+        self.pinned.contains(&id)
+    }
+
+    /// Appends cycles the ordering doesn't know yet and forgets the ones
+    /// that are gone.
+    pub fn sync<I: IntoIterator<Item=Uuid>>(&mut self, ids: I) {
+        let ids: Vec<Uuid> = ids.into_iter().collect();
+        let live: HashSet<&Uuid> = ids.iter().collect();
+        self.pinned.retain(|id| live.contains(id));
+        self.order.retain(|id| live.contains(id));
+        for id in ids {
+            if !self.contains(id) {
+                self.order.push(id);
+            }
+        }
+    }
+
+    /// Pinning moves a cycle to the bottom of the pinned group, unpinning
+    /// to the top of the rest.
+    pub fn set_pinned(&mut self, id: Uuid, pinned: bool) {
+        self.remove(id);
+        if pinned {
+            self.pinned.push(id);
+        } else {
+            self.order.insert(0, id);
+        }
+    }
+
+    /// Moves `id` to where `target` is, joining the target's group.
+    pub fn move_before(&mut self, id: Uuid, target: Uuid) {
+        if id == target || !self.contains(target) {
+            return;
+        }
+        self.remove(id);
+        let group = if self.is_pinned(target) { &mut self.pinned } else { &mut self.order };
+        let index = group.iter().position(|x| *x == target).unwrap_or(group.len());
+        group.insert(index, id);
+    }
+
+    /// Every known cycle, pinned ones first.
+    pub fn ids(&self) -> impl Iterator<Item=&Uuid> {
+        self.pinned.iter().chain(self.order.iter())
+    }
+
+    /// Adds what `other` knows and this ordering doesn't, keeping it pinned if it was.
+    pub fn merge(&mut self, other: &Ordering) {
+        for id in &other.pinned {
+            if !self.contains(*id) {
+                self.pinned.push(*id);
+            }
+        }
+        for id in &other.order {
+            if !self.contains(*id) {
+                self.order.push(*id);
+            }
+        }
+    }
+
+    fn contains(&self, id: Uuid) -> bool {
+        self.pinned.contains(&id) || self.order.contains(&id)
+    }
+
+    fn remove(&mut self, id: Uuid) {
+        self.pinned.retain(|x| *x != id);
+        self.order.retain(|x| *x != id);
+    }
+}
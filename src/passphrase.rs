@@ -0,0 +1,137 @@
+use gtk::prelude::*;
+use relm::{connect, Relm, Widget, Update};
+use relm_derive::Msg;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Encryption {
+    /// The storage backend keeps cycles readable, see `Database::can_encrypt`.
+    Unavailable,
+    Off,
+    On
+}
+
+#[derive(Msg)]
+pub enum Msg {
+    Response(gtk::ResponseType),
+    Remove,
+    /// The current passphrase when encrypted, and the new one or `None` to decrypt.
+    Submit(Option<String>, Option<String>),
+    Rejected,
+    Done
+}
+
+pub struct Widgets {
+    container: gtk::Dialog,
+    current: gtk::Entry,
+    new: gtk::Entry,
+    confirm: gtk::Entry,
+    error: gtk::Label
+}
+
+/// Sets, changes or removes the database passphrase.
+pub struct Passphrase {
+    model: Model,
+    widgets: Widgets,
+}
+
+pub struct Model {
+    relm: Relm<Passphrase>,
+    win: libhandy::ApplicationWindow,
+    encryption: Encryption
+}
+
+impl Passphrase {
+
+    fn current(&self) -> Option<String> {
+        match self.model.encryption {
+            Encryption::On => Some(self.widgets.current.get_text().to_string()),
+            _ => None
+        }
+    }
+
+    fn show_error(&self, message: &str) {
+        self.widgets.error.set_text(message);
+        self.widgets.error.show();
+    }
+}
+
+impl Update for Passphrase {
+    type Model = Model;
+    type ModelParam = (libhandy::ApplicationWindow, Encryption);
+    type Msg = Msg;
+
+    fn model(relm: &Relm<Self>, (win, encryption): (libhandy::ApplicationWindow, Encryption)) -> Model {
+        Model {
+            relm: relm.clone(),
+            win, encryption
+        }
+    }
+
+    fn update(&mut self, event: Msg) {
+        match event {
+            Msg::Response(gtk::ResponseType::Accept) => {
+                let new = self.widgets.new.get_text().to_string();
+                if new.is_empty() {
+                    self.show_error("Enter a new passphrase");
+                } else if new != self.widgets.confirm.get_text().as_str() {
+                    self.show_error("The passphrases don't match");
+                } else {
+                    self.model.relm.stream().emit(Msg::Submit(self.current(), Some(new)));
+                }
+            },
+            Msg::Response(_) | Msg::Done => {
+                self.widgets.container.close();
+            },
+            Msg::Remove => {
+                self.model.relm.stream().emit(Msg::Submit(self.current(), None));
+            },
+            Msg::Rejected => {
+                self.show_error("Wrong passphrase");
+                self.widgets.current.set_text("");
+                self.widgets.current.grab_focus();
+            },
+            Msg::Submit(..) => {}
+        }
+    }
+}
+
+impl Widget for Passphrase {
+    type Root = gtk::Dialog;
+
+    fn root(&self) -> Self::Root {
+        self.widgets.container.clone()
+    }
+
+    fn view(relm: &Relm<Self>, model: Self::Model) -> Self {
+        let glade_src = include_str!("../ui/passphrase.ui");
+        let builder = gtk::Builder::from_string(glade_src);
+        let container: gtk::Dialog = or_panic!(builder.get_object("passphraseDialog"));
+        let current: gtk::Entry = or_panic!(builder.get_object("currentEntry"));
+        let new: gtk::Entry = or_panic!(builder.get_object("newEntry"));
+        let confirm: gtk::Entry = or_panic!(builder.get_object("confirmEntry"));
+        let error: gtk::Label = or_panic!(builder.get_object("passphraseError"));
+        let remove_btn: gtk::Button = or_panic!(builder.get_object("removeButton"));
+
+        if model.encryption != Encryption::On {
+            current.hide();
+            remove_btn.hide();
+        }
+
+        connect!(relm, container, connect_response(_, response), Msg::Response(response));
+        connect!(relm, remove_btn, connect_clicked(_), Msg::Remove);
+
+        container.set_transient_for(Some(&model.win));
+        container.show();
+
+        Passphrase {
+            model,
+            widgets: Widgets {
+                container,
+                current,
+                new,
+                confirm,
+                error
+            },
+        }
+    }
+}
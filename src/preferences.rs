@@ -0,0 +1,154 @@
+use chrono::NaiveDate;
+use chrono::format::{Item, StrftimeItems};
+
+use std::cmp;
+
+use crate::description::{Description, Interval};
+use crate::fields::FieldDefinition;
+
+/// The GSettings schema the preferences window reads and writes.
+pub const SCHEMA_ID: &str = "com.perjoratic.Cycles";
+
+const DEFAULT_DATE_FORMAT: &str = "%m/%d";
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SortOrder {
+    /// Pinned cycles first, then the order they were dragged into.
+    Manual,
+    Label,
+    NextRenewal,
+    Price
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Theme {
+    System,
+    Light,
+    Dark
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Preferences {
+    pub default_interval: Interval,
+    /// Days before a renewal when a cycle is flagged as due soon.
+    pub reminder_days: u32,
+    /// Appended to prices, left out when empty.
+    pub currency: String,
+    /// A `strftime` format used for period dates.
+    pub date_format: String,
+    pub sort_order: SortOrder,
+    pub theme: Theme,
+    pub backup_retention: usize,
+    /// Custom fields, kept in the database rather than GSettings so exports carry them.
+    pub fields: Vec<FieldDefinition>
+}
+
+impl Default for Preferences {
+    fn default() -> Preferences {
+        Preferences {
+            default_interval: Interval::Monthly,
+            reminder_days: 3,
+            currency: String::new(),
+            date_format: DEFAULT_DATE_FORMAT.into(),
+            sort_order: SortOrder::Manual,
+            theme: Theme::System,
+            backup_retention: 7,
+            fields: vec![]
+        }
+    }
+}
+
+impl Preferences {
+
+    /// Formats `date` with the chosen format, falling back to the default
+    /// when the format doesn't parse.
+    pub fn format_date(&self, date: NaiveDate) -> String {
+        let format = if is_valid_format(&self.date_format) { &self.date_format } else { DEFAULT_DATE_FORMAT };
+        date.format(format).to_string()
+    }
+
+    pub fn format_price(&self, amount: f64) -> String {
+        match self.currency.trim() {
+            "" => format!("{:.2}", amount),
+            currency => format!("{:.2} {}", amount, currency)
+        }
+    }
+
+    /// Whether the next payment is at most `reminder_days` away. A cycle
+    /// that hasn't started yet counts too, since the first payment is due on
+    /// its start date.
+    pub fn is_due_soon(&self, description: &Description, time: Option<NaiveDate>) -> bool {
+        description.render_arguments(time).remaining_days <= self.reminder_days as i64
+    }
+
+    /// Compares two cycles for the chosen sort order. `Manual` keeps them
+    /// equal so a stable sort leaves the saved order alone.
+    pub fn compare(&self, a: &Description, b: &Description, time: NaiveDate) -> cmp::Ordering {
+        match self.sort_order {
+            SortOrder::Manual => cmp::Ordering::Equal,
+            SortOrder::Label => a.label.to_lowercase().cmp(&b.label.to_lowercase()),
+            SortOrder::NextRenewal => a.render_arguments(Some(time)).end_date.cmp(&b.render_arguments(Some(time)).end_date),
+            SortOrder::Price => b.price.unwrap_or(0.).partial_cmp(&a.price.unwrap_or(0.)).unwrap_or(cmp::Ordering::Equal)
+        }
+    }
+}
+
+pub fn is_valid_format(format: &str) -> bool {
+    !format.is_empty() && StrftimeItems::new(format).all(|item| item != Item::Error)
+}
+
+impl SortOrder {
+
+    pub fn nick(self) -> &'static str {
+        match self {
+            SortOrder::Manual => "manual",
+            SortOrder::Label => "label",
+            SortOrder::NextRenewal => "next-renewal",
+            SortOrder::Price => "price"
+        }
+    }
+
+    pub fn from_nick(nick: &str) -> SortOrder {
+        match nick {
+            "label" => SortOrder::Label,
+            "next-renewal" => SortOrder::NextRenewal,
+            "price" => SortOrder::Price,
+            _ => SortOrder::Manual
+        }
+    }
+}
+
+impl Theme {
+
+    pub fn nick(self) -> &'static str {
+        match self {
+            Theme::System => "system",
+            Theme::Light => "light",
+            Theme::Dark => "dark"
+        }
+    }
+
+    pub fn from_nick(nick: &str) -> Theme {
+        match nick {
+            "light" => Theme::Light,
+            "dark" => Theme::Dark,
+            _ => Theme::System
+        }
+    }
+}
+
+pub fn interval_nick(interval: &Interval) -> &'static str {
+    match interval {
+        Interval::Weekly => "weekly",
+        Interval::Monthly => "monthly",
+        Interval::Yearly => "yearly"
+    }
+}
+
+pub fn interval_from_nick(nick: &str) -> Interval {
+    match nick {
+        "weekly" => Interval::Weekly,
+        "yearly" => Interval::Yearly,
+        _ => Interval::Monthly
+    }
+}
@@ -0,0 +1,268 @@
+use gtk::prelude::*;
+use gio::prelude::*;
+use relm::{connect, Relm, Widget, Update};
+use relm_derive::Msg;
+
+use crate::fields::{FieldDefinition, FieldKind};
+use crate::passphrase::Encryption;
+use crate::preferences::{self, Preferences, SortOrder, Theme, SCHEMA_ID};
+
+/// Opens the GSettings schema, or `None` when it isn't installed.
+pub fn open_settings() -> Option<gio::Settings> {
+    let schema = gio::SettingsSchemaSource::get_default()?.lookup(SCHEMA_ID, true)?;
+    Some(gio::Settings::new_full(&schema, None::<&gio::SettingsBackend>, None))
+}
+
+pub fn read_preferences(settings: &gio::Settings) -> Preferences {
+    let string = |key| settings.get_string(key).map(|value| value.to_string()).unwrap_or_default();
+    Preferences {
+        default_interval: preferences::interval_from_nick(&string("default-interval")),
+        reminder_days: settings.get_uint("reminder-days"),
+        currency: string("currency"),
+        date_format: string("date-format"),
+        sort_order: SortOrder::from_nick(&string("sort-order")),
+        theme: Theme::from_nick(&string("theme")),
+        backup_retention: settings.get_uint("backup-retention") as usize,
+        fields: vec![]
+    }
+}
+
+pub fn write_preferences(settings: &gio::Settings, prefs: &Preferences) {
+    or_panic!(Res settings.set_string("default-interval", preferences::interval_nick(&prefs.default_interval)));
+    or_panic!(Res settings.set_uint("reminder-days", prefs.reminder_days));
+    or_panic!(Res settings.set_string("currency", &prefs.currency));
+    or_panic!(Res settings.set_string("date-format", &prefs.date_format));
+    or_panic!(Res settings.set_string("sort-order", prefs.sort_order.nick()));
+    or_panic!(Res settings.set_string("theme", prefs.theme.nick()));
+    or_panic!(Res settings.set_uint("backup-retention", prefs.backup_retention as u32));
+}
+
+pub fn apply_theme(theme: Theme) {
+    if let Some(settings) = gtk::Settings::get_default() {
+        match theme {
+            Theme::System => settings.reset_property("gtk-application-prefer-dark-theme"),
+            Theme::Light => settings.set_property_gtk_application_prefer_dark_theme(false),
+            Theme::Dark => settings.set_property_gtk_application_prefer_dark_theme(true)
+        }
+    }
+}
+
+#[derive(Msg)]
+pub enum Msg {
+    DefaultInterval(Option<String>),
+    Reminder(u32),
+    Currency(String),
+    DateFormat(String),
+    SortOrder(Option<String>),
+    Theme(Option<String>),
+    Retention(usize),
+    Changed(Preferences),
+    Encryption(Encryption),
+    Passphrase,
+    AddField,
+    RemoveField(String)
+}
+
+pub struct Widgets {
+    container: libhandy::PreferencesWindow,
+    date_format: gtk::Entry,
+    encryption_row: libhandy::ActionRow,
+    passphrase_btn: gtk::Button,
+    fields_group: libhandy::PreferencesGroup,
+    field_name: gtk::Entry,
+    field_kind: gtk::ComboBoxText
+}
+
+pub struct PreferencesWindow {
+    model: Model,
+    widgets: Widgets,
+}
+
+pub struct Model {
+    relm: Relm<PreferencesWindow>,
+    win: libhandy::ApplicationWindow,
+    preferences: Preferences,
+    encryption: Encryption,
+    field_rows: Vec<(String, libhandy::ActionRow)>
+}
+
+impl PreferencesWindow {
+
+    fn show_encryption(&self) {
+        use libhandy::ActionRowExt;
+        let (subtitle, label) = match self.model.encryption {
+            Encryption::Unavailable => ("Not available with the file storage backend", "Set Passphrase…"),
+            Encryption::Off => ("Cycles are stored in clear text", "Set Passphrase…"),
+            Encryption::On => ("Cycles are encrypted with a passphrase", "Change Passphrase…")
+        };
+        self.widgets.encryption_row.set_subtitle(Some(subtitle));
+        self.widgets.passphrase_btn.set_label(label);
+        self.widgets.passphrase_btn.set_sensitive(self.model.encryption != Encryption::Unavailable);
+    }
+
+    fn add_field(&mut self) {
+        let name = self.widgets.field_name.get_text().trim().to_string();
+        let taken = self.model.preferences.fields.iter().any(|field| field.name == name);
+        if name.is_empty() || taken {
+            self.widgets.field_name.get_style_context().add_class("error");
+            return;
+        }
+        self.widgets.field_name.get_style_context().remove_class("error");
+        self.widgets.field_name.set_text("");
+        let kind = FieldKind::from_nick(&self.widgets.field_kind.get_active_id().unwrap_or_default());
+        let definition = FieldDefinition { name, kind };
+        let row = field_row(&self.model.relm, &definition);
+        self.widgets.fields_group.add(&row);
+        self.model.field_rows.push((definition.name.clone(), row));
+        self.model.preferences.fields.push(definition);
+    }
+}
+
+impl Update for PreferencesWindow {
+    type Model = Model;
+    type ModelParam = (libhandy::ApplicationWindow, Preferences, Encryption);
+    type Msg = Msg;
+
+    fn model(relm: &Relm<Self>, (win, preferences, encryption): (libhandy::ApplicationWindow, Preferences, Encryption)) -> Model {
+        Model {
+            relm: relm.clone(),
+            win, preferences, encryption,
+            field_rows: vec![]
+        }
+    }
+
+    fn update(&mut self, event: Msg) {
+        let prefs = &mut self.model.preferences;
+        match event {
+            Msg::DefaultInterval(nick) => {
+                prefs.default_interval = preferences::interval_from_nick(&nick.unwrap_or_default());
+            },
+            Msg::Reminder(days) => prefs.reminder_days = days,
+            Msg::Currency(currency) => prefs.currency = currency,
+            Msg::DateFormat(format) => {
+                let valid = preferences::is_valid_format(&format);
+                let style = self.widgets.date_format.get_style_context();
+                if valid {
+                    style.remove_class("error");
+                    prefs.date_format = format;
+                } else {
+                    style.add_class("error");
+                    return;
+                }
+            },
+            Msg::SortOrder(nick) => prefs.sort_order = SortOrder::from_nick(&nick.unwrap_or_default()),
+            Msg::Theme(nick) => prefs.theme = Theme::from_nick(&nick.unwrap_or_default()),
+            Msg::Retention(retention) => prefs.backup_retention = retention,
+            Msg::Encryption(encryption) => {
+                self.model.encryption = encryption;
+                self.show_encryption();
+                return;
+            },
+            Msg::AddField => self.add_field(),
+            Msg::RemoveField(name) => {
+                prefs.fields.retain(|field| field.name != name);
+                if let Some(at) = self.model.field_rows.iter().position(|(row_name, _)| *row_name == name) {
+                    let (_, row) = self.model.field_rows.remove(at);
+                    self.widgets.fields_group.remove(&row);
+                }
+            },
+            Msg::Changed(_) | Msg::Passphrase => return
+        }
+        self.model.relm.stream().emit(Msg::Changed(self.model.preferences.clone()));
+    }
+}
+
+impl Widget for PreferencesWindow {
+    type Root = libhandy::PreferencesWindow;
+
+    fn root(&self) -> Self::Root {
+        self.widgets.container.clone()
+    }
+
+    fn view(relm: &Relm<Self>, mut model: Self::Model) -> Self {
+        let glade_src = include_str!("../ui/preferences.ui");
+        let builder = gtk::Builder::from_string(glade_src);
+        let container: libhandy::PreferencesWindow = or_panic!(builder.get_object("preferencesWindow"));
+        let default_interval: gtk::ComboBoxText = or_panic!(builder.get_object("defaultIntervalSelect"));
+        let reminder: gtk::SpinButton = or_panic!(builder.get_object("reminderEntry"));
+        let currency: gtk::Entry = or_panic!(builder.get_object("currencyEntry"));
+        let date_format: gtk::Entry = or_panic!(builder.get_object("dateFormatEntry"));
+        let sort_order: gtk::ComboBoxText = or_panic!(builder.get_object("sortOrderSelect"));
+        let theme: gtk::ComboBoxText = or_panic!(builder.get_object("themeSelect"));
+        let retention: gtk::SpinButton = or_panic!(builder.get_object("retentionEntry"));
+        let encryption_row: libhandy::ActionRow = or_panic!(builder.get_object("encryptionRow"));
+        let passphrase_btn: gtk::Button = or_panic!(builder.get_object("passphraseButton"));
+        let fields_group: libhandy::PreferencesGroup = or_panic!(builder.get_object("fieldsGroup"));
+        let field_name: gtk::Entry = or_panic!(builder.get_object("fieldNameEntry"));
+        let field_kind: gtk::ComboBoxText = or_panic!(builder.get_object("fieldKindSelect"));
+        let add_field_btn: gtk::Button = or_panic!(builder.get_object("addFieldButton"));
+
+        let prefs = &model.preferences;
+        default_interval.set_active_id(Some(preferences::interval_nick(&prefs.default_interval)));
+        reminder.set_value(prefs.reminder_days as f64);
+        currency.set_text(&prefs.currency);
+        date_format.set_text(&prefs.date_format);
+        sort_order.set_active_id(Some(prefs.sort_order.nick()));
+        theme.set_active_id(Some(prefs.theme.nick()));
+        retention.set_value(prefs.backup_retention as f64);
+        for definition in &prefs.fields {
+            let row = field_row(relm, definition);
+            fields_group.add(&row);
+            model.field_rows.push((definition.name.clone(), row));
+        }
+
+        connect!(relm, default_interval, connect_changed(combo), Msg::DefaultInterval(combo.get_active_id().map(String::from)));
+        connect!(relm, reminder, connect_value_changed(spin), Msg::Reminder(spin.get_value_as_int() as u32));
+        connect!(relm, currency, connect_changed(entry), Msg::Currency(entry.get_text().to_string()));
+        connect!(relm, date_format, connect_changed(entry), Msg::DateFormat(entry.get_text().to_string()));
+        connect!(relm, sort_order, connect_changed(combo), Msg::SortOrder(combo.get_active_id().map(String::from)));
+        connect!(relm, theme, connect_changed(combo), Msg::Theme(combo.get_active_id().map(String::from)));
+        connect!(relm, retention, connect_value_changed(spin), Msg::Retention(spin.get_value_as_int() as usize));
+        connect!(relm, passphrase_btn, connect_clicked(_), Msg::Passphrase);
+        connect!(relm, add_field_btn, connect_clicked(_), Msg::AddField);
+        connect!(relm, field_name, connect_activate(_), Msg::AddField);
+
+        container.set_transient_for(Some(&model.win));
+        container.show_all();
+
+        let window = PreferencesWindow {
+            model,
+            widgets: Widgets {
+                container,
+                date_format,
+                encryption_row,
+                passphrase_btn,
+                fields_group,
+                field_name,
+                field_kind
+            },
+        };
+        window.show_encryption();
+        window
+    }
+}
+
+fn kind_label(kind: FieldKind) -> &'static str {
+    match kind {
+        FieldKind::Text => "Text",
+        FieldKind::Number => "Number",
+        FieldKind::Date => "Date",
+        FieldKind::Url => "Link"
+    }
+}
+
+/// A defined field with a button to remove it. Values already entered stay on the cycles.
+fn field_row(relm: &Relm<PreferencesWindow>, definition: &FieldDefinition) -> libhandy::ActionRow {
+    use libhandy::{ActionRowExt, PreferencesRowExt};
+    let row = libhandy::ActionRow::new();
+    row.set_title(Some(&definition.name));
+    row.set_subtitle(Some(kind_label(definition.kind)));
+    let remove_btn = gtk::Button::from_icon_name(Some("list-remove-symbolic"), gtk::IconSize::Button);
+    remove_btn.set_valign(gtk::Align::Center);
+    remove_btn.set_tooltip_text(Some("Remove Field"));
+    row.add(&remove_btn);
+    row.show_all();
+    let name = definition.name.clone();
+    connect!(relm, remove_btn, connect_clicked(_), Msg::RemoveField(name.clone()));
+    row
+}
@@ -0,0 +1,139 @@
+use gtk::prelude::*;
+use relm::{connect, Relm, Widget, Update};
+use relm_derive::Msg;
+
+use std::path::PathBuf;
+
+use crate::backup::{Backup, BackupSettings, Frequency};
+use crate::preferences::Preferences;
+
+#[derive(Msg)]
+pub enum Msg {
+    Restore(PathBuf),
+    FrequencyChanged(Option<u32>),
+    RetentionChanged(usize),
+    SettingsChanged(BackupSettings)
+}
+
+pub struct Widgets {
+    container: gtk::Dialog,
+    backups_list: gtk::ListBox,
+    frequency: gtk::ComboBoxText,
+    retention: gtk::SpinButton
+}
+
+pub struct Restore {
+    model: Model,
+    widgets: Widgets,
+}
+
+pub struct Model {
+    relm: Relm<Restore>,
+    win: libhandy::ApplicationWindow,
+    settings: BackupSettings,
+    backups: Vec<Backup>,
+    preferences: Preferences
+}
+
+impl Update for Restore {
+    type Model = Model;
+    type ModelParam = (libhandy::ApplicationWindow, BackupSettings, Vec<Backup>, Preferences);
+    type Msg = Msg;
+
+    fn model(relm: &Relm<Self>, (win, settings, backups, preferences): (libhandy::ApplicationWindow, BackupSettings, Vec<Backup>, Preferences)) -> Model {
+        Model {
+            relm: relm.clone(),
+            win, settings, backups, preferences
+        }
+    }
+
+    fn update(&mut self, event: Msg) {
+        match event {
+            Msg::Restore(_) => {
+                self.widgets.container.close();
+            },
+            Msg::FrequencyChanged(active) => {
+                self.model.settings.frequency = match active {
+                    Some(1) => Frequency::Weekly,
+                    Some(2) => Frequency::Monthly,
+                    _ => Frequency::Daily
+                };
+                self.model.relm.stream().emit(Msg::SettingsChanged(self.model.settings.clone()));
+            },
+            Msg::RetentionChanged(retention) => {
+                self.model.settings.retention = retention;
+                self.model.relm.stream().emit(Msg::SettingsChanged(self.model.settings.clone()));
+            },
+            Msg::SettingsChanged(_) => {}
+        }
+    }
+}
+
+impl Widget for Restore {
+    type Root = gtk::Dialog;
+
+    fn root(&self) -> Self::Root {
+        self.widgets.container.clone()
+    }
+
+    fn view(relm: &Relm<Self>, model: Self::Model) -> Self {
+        let glade_src = include_str!("../ui/backups.ui");
+        let builder = gtk::Builder::from_string(glade_src);
+        let container: gtk::Dialog = or_panic!(builder.get_object("backupsDialog"));
+        let backups_list: gtk::ListBox = or_panic!(builder.get_object("backupsList"));
+        let frequency: gtk::ComboBoxText = or_panic!(builder.get_object("frequencySelect"));
+        let retention: gtk::SpinButton = or_panic!(builder.get_object("retentionEntry"));
+
+        frequency.set_active(Some(match model.settings.frequency {
+            Frequency::Daily => 0,
+            Frequency::Weekly => 1,
+            Frequency::Monthly => 2
+        }));
+        retention.set_value(model.settings.retention as f64);
+
+        for backup in &model.backups {
+            backups_list.add(&backup_row(relm, backup, &model.preferences));
+        }
+
+        connect!(relm, frequency, connect_changed(combo), Msg::FrequencyChanged(combo.get_active()));
+        connect!(relm, retention, connect_value_changed(spin), Msg::RetentionChanged(spin.get_value_as_int() as usize));
+
+        container.set_transient_for(Some(&model.win));
+        container.connect_response(|dialog, _| dialog.close());
+        container.show_all();
+
+        Restore {
+            model,
+            widgets: Widgets {
+                container,
+                backups_list,
+                frequency,
+                retention
+            },
+        }
+    }
+}
+
+fn backup_row(relm: &Relm<Restore>, backup: &Backup, preferences: &Preferences) -> gtk::Box {
+    let row = gtk::Box::new(gtk::Orientation::Horizontal, 5);
+    row.set_margin_top(5);
+    row.set_margin_bottom(5);
+    row.set_margin_start(10);
+    row.set_margin_end(10);
+
+    let labels = gtk::Box::new(gtk::Orientation::Vertical, 2);
+    let date = gtk::Label::new(Some(&preferences.format_date(backup.date)));
+    date.set_halign(gtk::Align::Start);
+    let entries = gtk::Label::new(Some(&format!("{} cycles", backup.entries)));
+    entries.set_halign(gtk::Align::Start);
+    labels.pack_start(&date, false, true, 0);
+    labels.pack_start(&entries, false, true, 0);
+    row.pack_start(&labels, true, true, 0);
+
+    let restore_btn = gtk::Button::with_label("Restore");
+    row.pack_end(&restore_btn, false, true, 0);
+
+    let path = backup.path.clone();
+    connect!(relm, restore_btn, connect_clicked(_), Msg::Restore(path.clone()));
+    row
+}
@@ -0,0 +1,239 @@
+use uuid::Uuid;
+use serde::{ Serialize, Deserialize };
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::{env, fmt, fs, io};
+use std::path::{Path, PathBuf};
+
+use crate::description::Description;
+use crate::cache::{self, Database, Export};
+
+const BACKEND_VAR: &str = "CYCLES_STORAGE";
+
+/// Where the live cycles are kept.
+pub trait Storage {
+
+    fn append(&self, description: Description) -> Uuid {
+        let id = Uuid::new_v4();
+        self.update(id, description);
+        id
+    }
+
+    fn update(&self, id: Uuid, description: Description);
+
+    /// Takes a cycle out of the live list, returning it if it was there.
+    fn remove(&self, id: Uuid) -> Option<Description>;
+
+    fn get(&self, id: Uuid) -> Option<Description>;
+
+    fn entries(&self) -> Vec<(Uuid, Description)>;
+
+    fn export(&self) -> Export {
+        Export { cycles: self.entries(), ..Export::default() }
+    }
+
+    fn import(&self, export: &Export) {
+        for (id, description) in &export.cycles {
+            self.update(*id, description.clone());
+        }
+    }
+}
+
+impl Storage for Database {
+
+    fn update(&self, id: Uuid, description: Description) {
+        Database::update(self, id, description);
+    }
+
+    /// Moves the cycle into the trash, like deleting it from the list does.
+    fn remove(&self, id: Uuid) -> Option<Description> {
+        let description = Database::get(self, id);
+        Database::remove(self, id);
+        description
+    }
+
+    fn get(&self, id: Uuid) -> Option<Description> {
+        Database::get(self, id)
+    }
+
+    fn entries(&self) -> Vec<(Uuid, Description)> {
+        Database::entries(self).collect()
+    }
+
+    fn export(&self) -> Export {
+        self.bundle(true)
+    }
+
+    fn import(&self, export: &Export) {
+        self.merge(export);
+    }
+}
+
+/// Keeps cycles in memory only, for tests.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    cycles: RefCell<BTreeMap<Uuid, Description>>
+}
+
+impl Storage for MemoryStore {
+
+    fn update(&self, id: Uuid, description: Description) {
+        self.cycles.borrow_mut().insert(id, description);
+    }
+
+    fn remove(&self, id: Uuid) -> Option<Description> {
+        self.cycles.borrow_mut().remove(&id)
+    }
+
+    fn get(&self, id: Uuid) -> Option<Description> {
+        self.cycles.borrow().get(&id).cloned()
+    }
+
+    fn entries(&self) -> Vec<(Uuid, Description)> {
+        self.cycles.borrow().iter().map(|(id, description)| (*id, description.clone())).collect()
+    }
+}
+
+#[derive(Debug)]
+pub enum StorageError {
+    Io(PathBuf, io::Error),
+    /// The file isn't valid TOML or doesn't describe cycles, e.g. after a typo.
+    Parse(PathBuf, toml::de::Error)
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StorageError::Io(path, err) => write!(f, "Could not read {}: {}", path.display(), err),
+            StorageError::Parse(path, err) => write!(f, "Could not read {}: {}", path.display(), err)
+        }
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct CycleFile {
+    #[serde(default)]
+    cycles: BTreeMap<Uuid, Description>
+}
+
+/// Keeps cycles in a TOML file that can be read and edited by hand. The
+/// whole file is rewritten on every change.
+#[derive(Debug)]
+pub struct FileStore {
+    path: PathBuf,
+    cycles: RefCell<BTreeMap<Uuid, Description>>
+}
+
+impl FileStore {
+
+    /// Reads the file at `path`, starting empty when it doesn't exist yet.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<FileStore, StorageError> {
+        let path = path.as_ref().to_path_buf();
+        let file: CycleFile = match fs::read_to_string(&path) {
+            Ok(data) => toml::from_str(&data).map_err(|err| StorageError::Parse(path.clone(), err))?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => CycleFile::default(),
+            Err(err) => return Err(StorageError::Io(path, err))
+        };
+        Ok(FileStore { path, cycles: RefCell::new(file.cycles) })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Replaces every cycle in the file with `entries`.
+    pub fn replace_all(&self, entries: Vec<(Uuid, Description)>) {
+        *self.cycles.borrow_mut() = entries.into_iter().collect();
+        self.write();
+    }
+
+    fn write(&self) {
+        let file = CycleFile { cycles: self.cycles.borrow().clone() };
+        let data = or_panic!(Res toml::to_string_pretty(&file));
+        if let Some(dir) = self.path.parent() {
+            or_panic!(Res fs::create_dir_all(dir));
+        }
+        let partial = self.path.with_extension("toml.partial");
+        or_panic!(Res fs::write(&partial, data));
+        or_panic!(Res fs::rename(&partial, &self.path));
+    }
+}
+
+impl Storage for FileStore {
+
+    fn update(&self, id: Uuid, description: Description) {
+        self.cycles.borrow_mut().insert(id, description);
+        self.write();
+    }
+
+    fn remove(&self, id: Uuid) -> Option<Description> {
+        let removed = self.cycles.borrow_mut().remove(&id);
+        if removed.is_some() {
+            self.write();
+        }
+        removed
+    }
+
+    fn get(&self, id: Uuid) -> Option<Description> {
+        self.cycles.borrow().get(&id).cloned()
+    }
+
+    fn entries(&self) -> Vec<(Uuid, Description)> {
+        self.cycles.borrow().iter().map(|(id, description)| (*id, description.clone())).collect()
+    }
+
+    fn import(&self, export: &Export) {
+        self.cycles.borrow_mut().extend(export.cycles.iter().cloned());
+        self.write();
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Backend {
+    Sled,
+    File
+}
+
+impl Backend {
+
+    pub fn parse(name: &str) -> Option<Backend> {
+        match name {
+            "sled" => Some(Backend::Sled),
+            "file" => Some(Backend::File),
+            _ => None
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Backend::Sled => "sled",
+            Backend::File => "file"
+        }
+    }
+
+    /// The backend picked with `CYCLES_STORAGE`, sled unless it says `file`.
+    pub fn from_env() -> Backend {
+        env::var(BACKEND_VAR).ok()
+            .and_then(|name| Backend::parse(&name))
+            .unwrap_or(Backend::Sled)
+    }
+
+    pub fn open(self) -> Result<Box<dyn Storage>, StorageError> {
+        Ok(match self {
+            Backend::Sled => Box::new(Database::open_default()),
+            Backend::File => Box::new(FileStore::open(file_path())?)
+        })
+    }
+}
+
+/// Copies every cycle from one store into another, returning how many were copied.
+pub fn migrate(from: &dyn Storage, to: &dyn Storage) -> usize {
+    let export = from.export();
+    to.import(&export);
+    export.cycles.len()
+}
+
+pub fn file_path() -> PathBuf {
+    cache::data_dir().join("cycles.toml")
+}
@@ -0,0 +1,236 @@
+use uuid::Uuid;
+use serde::{ Serialize, Deserialize };
+
+use std::{fmt, fs, io};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::cache::Database;
+use crate::description::Description;
+
+/// Bumped when the sync file layout changes. Newer files are skipped.
+pub const VERSION: u32 = 1;
+pub const EXTENSION: &str = "cycles-sync";
+const FOLDER_KEY: &str = "sync-folder";
+const SEEN_KEY: &str = "sync-seen";
+
+/// When a cycle last changed, on which device, and whether it was deleted
+/// or archived.
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct Change {
+    /// Milliseconds since the Unix epoch, on the device's own clock.
+    pub modified: i64,
+    pub device: Uuid,
+    pub deleted: bool,
+    #[serde(default)]
+    pub archived: bool
+}
+
+impl Change {
+
+    /// Later changes win, ties go to the larger device id so every device
+    /// picks the same one.
+    pub fn wins_over(&self, other: &Change) -> bool {
+        (self.modified, self.device) > (other.modified, other.device)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Record {
+    pub id: Uuid,
+    pub change: Change,
+    /// `None` for a tombstone. An archived cycle keeps its description.
+    pub description: Option<Description>
+}
+
+/// What one device writes into the shared folder, every cycle it knows
+/// about with its last change.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct SyncFile {
+    pub version: u32,
+    pub device: Uuid,
+    pub records: Vec<Record>,
+    /// The newest change from each other device that this one has merged.
+    #[serde(default)]
+    pub seen: BTreeMap<Uuid, i64>
+}
+
+/// A cycle both devices changed since they last synced. `kept` is the
+/// version last-writer-wins picked, `other` the one it replaced.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Conflict {
+    pub id: Uuid,
+    pub kept: Option<Description>,
+    pub other: Option<Description>
+}
+
+impl Conflict {
+
+    pub fn label(&self) -> &str {
+        self.kept.as_ref().or_else(|| self.other.as_ref())
+            .map(|description| description.label.as_str())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Summary {
+    pub devices: usize,
+    /// Cycles whose live version changed.
+    pub changed: Vec<Uuid>,
+    pub conflicts: usize,
+    /// Files that couldn't be read or were written by a newer version.
+    pub skipped: usize
+}
+
+impl Summary {
+
+    pub fn add(&mut self, other: Summary) {
+        self.devices += other.devices;
+        self.changed.extend(other.changed);
+        self.conflicts += other.conflicts;
+        self.skipped += other.skipped;
+    }
+}
+
+#[derive(Debug)]
+pub enum SyncError {
+    /// Sync files are written in clear, so an encrypted database doesn't sync.
+    Encrypted,
+    Io(io::Error)
+}
+
+impl fmt::Display for SyncError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SyncError::Encrypted => write!(f, "Sync isn't available for an encrypted database"),
+            SyncError::Io(err) => write!(f, "Could not sync: {}", err)
+        }
+    }
+}
+
+impl From<io::Error> for SyncError {
+    fn from(err: io::Error) -> SyncError {
+        SyncError::Io(err)
+    }
+}
+
+impl SyncFile {
+
+    /// Everything `db` knows. Cycles from before change tracking go out as
+    /// changed at time zero, so any tracked change beats them.
+    pub fn from_database(db: &Database) -> SyncFile {
+        let device = db.device();
+        let mut records: Vec<Record> = db.changes()
+            .filter_map(|(id, change)| {
+                let description = if change.archived { db.get_archived(id) } else { db.get(id) };
+                if !change.deleted && description.is_none() {
+                    return None;
+                }
+                Some(Record { id, change, description: description.filter(|_| !change.deleted) })
+            })
+            .collect();
+        for (id, description) in db.entries() {
+            if db.change(id).is_none() {
+                let change = Change { modified: 0, device, deleted: false, archived: false };
+                records.push(Record { id, change, description: Some(description) });
+            }
+        }
+        SyncFile { version: VERSION, device, records, seen: seen(db) }
+    }
+}
+
+/// Merges another device's file into `db` with last-writer-wins, noting a
+/// conflict when each side changed a cycle without having seen the other's change.
+pub fn merge(db: &Database, file: &SyncFile) -> Summary {
+    let mut seen = seen(db);
+    let seen_there = seen.get(&file.device).copied().unwrap_or(i64::MIN);
+    let seen_here = file.seen.get(&db.device()).copied().unwrap_or(i64::MIN);
+    let device = db.device();
+    let mut summary = Summary { devices: 1, ..Summary::default() };
+
+    for record in &file.records {
+        let local = db.change(record.id);
+        if local == Some(record.change) {
+            continue;
+        }
+        let archived_here = local.map_or(false, |change| change.archived && !change.deleted);
+        let current = if archived_here { db.get_archived(record.id) } else { db.get(record.id) };
+        let differs = current != record.description || archived_here != record.change.archived;
+        let changed_there = record.change.device == file.device && record.change.modified > seen_there;
+        let changed_here = local.map_or(false, |change| change.device == device && change.modified > seen_here);
+        let conflict = differs && changed_there && changed_here;
+
+        if local.map_or(true, |change| record.change.wins_over(&change)) {
+            if differs {
+                db.apply_synced(record.id, record.description.as_ref(), record.change.archived);
+                summary.changed.push(record.id);
+            }
+            db.set_change(record.id, &record.change);
+            if conflict {
+                db.add_conflict(&Conflict { id: record.id, kept: record.description.clone(), other: current });
+            } else {
+                db.remove_conflict(record.id);
+            }
+        } else if conflict {
+            db.add_conflict(&Conflict { id: record.id, kept: current, other: record.description.clone() });
+        }
+        if conflict {
+            summary.conflicts += 1;
+        }
+    }
+
+    let newest = file.records.iter()
+        .filter(|record| record.change.device == file.device)
+        .map(|record| record.change.modified)
+        .fold(seen_there, i64::max);
+    seen.insert(file.device, newest);
+    db.set_setting(SEEN_KEY, &seen);
+    summary
+}
+
+/// Merges every other device's file in `folder`, then writes ours.
+pub fn run(db: &Database, folder: &Path) -> Result<Summary, SyncError> {
+    if db.is_encrypted() {
+        return Err(SyncError::Encrypted);
+    }
+    fs::create_dir_all(folder)?;
+    let own = file_path(folder, db.device());
+    let mut summary = Summary::default();
+
+    for entry in fs::read_dir(folder)? {
+        let path = entry?.path();
+        if path == own || path.extension().map_or(true, |extension| extension != EXTENSION) {
+            continue;
+        }
+        let file = fs::File::open(&path).ok()
+            .and_then(|file| serde_cbor::from_reader::<SyncFile, _>(file).ok())
+            .filter(|file| file.version <= VERSION);
+        match file {
+            Some(file) => summary.add(merge(db, &file)),
+            None => summary.skipped += 1
+        }
+    }
+
+    // Written under another name first so a syncing peer never sees half a file.
+    let partial = own.with_extension(format!("{}.partial", EXTENSION));
+    fs::write(&partial, or_panic!(Res serde_cbor::to_vec(&SyncFile::from_database(db))))?;
+    fs::rename(&partial, &own)?;
+    Ok(summary)
+}
+
+fn seen(db: &Database) -> BTreeMap<Uuid, i64> {
+    db.setting(SEEN_KEY).unwrap_or_default()
+}
+
+pub fn file_path(folder: &Path, device: Uuid) -> PathBuf {
+    folder.join(format!("{}.{}", device, EXTENSION))
+}
+
+pub fn folder(db: &Database) -> Option<PathBuf> {
+    db.setting(FOLDER_KEY)
+}
+
+pub fn set_folder(db: &Database, folder: &Path) {
+    db.set_setting(FOLDER_KEY, &folder.to_path_buf());
+}
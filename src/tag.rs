@@ -4,8 +4,7 @@ use relm_derive::{Msg, widget};
 
 #[derive(Debug, Msg)]
 pub enum Msg {
-    Delete,
-    Update(String)
+    Delete
 }
 
 pub struct Model {
@@ -15,7 +14,10 @@ pub struct Model {
 
 #[widget]
 impl Widget for Tag {
-    fn init_view(&mut self) {}
+    fn init_view(&mut self) {
+        add_stylesheet!("../css/badge.css", self.widgets.chip);
+    }
+
     fn model(relm: &Relm<Self>, text: String) -> Model {
         Model {
             relm: relm.clone(),
@@ -25,29 +27,24 @@ impl Widget for Tag {
 
     fn update(&mut self, event: Msg) {
         match event {
-            Msg::Delete => {},
-            Msg::Update(_) => {}
+            Msg::Delete => {}
         }
     }
 
     view! {
+        #[name="chip"]
         gtk::Box {
-            #[name="entry"]
-            gtk::Entry {
-                margin_top: 5,
-                margin_bottom: 5,
-                margin_start: 5,
-                text: &self.model.text,
-                changed(entry) => Msg::Update(entry.get_text().into())
+            spacing: 2,
+            gtk::Label {
+                margin_start: 4,
+                label: &self.model.text
             },
             #[name="delete_btn"]
             gtk::Button {
-                margin_top: 5,
-                margin_bottom: 5,
-                margin_end: 5,
+                relief: gtk::ReliefStyle::None,
                 clicked() => Msg::Delete,
                 gtk::Image {
-                    property_icon_name: Some("user-trash-symbolic")
+                    property_icon_name: Some("window-close-symbolic")
                 }
             }
         }
@@ -0,0 +1,63 @@
+/// The state behind the `Tags` editor, kept free of GTK so it can be tested.
+///
+/// Every chip gets an id that is never reused, so deleting one can't make a
+/// later `add` overwrite another tag, and chips keep the order they were added in.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TagList {
+    next_id: usize,
+    entries: Vec<(usize, String)>
+}
+
+/// Lowercases a tag and collapses its whitespace, `None` if nothing is left.
+pub fn normalize(tag: &str) -> Option<String> {
+    let words: Vec<&str> = tag.split_whitespace().collect();
+    if words.is_empty() {
+        None
+    } else {
+        Some(words.join(" ").to_lowercase())
+    }
+}
+
+impl TagList {
+
+    pub fn new(tags: Vec<String>) -> TagList {
+        let mut list = TagList::default();
+        for tag in tags {
+            list.add(&tag);
+        }
+        list
+    }
+
+    /// Adds a normalised tag, refusing blanks and tags already in the list.
+    pub fn add(&mut self, tag: &str) -> Option<usize> {
+        let tag = normalize(tag)?;
+        if self.contains(&tag) {
+            return None;
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        self.entries.push((id, tag));
+        Some(id)
+    }
+
+    pub fn contains(&self, tag: &str) -> bool {
+        match normalize(tag) {
+            Some(tag) => self.entries.iter().any(|(_, entry)| *entry == tag),
+            None => false
+        }
+    }
+
+    pub fn remove(&mut self, id: usize) -> bool {
+        let before = self.entries.len();
+        self.entries.retain(|(entry, _)| *entry != id);
+        before != self.entries.len()
+    }
+
+    pub fn entries(&self) -> &[(usize, String)] {
+        &self.entries
+    }
+
+    pub fn values(&self) -> Vec<String> {
+        self.entries.iter().map(|(_, tag)| tag.clone()).collect()
+    }
+}
@@ -0,0 +1,131 @@
+use gtk::prelude::*;
+use relm::{connect, Relm, Widget, Update};
+use relm_derive::Msg;
+
+use std::str::FromStr;
+
+use crate::description::TagStyle;
+
+#[derive(Msg)]
+pub enum Msg {
+    SetColor(String, String),
+    SetIcon(String, String),
+    Rename(String, String),
+    Delete(String),
+    Refresh(Vec<(String, usize, TagStyle)>)
+}
+
+pub struct Widgets {
+    container: gtk::Dialog,
+    tags_list: gtk::ListBox
+}
+
+pub struct TagManager {
+    model: Model,
+    widgets: Widgets,
+}
+
+pub struct Model {
+    relm: Relm<TagManager>,
+    win: libhandy::ApplicationWindow,
+    tags: Vec<(String, usize, TagStyle)>
+}
+
+impl Update for TagManager {
+    type Model = Model;
+    type ModelParam = (libhandy::ApplicationWindow, Vec<(String, usize, TagStyle)>);
+    type Msg = Msg;
+
+    fn model(relm: &Relm<Self>, (win, tags): (libhandy::ApplicationWindow, Vec<(String, usize, TagStyle)>)) -> Model {
+        Model {
+            relm: relm.clone(),
+            win, tags
+        }
+    }
+
+    fn update(&mut self, event: Msg) {
+        match event {
+            Msg::Refresh(tags) => {
+                for row in self.widgets.tags_list.get_children() {
+                    self.widgets.tags_list.remove(&row);
+                }
+                for (name, count, style) in &tags {
+                    self.widgets.tags_list.add(&tag_row(&self.model.relm, name, *count, style));
+                }
+                self.widgets.tags_list.show_all();
+                self.model.tags = tags;
+            },
+            _ => {}
+        }
+    }
+}
+
+impl Widget for TagManager {
+    type Root = gtk::Dialog;
+
+    fn root(&self) -> Self::Root {
+        self.widgets.container.clone()
+    }
+
+    fn view(relm: &Relm<Self>, model: Self::Model) -> Self {
+        let glade_src = include_str!("../ui/tag_manager.ui");
+        let builder = gtk::Builder::from_string(glade_src);
+        let container: gtk::Dialog = or_panic!(builder.get_object("tagManagerDialog"));
+        let tags_list: gtk::ListBox = or_panic!(builder.get_object("tagsList"));
+
+        for (name, count, style) in &model.tags {
+            tags_list.add(&tag_row(relm, name, *count, style));
+        }
+
+        container.set_transient_for(Some(&model.win));
+        container.connect_response(|dialog, _| dialog.close());
+        container.show_all();
+
+        TagManager {
+            model,
+            widgets: Widgets {
+                container,
+                tags_list
+            },
+        }
+    }
+}
+
+fn tag_row(relm: &Relm<TagManager>, name: &str, count: usize, style: &TagStyle) -> gtk::Box {
+    let row = gtk::Box::new(gtk::Orientation::Vertical, 5);
+    row.set_margin_top(5);
+    row.set_margin_bottom(5);
+    row.set_margin_start(10);
+    row.set_margin_end(10);
+
+    let top = gtk::Box::new(gtk::Orientation::Horizontal, 5);
+    let color_btn = gtk::ColorButton::new();
+    if let Some(color) = style.color.as_ref() {
+        if let Ok(rgba) = gdk::RGBA::from_str(color) {
+            color_btn.set_rgba(&rgba);
+        }
+    }
+    let name_entry = gtk::Entry::new();
+    name_entry.set_text(name);
+    let count_label = gtk::Label::new(Some(&format!("{} cycles", count)));
+    let delete_btn = gtk::Button::from_icon_name(Some("user-trash-symbolic"), gtk::IconSize::Button);
+    delete_btn.set_tooltip_text(Some("Delete from every cycle"));
+    top.pack_start(&color_btn, false, true, 0);
+    top.pack_start(&name_entry, true, true, 0);
+    top.pack_end(&delete_btn, false, true, 0);
+    top.pack_end(&count_label, false, true, 0);
+
+    let icon_entry = gtk::Entry::new();
+    icon_entry.set_placeholder_text(Some("Icon name"));
+    icon_entry.set_text(style.icon.as_deref().unwrap_or(""));
+
+    row.pack_start(&top, false, true, 0);
+    row.pack_start(&icon_entry, false, true, 0);
+
+    let (color_tag, rename_tag, icon_tag, delete_tag) = (name.to_string(), name.to_string(), name.to_string(), name.to_string());
+    connect!(relm, color_btn, connect_color_set(btn), Msg::SetColor(color_tag.clone(), btn.get_rgba().to_string()));
+    connect!(relm, name_entry, connect_activate(entry), Msg::Rename(rename_tag.clone(), entry.get_text().into()));
+    connect!(relm, icon_entry, connect_activate(entry), Msg::SetIcon(icon_tag.clone(), entry.get_text().into()));
+    connect!(relm, delete_btn, connect_clicked(_), Msg::Delete(delete_tag.clone()));
+    row
+}
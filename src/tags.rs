@@ -3,11 +3,14 @@ use relm::{connect, Component, Relm, Widget, Update};
 use relm_derive::Msg;
 use std::collections::HashMap;
 
+use crate::tag_list::TagList;
+
 #[derive(Msg)]
 pub enum Msg {
     Add,
+    Completed(String),
+    Typed(String),
     Delete(usize),
-    Updated((usize, String)),
     Changed(Vec<String>),
     SetTags(Vec<String>)
 }
@@ -16,25 +19,30 @@ pub struct Tags {
     model: Model,
     container: gtk::Box,
     expander: gtk::Expander,
-    tags_list: gtk::Box,
+    tags_list: gtk::FlowBox,
+    tag_entry: gtk::Entry,
     create_button: gtk::Button
 }
 
 pub struct Model {
-    tags: Vec<String>,
+    tags: TagList,
     relm: Relm<Tags>,
-    entries: HashMap<usize, (String, Component<crate::tag::Tag>)>
+    suggestions: gtk::ListStore,
+    rejected: String,
+    entries: HashMap<usize, Component<crate::tag::Tag>>
 }
 
 impl Update for Tags {
     type Model = Model;
-    type ModelParam = Vec<String>;
+    type ModelParam = (Vec<String>, gtk::ListStore);
     type Msg = Msg;
 
-    fn model(relm: &Relm<Self>, tags: Vec<String>) -> Model {
+    fn model(relm: &Relm<Self>, (tags, suggestions): (Vec<String>, gtk::ListStore)) -> Model {
         Model {
-            tags,
+            tags: TagList::new(tags),
             relm: relm.clone(),
+            suggestions,
+            rejected: String::new(),
             entries: Default::default()
         }
     }
@@ -42,53 +50,97 @@ impl Update for Tags {
     fn update(&mut self, event: Msg) {
         match event {
             Msg::SetTags(tags) => {
-                for tag in tags {
-                    let id = self.model.entries.len();
-                    let element = relm::init::<crate::tag::Tag>(tag.clone()).expect("Failed to create tag widget");
-                    let stream = self.model.relm.stream().clone();
-                    element.stream().observe(move |msg| {
-                        match msg {
-                            crate::tag::Msg::Update(a) => {
-                                stream.emit(Msg::Updated((id, a.clone())))
-                            },
-                            crate::tag::Msg::Delete => {
-                                stream.emit(Msg::Delete(id));
-                            },
-                        }
-                    });
-                    self.tags_list.add(element.widget());
-                    self.model.entries.insert(id, (tag, element));
+                let ids: Vec<usize> = self.model.entries.keys().cloned().collect();
+                for id in ids {
+                    self.remove_chip(id);
                 }
+                self.model.tags = TagList::new(tags);
+                self.add_chips();
+                self.emit_changed();
             },
             Msg::Add => {
-                let id = self.model.entries.len();
-                let element = relm::init::<crate::tag::Tag>(String::new()).expect("Failed to create tag widget");
-                let stream = self.model.relm.stream().clone();
-                element.stream().observe(move |msg| {
-                    match msg {
-                        crate::tag::Msg::Update(a) => {
-                            stream.emit(Msg::Updated((id, a.clone())))
-                        },
-                        crate::tag::Msg::Delete => {
-                            stream.emit(Msg::Delete(id));
-                        },
-                    }
-                });
-                self.tags_list.add(element.widget());
-                self.model.entries.insert(id, (String::new(), element));
+                let text: String = self.tag_entry.get_text().into();
+                self.add_text(&text);
+            },
+            Msg::Completed(text) => {
+                self.add_text(&text);
             },
-            Msg::Delete(item) => {
-                let (_, element) = or_panic!(self.model.entries.remove(&item));
-                self.tags_list.remove(element.widget());
-                self.model.relm.stream().emit(Msg::Changed(self.model.entries.values().map(|(data, _)| data.clone()).collect()));
+            Msg::Typed(text) => {
+                if text != self.model.rejected {
+                    self.tag_entry.get_style_context().remove_class("error");
+                }
+                if text.contains(',') {
+                    self.add_text(&text);
+                }
             },
-            Msg::Updated((id, string)) => {
-                self.model.entries.get_mut(&id).map(|(x, _)| *x = string);
-                self.model.relm.stream().emit(Msg::Changed(self.model.entries.values().map(|(data, _)| data.clone()).collect()));
+            Msg::Delete(id) => {
+                self.remove_chip(id);
+                self.model.tags.remove(id);
+                self.emit_changed();
             },
-            _ => {}
+            Msg::Changed(_) => {}
+        }
+    }
+}
+
+impl Tags {
+
+    // Adds every comma separated tag in `text`, leaving anything that was
+    // rejected in the entry so it can be fixed.
+    fn add_text(&mut self, text: &str) {
+        let mut rejected = vec![];
+        for part in text.split(',') {
+            match self.model.tags.add(part) {
+                Some(id) => {
+                    let tag = or_panic!(self.model.tags.entries().last()).1.clone();
+                    let element = tag_chip(&self.model.relm, &self.tags_list, id, tag);
+                    self.model.entries.insert(id, element);
+                },
+                None if !part.trim().is_empty() => rejected.push(part.trim()),
+                None => {}
+            }
+        }
+        self.model.rejected = rejected.join(", ");
+        self.tag_entry.set_text(&self.model.rejected);
+        if !rejected.is_empty() {
+            self.tag_entry.get_style_context().add_class("error");
+        }
+        self.emit_changed();
+    }
+
+    fn add_chips(&mut self) {
+        for (id, tag) in self.model.tags.entries().to_vec() {
+            let element = tag_chip(&self.model.relm, &self.tags_list, id, tag);
+            self.model.entries.insert(id, element);
+        }
+    }
+
+    fn remove_chip(&mut self, id: usize) {
+        if let Some(element) = self.model.entries.remove(&id) {
+            if let Some(child) = element.widget().get_parent() {
+                self.tags_list.remove(&child);
+            }
         }
     }
+
+    fn emit_changed(&self) {
+        self.model.relm.stream().emit(Msg::Changed(self.model.tags.values()));
+    }
+}
+
+fn tag_chip(relm: &Relm<Tags>, tags_list: &gtk::FlowBox, id: usize, tag: String) -> Component<crate::tag::Tag> {
+    let element = relm::init::<crate::tag::Tag>(tag).expect("Failed to create tag widget");
+    let stream = relm.stream().clone();
+    element.stream().observe(move |msg| {
+        match msg {
+            crate::tag::Msg::Delete => {
+                stream.emit(Msg::Delete(id));
+            },
+        }
+    });
+    tags_list.add(element.widget());
+    tags_list.show_all();
+    element
 }
 
 impl Widget for Tags {
@@ -103,19 +155,42 @@ impl Widget for Tags {
         let builder = gtk::Builder::from_string(glade_src);
         let expander: gtk::Expander = or_panic!(builder.get_object("tagsExpander"));
         let container: gtk::Box = or_panic!(builder.get_object("tagsContainer"));
-        let tags_list: gtk::Box = or_panic!(builder.get_object("tagsList"));
+        let tags_list: gtk::FlowBox = or_panic!(builder.get_object("tagsList"));
+        let tag_entry: gtk::Entry = or_panic!(builder.get_object("tagEntry"));
         let create_button: gtk::Button = or_panic!(builder.get_object("createButton"));
 
-        connect!(_relm, create_button, connect_clicked(_), Msg::Add);
+        let completion = gtk::EntryCompletion::new();
+        completion.set_model(Some(&model.suggestions));
+        completion.set_text_column(0);
+        completion.set_minimum_key_length(1);
+        tag_entry.set_completion(Some(&completion));
 
-        container.show_all();
+        connect!(_relm, create_button, connect_clicked(_), Msg::Add);
+        connect!(_relm, tag_entry, connect_activate(_), Msg::Add);
+        connect!(_relm, tag_entry, connect_changed(entry), Msg::Typed(entry.get_text().into()));
+        connect!(_relm, completion, connect_match_selected(_, store, iter), return (
+            store.get_value(iter, 0).get::<String>().ok().flatten().map(Msg::Completed),
+            Inhibit(true)
+        ));
 
-        Tags {
+        let mut tags = Tags {
             model,
             container,
             expander,
             tags_list,
+            tag_entry,
             create_button
-        }
+        };
+        tags.add_chips();
+        tags.container.show_all();
+        tags
+    }
+}
+
+/// Fills a completion model with every tag in `tags`, replacing what was there.
+pub fn fill_suggestions<I: IntoIterator<Item=String>>(store: &gtk::ListStore, tags: I) {
+    store.clear();
+    for tag in tags {
+        store.insert_with_values(None, &[0], &[&tag]);
     }
 }
@@ -0,0 +1,93 @@
+use chrono::NaiveDate;
+use serde::{ Serialize, Deserialize };
+
+use crate::cache::Database;
+use crate::description::{Description, Interval};
+
+const SETTINGS_KEY: &str = "templates";
+
+/// A starting point for a new cycle: everything but the start date.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Template {
+    pub label: String,
+    pub interval: Interval,
+    #[serde(default = "one")]
+    pub interval_multiplier: i64,
+    pub color: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub price: Option<f64>
+}
+
+fn one() -> i64 {
+    1
+}
+
+#[derive(Deserialize)]
+struct Catalog {
+    template: Vec<Template>
+}
+
+impl Template {
+
+    /// The catalog shipped in `data/templates.toml`.
+    pub fn bundled() -> Vec<Template> {
+        Template::parse(include_str!("../data/templates.toml"))
+    }
+
+    pub fn parse(catalog: &str) -> Vec<Template> {
+        or_panic!(Res toml::from_str::<Catalog>(catalog)).template
+    }
+
+    /// Templates the user saved from their own cycles.
+    pub fn custom(db: &Database) -> Vec<Template> {
+        db.setting(SETTINGS_KEY).unwrap_or_default()
+    }
+
+    /// Saves a custom template, replacing one with the same label.
+    pub fn save_custom(db: &Database, template: Template) {
+        let mut templates = Template::custom(db);
+        templates.retain(|existing| existing.label != template.label);
+        templates.push(template);
+        templates.sort_by(|a, b| a.label.to_lowercase().cmp(&b.label.to_lowercase()));
+        db.set_setting(SETTINGS_KEY, &templates);
+    }
+
+    /// The custom templates followed by the bundled ones.
+    pub fn all(db: &Database) -> Vec<Template> {
+        let mut templates = Template::custom(db);
+        templates.extend(Template::bundled());
+        templates
+    }
+
+    pub fn describe(&self, start: NaiveDate) -> Description {
+        Description {
+            label: self.label.clone(),
+            description: None,
+            color: self.color.clone(),
+            tags: self.tags.clone(),
+            start,
+            interval: self.interval.clone(),
+            interval_multiplier: self.interval_multiplier,
+            price: self.price,
+            notes: None,
+            url: None,
+            account: None,
+            payment: None,
+            fields: Default::default()
+        }
+    }
+}
+
+impl From<&Description> for Template {
+    fn from(description: &Description) -> Template {
+        Template {
+            label: description.label.clone(),
+            interval: description.interval.clone(),
+            interval_multiplier: description.interval_multiplier,
+            color: description.color.clone(),
+            tags: description.tags.clone(),
+            price: description.price
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use uuid::Uuid;
+use gtk::prelude::*;
+use relm::{connect, Relm, Widget, Update};
+use relm_derive::Msg;
+use chrono::{Utc, Duration};
+
+use std::collections::HashMap;
+
+use crate::cache::{Trashed, TRASH_DAYS};
+use crate::preferences::Preferences;
+
+#[derive(Msg)]
+pub enum Msg {
+    Restore(Uuid),
+    Purge(Uuid)
+}
+
+pub struct Widgets {
+    container: gtk::Dialog,
+    trash_list: gtk::ListBox
+}
+
+pub struct Trash {
+    model: Model,
+    widgets: Widgets,
+}
+
+pub struct Model {
+    relm: Relm<Trash>,
+    win: libhandy::ApplicationWindow,
+    entries: Vec<(Uuid, Trashed)>,
+    preferences: Preferences,
+    rows: HashMap<Uuid, gtk::Box>
+}
+
+impl Update for Trash {
+    type Model = Model;
+    type ModelParam = (libhandy::ApplicationWindow, Vec<(Uuid, Trashed)>, Preferences);
+    type Msg = Msg;
+
+    fn model(relm: &Relm<Self>, (win, entries, preferences): (libhandy::ApplicationWindow, Vec<(Uuid, Trashed)>, Preferences)) -> Model {
+        Model {
+            relm: relm.clone(),
+            win, entries, preferences,
+            rows: Default::default()
+        }
+    }
+
+    fn update(&mut self, event: Msg) {
+        match event {
+            Msg::Restore(id) | Msg::Purge(id) => {
+                if let Some(row) = self.model.rows.remove(&id) {
+                    if let Some(parent) = row.get_parent() {
+                        self.widgets.trash_list.remove(&parent);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Widget for Trash {
+    type Root = gtk::Dialog;
+
+    fn root(&self) -> Self::Root {
+        self.widgets.container.clone()
+    }
+
+    fn view(relm: &Relm<Self>, mut model: Self::Model) -> Self {
+        let glade_src = include_str!("../ui/trash.ui");
+        let builder = gtk::Builder::from_string(glade_src);
+        let container: gtk::Dialog = or_panic!(builder.get_object("trashDialog"));
+        let trash_list: gtk::ListBox = or_panic!(builder.get_object("trashList"));
+
+        for (id, trashed) in &model.entries {
+            let row = trash_row(relm, *id, trashed, &model.preferences);
+            trash_list.add(&row);
+            model.rows.insert(*id, row);
+        }
+
+        container.set_transient_for(Some(&model.win));
+        container.connect_response(|dialog, _| dialog.close());
+        container.show_all();
+
+        Trash {
+            model,
+            widgets: Widgets {
+                container,
+                trash_list
+            },
+        }
+    }
+}
+
+fn trash_row(relm: &Relm<Trash>, id: Uuid, trashed: &Trashed, preferences: &Preferences) -> gtk::Box {
+    let today = Utc::now().date().naive_local();
+    let purged = trashed.deleted + Duration::days(TRASH_DAYS);
+    let row = gtk::Box::new(gtk::Orientation::Horizontal, 5);
+    row.set_margin_top(5);
+    row.set_margin_bottom(5);
+    row.set_margin_start(10);
+    row.set_margin_end(10);
+
+    let labels = gtk::Box::new(gtk::Orientation::Vertical, 2);
+    let label = gtk::Label::new(Some(&trashed.description.label));
+    label.set_halign(gtk::Align::Start);
+    let deleted = gtk::Label::new(Some(&format!("Deleted {}, removed in {} days",
+        preferences.format_date(trashed.deleted),
+        (purged - today).num_days().max(0)
+    )));
+    deleted.set_halign(gtk::Align::Start);
+    labels.pack_start(&label, false, true, 0);
+    labels.pack_start(&deleted, false, true, 0);
+    row.pack_start(&labels, true, true, 0);
+
+    let restore_btn = gtk::Button::from_icon_name(Some("edit-undo-symbolic"), gtk::IconSize::Button);
+    restore_btn.set_tooltip_text(Some("Restore"));
+    let purge_btn = gtk::Button::from_icon_name(Some("edit-delete-symbolic"), gtk::IconSize::Button);
+    purge_btn.set_tooltip_text(Some("Delete Forever"));
+    purge_btn.get_style_context().add_class("destructive-action");
+    row.pack_end(&purge_btn, false, true, 0);
+    row.pack_end(&restore_btn, false, true, 0);
+
+    connect!(relm, restore_btn, connect_clicked(_), Msg::Restore(id));
+    connect!(relm, purge_btn, connect_clicked(_), Msg::Purge(id));
+    row
+}
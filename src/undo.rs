@@ -0,0 +1,75 @@
+use uuid::Uuid;
+
+use crate::Description;
+use crate::attachment::{Attachment, Blob};
+use crate::cache::Merged;
+
+const LIMIT: usize = 50;
+
+/// A change made through the main window, holding enough state to revert it.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Action {
+    Added(Uuid, Description),
+    Edited(Uuid, Description),
+    Deleted(Uuid, Description),
+    /// An import, holding whatever it replaced.
+    Imported(Merged),
+    /// A batch edit, holding the previous value of every changed cycle.
+    Changed(Vec<(Uuid, Description)>),
+    DeletedMany(Vec<(Uuid, Description)>),
+    Archived(Vec<(Uuid, Description)>),
+    /// A removed attachment, with its contents so it can be put back.
+    Detached(Attachment, Blob)
+}
+
+impl Action {
+
+    pub fn message(&self) -> String {
+        match self {
+            Action::Added(_, desc) => format!("Added “{}”", desc.label),
+            Action::Edited(_, desc) => format!("Edited “{}”", desc.label),
+            Action::Deleted(_, desc) => format!("Deleted “{}”", desc.label),
+            Action::Imported(merged) => format!("Imported {} cycles", merged.cycles.len()),
+            Action::Changed(entries) => format!("Changed {} cycles", entries.len()),
+            Action::DeletedMany(entries) => format!("Deleted {} cycles", entries.len()),
+            Action::Archived(entries) => format!("Archived {} cycles", entries.len()),
+            Action::Detached(attachment, _) => format!("Removed “{}”", attachment.name)
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct UndoStack {
+    actions: Vec<Action>
+}
+
+impl UndoStack {
+
+    pub fn push(&mut self, action: Action) {
+        if self.actions.len() == LIMIT {
+            self.actions.remove(0);
+        }
+        self.actions.push(action);
+    }
+
+    pub fn pop(&mut self) -> Option<Action> {
+        self.actions.pop()
+    }
+
+    pub fn peek(&self) -> Option<&Action> {
+        self.actions.last()
+    }
+
+    /// Forgets everything, e.g. after a restore the actions no longer apply to.
+    pub fn clear(&mut self) {
+        self.actions.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.actions.len()
+    }
+}
@@ -0,0 +1,44 @@
+use gtk::prelude::*;
+
+use crate::cache::Database;
+
+/// Asks for the passphrase until it unlocks `db`. Returns `false` if the
+/// user quits instead.
+pub fn prompt(db: &mut Database) -> bool {
+    ask(None, "Unlock Cycles", "Your cycles are encrypted. Enter the passphrase to open them.", "Quit", |passphrase| db.unlock(passphrase))
+}
+
+/// Asks for the password of a protected export until `open` accepts it.
+/// Returns `false` if the user cancels.
+pub fn export_password<F: FnMut(&str) -> bool>(parent: &gtk::Window, open: F) -> bool {
+    ask(Some(parent), "Protected Export", "This export is protected. Enter its password to import it.", "Cancel", open)
+}
+
+fn ask<F: FnMut(&str) -> bool>(parent: Option<&gtk::Window>, title: &str, message: &str, cancel: &str, mut open: F) -> bool {
+    let glade_src = include_str!("../ui/unlock.ui");
+    let builder = gtk::Builder::from_string(glade_src);
+    let dialog: gtk::Dialog = or_panic!(builder.get_object("unlockDialog"));
+    let message_label: gtk::Label = or_panic!(builder.get_object("unlockMessage"));
+    let cancel_btn: gtk::Button = or_panic!(builder.get_object("quitButton"));
+    let entry: gtk::Entry = or_panic!(builder.get_object("passphraseEntry"));
+    let error: gtk::Label = or_panic!(builder.get_object("unlockError"));
+
+    dialog.set_title(title);
+    dialog.set_transient_for(parent);
+    message_label.set_text(message);
+    cancel_btn.set_label(cancel);
+
+    let unlocked = loop {
+        if dialog.run() != gtk::ResponseType::Accept {
+            break false;
+        }
+        if open(&entry.get_text()) {
+            break true;
+        }
+        error.show();
+        entry.set_text("");
+        entry.grab_focus();
+    };
+    dialog.close();
+    unlocked
+}
@@ -0,0 +1,132 @@
+use chrono::NaiveDate;
+
+use std::fmt;
+use std::collections::BTreeMap;
+
+use crate::description::{Description, Interval};
+use crate::fields::{FieldDefinition, FieldKind, FieldValue};
+
+/// A field of the create/edit form that failed validation.
+#[derive(Debug, PartialEq, Clone)]
+pub enum FormError {
+    EmptyLabel,
+    MissingInterval,
+    InvalidMultiplier(i64),
+    InvalidDate(i32, u32, u32),
+    NegativePrice(f64),
+    InvalidUrl(String),
+    /// The name of a custom field whose value doesn't fit its kind.
+    InvalidField(String, FieldKind)
+}
+
+impl fmt::Display for FormError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FormError::EmptyLabel => write!(f, "Label can't be empty"),
+            FormError::MissingInterval => write!(f, "Choose an interval"),
+            FormError::InvalidMultiplier(value) => write!(f, "Repeat every {} is not a positive number", value),
+            FormError::InvalidDate(y, m, d) => write!(f, "{}-{:02}-{:02} is not a valid date", y, m, d),
+            FormError::NegativePrice(price) => write!(f, "Price {} can't be negative", price),
+            FormError::InvalidUrl(url) => write!(f, "{} is not a valid link", url),
+            FormError::InvalidField(name, kind) => match kind {
+                FieldKind::Number => write!(f, "{} must be a number", name),
+                FieldKind::Date => write!(f, "{} must be a date like 2021-04-04", name),
+                FieldKind::Url => write!(f, "{} must be a link", name),
+                FieldKind::Text => write!(f, "{} is not valid", name)
+            }
+        }
+    }
+}
+
+/// The raw values read off the form widgets.
+#[derive(Debug, PartialEq, Clone)]
+pub struct FormInput {
+    pub label: String,
+    pub description: String,
+    pub color: Option<String>,
+    pub tags: Vec<String>,
+    pub interval: Option<Interval>,
+    pub interval_multiplier: i64,
+    /// Year, month (starting at 1) and day.
+    pub start: (i32, u32, u32),
+    pub price: f64,
+    pub notes: String,
+    pub url: String,
+    pub account: String,
+    pub payment: String,
+    /// Each custom field with what was typed for it, empty when left blank.
+    pub fields: Vec<(FieldDefinition, String)>
+}
+
+impl FormInput {
+
+    /// Builds the description, or every problem found so each field can be marked.
+    pub fn validate(&self) -> Result<Description, Vec<FormError>> {
+        let mut errors = vec![];
+        let label = self.label.trim();
+        if label.is_empty() {
+            errors.push(FormError::EmptyLabel);
+        }
+        if self.interval.is_none() {
+            errors.push(FormError::MissingInterval);
+        }
+        if self.interval_multiplier <= 0 {
+            errors.push(FormError::InvalidMultiplier(self.interval_multiplier));
+        }
+        let (y, m, d) = self.start;
+        let start = NaiveDate::from_ymd_opt(y, m, d);
+        if start.is_none() {
+            errors.push(FormError::InvalidDate(y, m, d));
+        }
+        if self.price < 0. {
+            errors.push(FormError::NegativePrice(self.price));
+        }
+        let url = optional(&self.url);
+        let url = match url {
+            Some(url) if url.contains(char::is_whitespace) => {
+                errors.push(FormError::InvalidUrl(url));
+                None
+            },
+            // A bare domain is most likely meant as a web link.
+            Some(url) if !url.contains("://") && !url.starts_with("mailto:") => Some(format!("https://{}", url)),
+            url => url
+        };
+        let mut fields = BTreeMap::new();
+        for (definition, text) in &self.fields {
+            if text.trim().is_empty() {
+                continue;
+            }
+            match FieldValue::parse(definition.kind, text) {
+                Some(value) => {
+                    fields.insert(definition.name.clone(), value);
+                },
+                None => errors.push(FormError::InvalidField(definition.name.clone(), definition.kind))
+            }
+        }
+        match (start, self.interval.clone()) {
+            (Some(start), Some(interval)) if errors.is_empty() => {
+                Ok(Description {
+                    label: label.to_string(),
+                    description: optional(&self.description),
+                    color: self.color.clone(),
+                    tags: self.tags.clone(),
+                    start, interval,
+                    interval_multiplier: self.interval_multiplier,
+                    price: if self.price > 0. { Some(self.price) } else { None },
+                    notes: optional(&self.notes),
+                    url,
+                    account: optional(&self.account),
+                    payment: optional(&self.payment),
+                    fields
+                })
+            },
+            _ => Err(errors)
+        }
+    }
+}
+
+/// Trimmed text, or `None` when nothing was entered.
+fn optional(text: &str) -> Option<String> {
+    let text = text.trim();
+    if text.is_empty() { None } else { Some(text.to_string()) }
+}
@@ -5,21 +5,75 @@ use relm_derive::Msg;
 use relm::{connect, init, Relm, Update, Widget, Component};
 
 use std::path::PathBuf;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use crate::cycle::{Cycle, Msg as CycleMsg};
-use crate::form::{Form, Msg as FormMsg};
+use crate::form::{Form, Host, Msg as FormMsg};
 use crate::menu::{Menu, Msg as MenuMsg};
 use crate::about::About;
-use crate::cache::Database;
-use crate::description::{Description, Interval};
+use crate::trash::{Trash, Msg as TrashMsg};
+use crate::archive::{Archive, Msg as ArchiveMsg};
+use crate::restore::{Restore, Msg as RestoreMsg};
+use crate::backup::{Backups, BackupSettings};
+use crate::diagnostics::{Diagnostics, Msg as DiagnosticsMsg};
+use crate::tag_manager::{TagManager, Msg as TagManagerMsg};
+use crate::detail::{Detail, Msg as DetailMsg};
+use crate::template::Template;
+use crate::attachment::{self, Attachment, Blob};
+use crate::fields;
+use crate::order::Ordering;
+use crate::preferences::{Preferences, SortOrder};
+use crate::preferences_window::{self, PreferencesWindow, Msg as PreferencesMsg};
+use crate::passphrase::{Passphrase, Encryption, Msg as PassphraseMsg};
+use crate::conflicts::{Conflicts, Msg as ConflictsMsg};
+use crate::sync::{self, Summary};
+use crate::caldav::{self, Account, Collection};
+use crate::http::HttpTransport;
+use crate::cache::{Database, ExportError, Slot};
+use crate::description::{Description, Interval, TagStyle};
+use crate::undo::{Action, UndoStack};
+use crate::filter::{self, Filter, TagMode};
+
+const TOAST_TIMEOUT: u32 = 5000;
+/// How many past periods an attachment can be filed under.
+const PERIODS_OFFERED: usize = 24;
+
+/// How an export file is sealed.
+pub enum Protection {
+    /// With the database key when encrypted, in clear otherwise.
+    Database,
+    Plain,
+    Password(String)
+}
 
 pub struct Model {
     relm: Relm<Win>,
     cycles: HashMap<Uuid, (Description, Component<Cycle>)>,
+    tag_styles: HashMap<String, TagStyle>,
+    tag_suggestions: gtk::ListStore,
     descriptions: Database,
-    create_popup: Option<Component<Form>>,
-    menu_popup: Option<Component<Menu>>
+    backups: Backups,
+    backup_error: Option<String>,
+    undo: UndoStack,
+    toast_serial: u32,
+    filter: Filter,
+    selected: Option<Uuid>,
+    selecting: bool,
+    selection: HashSet<Uuid>,
+    ordering: Ordering,
+    settings: Option<gio::Settings>,
+    preferences: Preferences,
+    detail: Option<Component<Detail>>,
+    editor: Option<Component<Form>>,
+    menu_popup: Option<Component<Menu>>,
+    trash_dialog: Option<Component<Trash>>,
+    archive_dialog: Option<Component<Archive>>,
+    restore_dialog: Option<Component<Restore>>,
+    diagnostics_dialog: Option<Component<Diagnostics>>,
+    tag_manager: Option<Component<TagManager>>,
+    conflicts_dialog: Option<Component<Conflicts>>,
+    preferences_window: Option<Component<PreferencesWindow>>,
+    passphrase_dialog: Option<Component<Passphrase>>
 }
 
 #[derive(Msg)]
@@ -28,15 +82,59 @@ pub enum Msg {
     About,
     DisplaySearch,
     RequestExport,
-    Export(PathBuf),
+    Export(PathBuf, bool, Protection),
     RequestImport,
     Import(PathBuf),
     OpenMenu,
     OpenCreate,
     Delete(Uuid),
     Query(String),
+    ToggleTag(String, bool),
+    MatchAllTags(bool),
+    Select(gtk::ListBoxRow),
+    ShowList,
+    SaveTemplate,
+    OpenUrl(String),
+    AttachFile,
+    OpenAttachment(Attachment),
+    RemoveAttachment(Uuid),
+    Folded(bool),
     Updated((Uuid, Description)),
-    Add(Description)
+    Add(Description),
+    OpenTrash,
+    OpenArchive,
+    Unarchive(Uuid),
+    SelectionMode(bool),
+    SelectCycle(Uuid, bool),
+    Pin(Uuid, bool),
+    Reorder(Uuid, Uuid),
+    BatchDelete,
+    BatchTag(bool),
+    BatchColor(String),
+    BatchArchive,
+    BatchExport,
+    OpenBackups,
+    OpenDiagnostics,
+    OpenTagManager,
+    OpenPreferences,
+    SavePreferences(Preferences),
+    Sync,
+    ChooseSyncFolder,
+    ResolveConflict(Uuid, bool),
+    OpenPassphrase,
+    SetPassphrase(Option<String>, Option<String>),
+    SetTagColor(String, String),
+    SetTagIcon(String, String),
+    RenameTag(String, String),
+    DeleteTag(String),
+    Repair,
+    RestoreBackup(PathBuf),
+    SaveBackupSettings(BackupSettings),
+    Restore(Uuid),
+    Purge(Uuid),
+    Undo,
+    HideToast(u32),
+    DismissToast
 }
 
 #[derive(Clone)]
@@ -47,7 +145,22 @@ pub struct Widgets {
     cycle_list: gtk::ListBox,
     search_entry: gtk::SearchEntry,
     search_button: gtk::Button,
-    search_bar: libhandy::SearchBar
+    search_bar: libhandy::SearchBar,
+    tag_filter_bar: gtk::Box,
+    tag_filter_list: gtk::Box,
+    toast_revealer: gtk::Revealer,
+    toast_label: gtk::Label,
+    undo_btn: gtk::Button,
+    toast_close_btn: gtk::Button,
+    leaflet: libhandy::Leaflet,
+    list_page: gtk::Box,
+    list_header: libhandy::HeaderBar,
+    select_btn: gtk::ToggleButton,
+    selection_bar: gtk::ActionBar,
+    selection_label: gtk::Label,
+    batch_tag_entry: gtk::Entry,
+    batch_tag_popover: gtk::Popover,
+    editor_host: Host
 }
 
 pub struct Win {
@@ -61,12 +174,47 @@ impl Update for Win {
     type Msg = Msg;
 
     fn model(relm: &Relm<Self>, _: ()) -> Model {
+        let mut descriptions = crate::cache::Database::new();
+        if descriptions.is_locked() && !crate::unlock::prompt(&mut descriptions) {
+            std::process::exit(0);
+        }
+        let backups = Backups::default().with_key(descriptions.key().cloned());
+        let today = chrono::Utc::now().date().naive_local();
+        let settings = preferences_window::open_settings();
+        let mut preferences = match settings.as_ref() {
+            Some(settings) => preferences_window::read_preferences(settings),
+            None => Preferences::default()
+        };
+        preferences.fields = fields::definitions(&descriptions);
+        preferences_window::apply_theme(preferences.theme);
+        let backup_error = backups.run_if_due(&descriptions, &backup_settings(&descriptions, &preferences), today)
+            .err()
+            .map(|err| format!("Could not write a backup: {}", err));
         Model {
             relm: relm.clone(),
             menu_popup: None,
-            create_popup: None,
+            editor: None,
+            trash_dialog: None,
+            archive_dialog: None,
+            restore_dialog: None,
+            diagnostics_dialog: None,
+            conflicts_dialog: None,
+            tag_manager: None,
+            preferences_window: None,
+            passphrase_dialog: None,
             cycles: Default::default(),
-            descriptions: crate::cache::Database::new(),
+            tag_styles: descriptions.tag_styles(),
+            ordering: Ordering::load(&descriptions),
+            settings, preferences,
+            tag_suggestions: gtk::ListStore::new(&[String::static_type()]),
+            descriptions, backups, backup_error,
+            undo: Default::default(),
+            toast_serial: 0,
+            filter: Filter::default(),
+            selected: None,
+            selecting: false,
+            selection: Default::default(),
+            detail: None,
         }
     }
 
@@ -77,15 +225,45 @@ impl Update for Win {
                 self.widgets.search_bar.set_search_mode(true);
             },
             Msg::Import(filename) => {
-                let file = or_panic!(Res std::fs::File::open(filename));
-                let descriptions = self.model.descriptions.import_from_reader(file);
-                for (key, value) in descriptions {
-                    self.append_description(key, value);
+                let data = match std::fs::read(&filename) {
+                    Ok(data) => data,
+                    Err(err) => return self.notify(&format!("Could not read {}: {}", filename.display(), err), false)
+                };
+                let export = match crate::cache::read_export(&data[..], self.model.descriptions.key()) {
+                    Ok(export) => export,
+                    Err(ExportError::Locked) => {
+                        let mut opened = None;
+                        let window = self.widgets.window.clone().upcast::<gtk::Window>();
+                        let accepted = crate::unlock::export_password(&window, |password| {
+                            opened = crate::cache::read_protected_export(&data[..], password).ok();
+                            opened.is_some()
+                        });
+                        match opened {
+                            Some(export) if accepted => export,
+                            _ => return
+                        }
+                    },
+                    Err(err) => return self.notify(&err.to_string(), false)
+                };
+                let merged = self.model.descriptions.merge(&export);
+                self.model.ordering = Ordering::load(&self.model.descriptions);
+                self.reload_fields();
+                for id in export.trash.iter().map(|(id, _)| id).chain(export.archive.iter().map(|(id, _)| id)) {
+                    self.remove_description(*id);
                 }
+                self.refresh_cycles(export.cycles.iter().map(|(id, _)| *id).collect());
+                self.record(Action::Imported(merged));
             },
-            Msg::Export(file) => {
-                let file = or_panic!(Res std::fs::File::create(file));
-                self.model.descriptions.export_to_writer(file);
+            Msg::Export(file, include_trash, protection) => {
+                let file = match std::fs::File::create(&file) {
+                    Ok(created) => created,
+                    Err(err) => return self.notify(&format!("Could not write {}: {}", file.display(), err), false)
+                };
+                match protection {
+                    Protection::Database => self.model.descriptions.export_to_writer(file, include_trash),
+                    Protection::Plain => self.model.descriptions.export_plain_to_writer(file, include_trash),
+                    Protection::Password(password) => self.model.descriptions.export_protected_to_writer(file, include_trash, &password)
+                }
             },
             Msg::RequestImport => {
                 let dialog = gtk::FileChooserDialog::new(Some("Open a file"), Some(&self.widgets.window), gtk::FileChooserAction::Open);
@@ -103,35 +281,143 @@ impl Update for Win {
                 let dialog = gtk::FileChooserDialog::new(Some("Open a file"), Some(&self.widgets.window), gtk::FileChooserAction::Save);
                 dialog.add_button("Cancel", gtk::ResponseType::Cancel);
                 dialog.add_button("Accept", gtk::ResponseType::Accept);
+                let include_trash = gtk::CheckButton::with_label("Include Trash");
+                let plain = gtk::CheckButton::with_label("Export without encryption");
+                let protect = gtk::CheckButton::with_label("Protect with a password");
+                let password = gtk::Entry::new();
+                let confirm = gtk::Entry::new();
+                for (entry, placeholder) in &[(&password, "Password"), (&confirm, "Confirm password")] {
+                    entry.set_visibility(false);
+                    entry.set_input_purpose(gtk::InputPurpose::Password);
+                    entry.set_placeholder_text(Some(*placeholder));
+                    entry.set_sensitive(false);
+                }
+                {
+                    let (password, confirm, plain) = (password.clone(), confirm.clone(), plain.clone());
+                    protect.connect_toggled(move |check| {
+                        password.set_sensitive(check.get_active());
+                        confirm.set_sensitive(check.get_active());
+                        plain.set_sensitive(!check.get_active());
+                    });
+                }
+                let options = gtk::Box::new(gtk::Orientation::Horizontal, 12);
+                options.add(&include_trash);
+                options.add(&plain);
+                options.add(&protect);
+                options.add(&password);
+                options.add(&confirm);
+                options.show_all();
+                plain.set_visible(self.model.descriptions.is_encrypted());
+                dialog.set_extra_widget(&options);
                 let result = dialog.run();
                 if result == gtk::ResponseType::Accept {
                     if let Some(filename) = dialog.get_filename() {
-                        self.model.relm.stream().emit(Msg::Export(filename));
+                        let protection = if protect.get_active() {
+                            Protection::Password(password.get_text().to_string())
+                        } else if plain.get_active() {
+                            Protection::Plain
+                        } else {
+                            Protection::Database
+                        };
+                        let problem = match &protection {
+                            Protection::Password(text) if text.is_empty() => Some("Enter a password to protect the export"),
+                            Protection::Password(text) if text != confirm.get_text().as_str() => Some("The passwords don't match"),
+                            _ => None
+                        };
+                        match problem {
+                            Some(message) => self.notify(message, false),
+                            None => self.model.relm.stream().emit(Msg::Export(filename, include_trash.get_active(), protection))
+                        }
                     }
                 }
                 dialog.close();
             },
             Msg::Query(query) => {
-                if query.is_empty() {
-                    for (_, item) in self.model.cycles.values() {
-                        item.widget().show()
-                    }
+                self.model.filter.query = query;
+                self.apply_filter();
+            },
+            Msg::ToggleTag(tag, active) => {
+                if active {
+                    self.model.filter.tags.insert(tag);
                 } else {
-                    for (_, (value, widget)) in self.model.cycles.iter() {
-                        let query = query.to_lowercase();
-                        if value.label.to_lowercase().contains(&query) ||
-                           value.description.is_some() &&
-                           value.description.as_ref().unwrap().to_lowercase().contains(&query) ||
-                           tags_contain_value(&value.tags, &query)
-                        {
-                             widget.widget().show();
-                        } else {
-                            widget.widget().hide();
-                        }
-                    }
+                    self.model.filter.tags.remove(&tag);
+                }
+                self.apply_filter();
+            },
+            Msg::MatchAllTags(all) => {
+                self.model.filter.mode = if all { TagMode::All } else { TagMode::Any };
+                self.apply_filter();
+            },
+            Msg::Select(row) => {
+                use libhandy::LeafletExt;
+                self.model.selected = self.model.cycles.iter()
+                    .find(|(_, (_, element))| element.widget().get_parent().as_ref() == Some(row.upcast_ref::<gtk::Widget>()))
+                    .map(|(id, _)| *id);
+                self.refresh_detail();
+                if let Some(detail) = self.model.detail.as_ref() {
+                    self.widgets.leaflet.set_visible_child(detail.widget());
+                }
+            },
+            Msg::ShowList => {
+                use libhandy::LeafletExt;
+                self.widgets.leaflet.set_visible_child(&self.widgets.list_page);
+            },
+            Msg::SaveTemplate => {
+                let description = self.model.selected
+                    .and_then(|id| self.model.cycles.get(&id))
+                    .map(|(description, _)| description.clone());
+                if let Some(description) = description {
+                    Template::save_custom(&self.model.descriptions, Template::from(&description));
+                    self.notify(&format!("Saved “{}” as a template", description.label), false);
+                }
+            },
+            Msg::OpenUrl(url) => {
+                let screen = self.widgets.window.get_screen();
+                if let Err(err) = gtk::show_uri(screen.as_ref(), &url, gtk::get_current_event_time()) {
+                    self.notify(&format!("Could not open {}: {}", url, err), false);
+                }
+            },
+            Msg::AttachFile => {
+                if let Some(id) = self.model.selected {
+                    self.attach_file(id);
+                }
+            },
+            Msg::OpenAttachment(attachment) => {
+                let path = attachment.open_path();
+                let written = match self.model.descriptions.attachment_data(attachment.id) {
+                    Some(data) => path.parent().map_or(Ok(()), std::fs::create_dir_all)
+                        .and_then(|_| std::fs::write(&path, data)),
+                    None => return self.notify(&format!("“{}” is missing", attachment.name), false)
+                };
+                match written {
+                    Ok(()) => {
+                        use gio::FileExt;
+                        let uri = gio::File::new_for_path(&path).get_uri();
+                        self.model.relm.stream().emit(Msg::OpenUrl(uri.to_string()));
+                    },
+                    Err(err) => self.notify(&format!("Could not open “{}”: {}", attachment.name, err), false)
+                }
+            },
+            Msg::RemoveAttachment(id) => {
+                let attachment = self.model.selected
+                    .and_then(|cycle| self.model.descriptions.attachments(cycle).into_iter().find(|attachment| attachment.id == id));
+                let data = self.model.descriptions.attachment_data(id);
+                if let (Some(attachment), Some(data)) = (attachment, data) {
+                    self.model.descriptions.remove_attachment(id);
+                    self.refresh_detail();
+                    self.record(Action::Detached(attachment, Blob(data)));
                 }
             },
-            Msg::Quit => gtk::main_quit(),
+            Msg::Folded(folded) => {
+                self.widgets.list_header.set_show_close_button(folded);
+                if let Some(detail) = self.model.detail.as_ref() {
+                    detail.stream().emit(DetailMsg::Folded(folded));
+                }
+            },
+            Msg::Quit => {
+                attachment::clear_opened();
+                gtk::main_quit();
+            },
             #[allow(unused_must_use)]
             Msg::About => {
                 or_panic!(Res init::<About>(self.widgets.window.clone()));
@@ -142,29 +428,476 @@ impl Update for Win {
                 connect!(element@MenuMsg::Quit, self.model.relm, Msg::Quit);
                 connect!(element@MenuMsg::Import, self.model.relm, Msg::RequestImport);
                 connect!(element@MenuMsg::Export, self.model.relm, Msg::RequestExport);
+                connect!(element@MenuMsg::Trash, self.model.relm, Msg::OpenTrash);
+                connect!(element@MenuMsg::Archive, self.model.relm, Msg::OpenArchive);
+                connect!(element@MenuMsg::Backups, self.model.relm, Msg::OpenBackups);
+                connect!(element@MenuMsg::Diagnostics, self.model.relm, Msg::OpenDiagnostics);
+                connect!(element@MenuMsg::Tags, self.model.relm, Msg::OpenTagManager);
+                connect!(element@MenuMsg::Preferences, self.model.relm, Msg::OpenPreferences);
+                connect!(element@MenuMsg::Sync, self.model.relm, Msg::Sync);
+                connect!(element@MenuMsg::SyncFolder, self.model.relm, Msg::ChooseSyncFolder);
                 self.model.menu_popup = Some(element);
             },
             Msg::OpenCreate => {
-                let element = or_panic!(Res init::<Form>((self.widgets.create_btn.clone(), None)));
+                let element = or_panic!(Res init::<Form>((self.widgets.editor_host.clone(), None, self.model.tag_suggestions.clone(), Template::all(&self.model.descriptions), self.model.preferences.clone())));
                 let stream = self.model.relm.stream().clone();
                 element.stream().observe(move |msg| {
                     if let FormMsg::Created(a) = msg {
                         stream.emit(Msg::Add(a.clone()));
                     }
                 });
-                self.model.create_popup = Some(element);
+                self.model.editor = Some(element);
+            },
+            Msg::OpenTrash => {
+                let entries = self.model.descriptions.trash().collect();
+                let element = or_panic!(Res init::<Trash>((self.widgets.window.clone(), entries, self.model.preferences.clone())));
+                let stream = self.model.relm.stream().clone();
+                element.stream().observe(move |msg| {
+                    match msg {
+                        TrashMsg::Restore(id) => stream.emit(Msg::Restore(*id)),
+                        TrashMsg::Purge(id) => stream.emit(Msg::Purge(*id))
+                    }
+                });
+                self.model.trash_dialog = Some(element);
+            },
+            Msg::OpenArchive => {
+                let entries = self.model.descriptions.archived().collect();
+                let element = or_panic!(Res init::<Archive>((self.widgets.window.clone(), entries)));
+                let stream = self.model.relm.stream().clone();
+                element.stream().observe(move |msg| {
+                    let ArchiveMsg::Unarchive(id) = msg;
+                    stream.emit(Msg::Unarchive(*id));
+                });
+                self.model.archive_dialog = Some(element);
+            },
+            Msg::Unarchive(id) => {
+                if let Some(description) = self.model.descriptions.unarchive(id) {
+                    self.show_description(id, description);
+                    self.refresh_suggestions();
+                }
+            },
+            Msg::SelectionMode(selecting) => {
+                self.model.selecting = selecting;
+                if !selecting {
+                    self.model.selection.clear();
+                }
+                for (_, element) in self.model.cycles.values() {
+                    element.stream().emit(CycleMsg::SelectionMode(selecting));
+                }
+                self.widgets.selection_bar.set_visible(selecting);
+                self.refresh_selection();
+            },
+            Msg::SelectCycle(id, selected) => {
+                if selected {
+                    self.model.selection.insert(id);
+                } else {
+                    self.model.selection.remove(&id);
+                }
+                self.refresh_selection();
+            },
+            Msg::Pin(id, pinned) => {
+                if self.model.ordering.is_pinned(id) != pinned {
+                    self.model.ordering.set_pinned(id, pinned);
+                    self.model.ordering.save(&self.model.descriptions);
+                    self.reorder();
+                }
+            },
+            Msg::Reorder(id, target) => {
+                self.model.ordering.move_before(id, target);
+                self.model.ordering.save(&self.model.descriptions);
+                self.reorder();
+            },
+            Msg::BatchDelete => {
+                let removed = self.model.descriptions.remove_many(&self.selected_ids());
+                for (id, _) in &removed {
+                    self.remove_description(*id);
+                }
+                self.finish_batch(Action::DeletedMany(removed));
+            },
+            Msg::BatchTag(add) => {
+                let tag = match crate::tag_list::normalize(&self.widgets.batch_tag_entry.get_text()) {
+                    Some(tag) => tag,
+                    None => return
+                };
+                let previous = self.model.descriptions.update_many(&self.selected_ids(), |description| {
+                    if !add {
+                        description.remove_tag(&tag)
+                    } else if description.tags.contains(&tag) {
+                        false
+                    } else {
+                        description.tags.push(tag.clone());
+                        true
+                    }
+                });
+                self.widgets.batch_tag_entry.set_text("");
+                self.widgets.batch_tag_popover.popdown();
+                self.refresh_cycles(previous.iter().map(|(id, _)| *id).collect());
+                self.finish_batch(Action::Changed(previous));
+            },
+            Msg::BatchColor(color) => {
+                let previous = self.model.descriptions.update_many(&self.selected_ids(), |description| {
+                    let changed = description.color.as_ref() != Some(&color);
+                    description.color = Some(color.clone());
+                    changed
+                });
+                self.refresh_cycles(previous.iter().map(|(id, _)| *id).collect());
+                self.finish_batch(Action::Changed(previous));
+            },
+            Msg::BatchArchive => {
+                let archived = self.model.descriptions.archive(&self.selected_ids());
+                for (id, _) in &archived {
+                    self.remove_description(*id);
+                }
+                self.finish_batch(Action::Archived(archived));
+            },
+            Msg::BatchExport => {
+                let ids = self.selected_ids();
+                if ids.is_empty() {
+                    return;
+                }
+                let dialog = gtk::FileChooserDialog::new(Some("Export Selected"), Some(&self.widgets.window), gtk::FileChooserAction::Save);
+                dialog.add_button("Cancel", gtk::ResponseType::Cancel);
+                dialog.add_button("Accept", gtk::ResponseType::Accept);
+                if dialog.run() == gtk::ResponseType::Accept {
+                    if let Some(filename) = dialog.get_filename() {
+                        match std::fs::File::create(&filename) {
+                            Ok(file) => {
+                                self.model.descriptions.export_selected_to_writer(file, &ids);
+                                self.notify(&format!("Exported {} cycles", ids.len()), false);
+                            },
+                            Err(err) => self.notify(&format!("Could not write {}: {}", filename.display(), err), false)
+                        }
+                    }
+                }
+                dialog.close();
+            },
+            Msg::OpenBackups => {
+                let settings = backup_settings(&self.model.descriptions, &self.model.preferences);
+                let backups = self.model.backups.list();
+                let element = or_panic!(Res init::<Restore>((self.widgets.window.clone(), settings, backups, self.model.preferences.clone())));
+                let stream = self.model.relm.stream().clone();
+                element.stream().observe(move |msg| {
+                    match msg {
+                        RestoreMsg::Restore(path) => stream.emit(Msg::RestoreBackup(path.clone())),
+                        RestoreMsg::SettingsChanged(settings) => stream.emit(Msg::SaveBackupSettings(settings.clone())),
+                        _ => {}
+                    }
+                });
+                self.model.restore_dialog = Some(element);
+            },
+            Msg::OpenDiagnostics => {
+                let report = self.model.descriptions.check();
+                let element = or_panic!(Res init::<Diagnostics>((self.widgets.window.clone(), report)));
+                connect!(element@DiagnosticsMsg::Repair, self.model.relm, Msg::Repair);
+                self.model.diagnostics_dialog = Some(element);
+            },
+            Msg::Repair => {
+                let repaired = self.model.descriptions.repair();
+                self.notify(&format!("Moved {} damaged cycles to quarantine", repaired.issues.len()), false);
+                if let Some(dialog) = self.model.diagnostics_dialog.as_ref() {
+                    dialog.stream().emit(DiagnosticsMsg::Report(self.model.descriptions.check()));
+                }
+            },
+            Msg::OpenTagManager => {
+                let element = or_panic!(Res init::<TagManager>((self.widgets.window.clone(), self.tag_summary())));
+                let stream = self.model.relm.stream().clone();
+                element.stream().observe(move |msg| {
+                    match msg {
+                        TagManagerMsg::SetColor(tag, color) => stream.emit(Msg::SetTagColor(tag.clone(), color.clone())),
+                        TagManagerMsg::SetIcon(tag, icon) => stream.emit(Msg::SetTagIcon(tag.clone(), icon.clone())),
+                        TagManagerMsg::Rename(from, to) => stream.emit(Msg::RenameTag(from.clone(), to.clone())),
+                        TagManagerMsg::Delete(tag) => stream.emit(Msg::DeleteTag(tag.clone())),
+                        TagManagerMsg::Refresh(_) => {}
+                    }
+                });
+                self.model.tag_manager = Some(element);
+            },
+            Msg::SetTagColor(tag, color) => {
+                let mut style = self.model.tag_styles.get(&tag).cloned().unwrap_or_default();
+                style.color = Some(color);
+                self.model.descriptions.set_tag_style(&tag, &style);
+                self.refresh_tags();
+            },
+            Msg::SetTagIcon(tag, icon) => {
+                let mut style = self.model.tag_styles.get(&tag).cloned().unwrap_or_default();
+                let icon = icon.trim();
+                style.icon = if icon.is_empty() { None } else { Some(icon.to_string()) };
+                self.model.descriptions.set_tag_style(&tag, &style);
+                self.refresh_tags();
+            },
+            Msg::RenameTag(from, to) => {
+                let changed = self.model.descriptions.rename_tag(&from, &to);
+                self.refresh_cycles(changed);
+                self.refresh_tags();
+            },
+            Msg::DeleteTag(tag) => {
+                let changed = self.model.descriptions.delete_tag(&tag);
+                self.refresh_cycles(changed);
+                self.refresh_tags();
+            },
+            Msg::RestoreBackup(path) => {
+                let dialog = gtk::MessageDialog::new(Some(&self.widgets.window), gtk::DialogFlags::MODAL, gtk::MessageType::Warning,
+                    gtk::ButtonsType::None, "Replace every cycle with the ones in this backup?");
+                dialog.set_property_secondary_text(Some("Changes made since the backup was written are lost, and this can't be undone."));
+                dialog.add_button("Cancel", gtk::ResponseType::Cancel);
+                dialog.add_button("Restore", gtk::ResponseType::Accept);
+                let result = dialog.run();
+                dialog.close();
+                if result != gtk::ResponseType::Accept {
+                    return;
+                }
+                let file = match std::fs::File::open(&path) {
+                    Ok(file) => file,
+                    Err(err) => return self.notify(&format!("Could not read {}: {}", path.display(), err), false)
+                };
+                let export = match crate::cache::read_export(file, self.model.descriptions.key()) {
+                    Ok(export) => export,
+                    Err(err) => return self.notify(&err.to_string(), false)
+                };
+                self.model.descriptions.replace(&export);
+                self.model.undo.clear();
+                self.model.ordering = Ordering::load(&self.model.descriptions);
+                self.reload_fields();
+                let ids: Vec<Uuid> = self.model.cycles.keys().cloned().collect();
+                for id in ids {
+                    self.remove_description(id);
+                }
+                self.load_descriptions();
+                self.notify(&format!("Restored {} cycles", export.cycles.len()), false);
+            },
+            Msg::SaveBackupSettings(settings) => {
+                settings.save(&self.model.descriptions);
+                self.model.preferences.backup_retention = settings.retention;
+                if let Some(gsettings) = self.model.settings.as_ref() {
+                    preferences_window::write_preferences(gsettings, &self.model.preferences);
+                }
+            },
+            Msg::OpenPreferences => {
+                let element = or_panic!(Res init::<PreferencesWindow>((self.widgets.window.clone(), self.model.preferences.clone(), self.encryption())));
+                let stream = self.model.relm.stream().clone();
+                element.stream().observe(move |msg| {
+                    match msg {
+                        PreferencesMsg::Changed(preferences) => stream.emit(Msg::SavePreferences(preferences.clone())),
+                        PreferencesMsg::Passphrase => stream.emit(Msg::OpenPassphrase),
+                        _ => {}
+                    }
+                });
+                self.model.preferences_window = Some(element);
+            },
+            Msg::Sync => {
+                let account = Account::from_env();
+                let folder = match sync::folder(&self.model.descriptions) {
+                    None if account.is_none() => self.choose_sync_folder(),
+                    folder => folder
+                };
+                if folder.is_none() && account.is_none() {
+                    return;
+                }
+                let mut summary = Summary::default();
+                let mut error = None;
+                if let Some(folder) = folder {
+                    match sync::run(&self.model.descriptions, &folder) {
+                        Ok(merged) => summary.add(merged),
+                        Err(err) => error = Some(err.to_string())
+                    }
+                }
+                if let Some(account) = account {
+                    let collection = Collection::new(&account.url, HttpTransport::new(&account));
+                    match caldav::sync(&self.model.descriptions, &collection) {
+                        Ok(merged) => summary.add(merged),
+                        Err(err) => error = Some(err.to_string())
+                    }
+                }
+                for id in summary.changed {
+                    match self.model.descriptions.get(id) {
+                        Some(description) => self.show_description(id, description),
+                        None => self.remove_description(id)
+                    }
+                }
+                self.refresh_suggestions();
+                let message = match (error, summary.conflicts) {
+                    (Some(error), _) => error,
+                    (None, 0) => format!("Synced with {} devices", summary.devices),
+                    (None, conflicts) => format!("Synced with {} devices, {} conflicts", summary.devices, conflicts)
+                };
+                self.notify(&message, false);
+                let conflicts: Vec<_> = self.model.descriptions.conflicts().collect();
+                if !conflicts.is_empty() {
+                    let element = or_panic!(Res init::<Conflicts>((self.widgets.window.clone(), conflicts)));
+                    let stream = self.model.relm.stream().clone();
+                    element.stream().observe(move |msg| {
+                        match msg {
+                            ConflictsMsg::Keep(id) => stream.emit(Msg::ResolveConflict(*id, false)),
+                            ConflictsMsg::UseOther(id) => stream.emit(Msg::ResolveConflict(*id, true))
+                        }
+                    });
+                    self.model.conflicts_dialog = Some(element);
+                }
+            },
+            Msg::ChooseSyncFolder => {
+                if self.choose_sync_folder().is_some() {
+                    self.model.relm.stream().emit(Msg::Sync);
+                }
+            },
+            Msg::ResolveConflict(id, take_other) => {
+                let live = self.model.descriptions.resolve_conflict(id, take_other);
+                if take_other {
+                    match live {
+                        Some(description) => self.show_description(id, description),
+                        None => self.remove_description(id)
+                    }
+                    self.refresh_suggestions();
+                }
+            },
+            Msg::OpenPassphrase => {
+                let element = or_panic!(Res init::<Passphrase>((self.widgets.window.clone(), self.encryption())));
+                let stream = self.model.relm.stream().clone();
+                element.stream().observe(move |msg| {
+                    if let PassphraseMsg::Submit(current, new) = msg {
+                        stream.emit(Msg::SetPassphrase(current.clone(), new.clone()));
+                    }
+                });
+                self.model.passphrase_dialog = Some(element);
+            },
+            Msg::SetPassphrase(current, new) => {
+                let dialog = match self.model.passphrase_dialog.as_ref() {
+                    Some(dialog) => dialog.stream().clone(),
+                    None => return
+                };
+                if self.model.descriptions.is_encrypted() && !self.model.descriptions.check_passphrase(&current.unwrap_or_default()) {
+                    dialog.emit(PassphraseMsg::Rejected);
+                    return;
+                }
+                let message = match (self.model.descriptions.is_encrypted(), new.is_some()) {
+                    (false, _) => "Encryption turned on",
+                    (true, true) => "Passphrase changed",
+                    (true, false) => "Encryption turned off"
+                };
+                if let Err(err) = self.model.descriptions.set_passphrase(new.as_deref()) {
+                    dialog.emit(PassphraseMsg::Done);
+                    self.notify(&format!("Nothing was changed, a record is unreadable: {}", err), false);
+                    return;
+                }
+                self.model.backups = Backups::default().with_key(self.model.descriptions.key().cloned());
+                dialog.emit(PassphraseMsg::Done);
+                if let Some(window) = self.model.preferences_window.as_ref() {
+                    window.stream().emit(PreferencesMsg::Encryption(self.encryption()));
+                }
+                self.notify(message, false);
+            },
+            Msg::SavePreferences(preferences) => {
+                if let Some(settings) = self.model.settings.as_ref() {
+                    preferences_window::write_preferences(settings, &preferences);
+                }
+                if preferences.theme != self.model.preferences.theme {
+                    preferences_window::apply_theme(preferences.theme);
+                }
+                if preferences.fields != self.model.preferences.fields {
+                    fields::set_definitions(&self.model.descriptions, &preferences.fields);
+                }
+                self.model.preferences = preferences;
+                for (_, element) in self.model.cycles.values() {
+                    element.stream().emit(CycleMsg::Preferences(self.model.preferences.clone()));
+                }
+                self.refresh_suggestions();
+            },
+            Msg::Restore(id) => {
+                if let Some(description) = self.model.descriptions.restore(id) {
+                    self.show_description(id, description);
+                    self.refresh_suggestions();
+                }
+            },
+            Msg::Purge(id) => {
+                self.model.descriptions.purge(id);
             },
             Msg::Add(description) => {
                 let id = self.model.descriptions.append(description.clone());
-                self.append_description(id, description);
+                self.append_description(id, description.clone());
+                self.record(Action::Added(id, description));
             },
             Msg::Delete(id) => {
+                let previous = self.model.descriptions.get(id);
                 self.model.descriptions.remove(id);
-                or_panic!(self.model.cycles.remove(&id)).1.widget().hide();
+                self.remove_description(id);
+                if let Some(previous) = previous {
+                    self.record(Action::Deleted(id, previous));
+                }
             },
             Msg::Updated((id, description)) => {
-                self.model.descriptions.update(id, description);
+                let previous = self.model.descriptions.get(id);
+                self.model.descriptions.update(id, description.clone());
+                if let Some((cached, _)) = self.model.cycles.get_mut(&id) {
+                    *cached = description;
+                }
                 self.widgets.window.show_all();
+                match previous {
+                    Some(previous) => self.record(Action::Edited(id, previous)),
+                    None => self.apply_filter()
+                }
+            },
+            Msg::Undo => {
+                self.widgets.toast_revealer.set_reveal_child(false);
+                match self.model.undo.pop() {
+                    Some(Action::Added(id, _)) => {
+                        self.model.descriptions.purge(id);
+                        self.remove_description(id);
+                    },
+                    Some(Action::Edited(id, previous)) => {
+                        self.model.descriptions.update(id, previous.clone());
+                        self.show_description(id, previous);
+                    },
+                    Some(Action::Deleted(id, previous)) => {
+                        let description = self.model.descriptions.restore(id).unwrap_or(previous);
+                        self.model.descriptions.update(id, description.clone());
+                        self.show_description(id, description);
+                    },
+                    Some(Action::Changed(entries)) => {
+                        for (id, previous) in entries {
+                            self.model.descriptions.update(id, previous.clone());
+                            self.show_description(id, previous);
+                        }
+                    },
+                    Some(Action::DeletedMany(entries)) => {
+                        for (id, previous) in entries {
+                            let description = self.model.descriptions.restore(id).unwrap_or(previous);
+                            self.model.descriptions.update(id, description.clone());
+                            self.show_description(id, description);
+                        }
+                    },
+                    Some(Action::Archived(entries)) => {
+                        for (id, previous) in entries {
+                            let description = self.model.descriptions.unarchive(id).unwrap_or(previous);
+                            self.model.descriptions.update(id, description.clone());
+                            self.show_description(id, description);
+                        }
+                    },
+                    Some(Action::Detached(attachment, data)) => {
+                        self.model.descriptions.insert_attachments(&[(attachment, data)]);
+                        self.refresh_detail();
+                    },
+                    Some(Action::Imported(merged)) => {
+                        self.model.descriptions.unmerge(&merged);
+                        self.model.ordering = Ordering::load(&self.model.descriptions);
+                        self.reload_fields();
+                        for (id, slot) in merged.cycles {
+                            match slot {
+                                Slot::Live(previous) => self.show_description(id, previous),
+                                _ => self.remove_description(id)
+                            }
+                        }
+                        self.refresh_detail();
+                    },
+                    None => {}
+                }
+                self.refresh_suggestions();
+            },
+            Msg::HideToast(serial) => {
+                if serial == self.model.toast_serial {
+                    self.widgets.toast_revealer.set_reveal_child(false);
+                }
+            },
+            Msg::DismissToast => {
+                self.widgets.toast_revealer.set_reveal_child(false);
             }
         }
     }
@@ -176,25 +909,83 @@ impl Widget for Win {
         self.widgets.window.clone()
     }
 
-    fn view(relm: &Relm<Self>, model: Self::Model) -> Self {
+    fn view(relm: &Relm<Self>, mut model: Self::Model) -> Self {
         let glade_src = include_str!("../ui/main.ui");
         let builder = gtk::Builder::from_string(glade_src);
 
         let window: libhandy::ApplicationWindow = or_panic!(builder.get_object("applicationWindow"));
+        let deck: libhandy::Deck = or_panic!(builder.get_object("mainDeck"));
+        let leaflet: libhandy::Leaflet = or_panic!(builder.get_object("mainLeaflet"));
+        let list_page: gtk::Box = or_panic!(builder.get_object("listPage"));
+        let list_header: libhandy::HeaderBar = or_panic!(builder.get_object("listHeader"));
+        let select_btn: gtk::ToggleButton = or_panic!(builder.get_object("selectButton"));
+        let selection_bar: gtk::ActionBar = or_panic!(builder.get_object("selectionBar"));
+        let selection_label: gtk::Label = or_panic!(builder.get_object("selectionLabel"));
+        let batch_delete_btn: gtk::Button = or_panic!(builder.get_object("batchDeleteButton"));
+        let batch_export_btn: gtk::Button = or_panic!(builder.get_object("batchExportButton"));
+        let batch_archive_btn: gtk::Button = or_panic!(builder.get_object("batchArchiveButton"));
+        let batch_color_btn: gtk::ColorButton = or_panic!(builder.get_object("batchColorButton"));
+        let batch_tag_popover: gtk::Popover = or_panic!(builder.get_object("batchTagPopover"));
+        let batch_tag_entry: gtk::Entry = or_panic!(builder.get_object("batchTagEntry"));
+        let batch_tag_add_btn: gtk::Button = or_panic!(builder.get_object("batchTagAddButton"));
+        let batch_tag_remove_btn: gtk::Button = or_panic!(builder.get_object("batchTagRemoveButton"));
         let menu_btn: gtk::Button = or_panic!(builder.get_object("menuButton"));
         let create_btn: gtk::Button = or_panic!(builder.get_object("createButton"));
         let cycle_list: gtk::ListBox = or_panic!(builder.get_object("cycleList"));
         let search_entry: gtk::SearchEntry = or_panic!(builder.get_object("searchEntry"));
         let search_button: gtk::Button = or_panic!(builder.get_object("searchButton"));
         let search_bar: libhandy::SearchBar = or_panic!(builder.get_object("searchBar"));
+        let tag_filter_bar: gtk::Box = or_panic!(builder.get_object("tagFilterBar"));
+        let tag_filter_list: gtk::Box = or_panic!(builder.get_object("tagFilterList"));
+        let tag_mode_btn: gtk::ToggleButton = or_panic!(builder.get_object("tagModeButton"));
+        let toast_revealer: gtk::Revealer = or_panic!(builder.get_object("toastRevealer"));
+        let toast_label: gtk::Label = or_panic!(builder.get_object("toastLabel"));
+        let undo_btn: gtk::Button = or_panic!(builder.get_object("undoButton"));
+        let toast_close_btn: gtk::Button = or_panic!(builder.get_object("toastCloseButton"));
 
         connect!(relm, search_button, connect_clicked(_), Msg::DisplaySearch);
         connect!(relm, search_entry, connect_changed(btn), Msg::Query(btn.get_text().into()));
+        connect!(relm, tag_mode_btn, connect_toggled(btn), {
+            btn.set_label(if btn.get_active() { "Match all" } else { "Match any" });
+            Msg::MatchAllTags(btn.get_active())
+        });
         connect!(relm, menu_btn, connect_clicked(_), Msg::OpenMenu);
+        connect!(relm, cycle_list, connect_row_activated(_, row), Msg::Select(row.clone()));
+        connect!(relm, select_btn, connect_toggled(btn), Msg::SelectionMode(btn.get_active()));
+        connect!(relm, batch_delete_btn, connect_clicked(_), Msg::BatchDelete);
+        connect!(relm, batch_export_btn, connect_clicked(_), Msg::BatchExport);
+        connect!(relm, batch_archive_btn, connect_clicked(_), Msg::BatchArchive);
+        connect!(relm, batch_color_btn, connect_color_set(btn), Msg::BatchColor(btn.get_rgba().to_string()));
+        connect!(relm, batch_tag_add_btn, connect_clicked(_), Msg::BatchTag(true));
+        connect!(relm, batch_tag_remove_btn, connect_clicked(_), Msg::BatchTag(false));
+        connect!(relm, batch_tag_entry, connect_activate(_), Msg::BatchTag(true));
+        connect!(relm, leaflet, connect_property_folded_notify(leaflet), {
+            use libhandy::LeafletExt;
+            Msg::Folded(leaflet.get_folded())
+        });
         connect!(relm, create_btn, connect_clicked(_), Msg::OpenCreate);
+        connect!(relm, undo_btn, connect_clicked(_), Msg::Undo);
+        connect!(relm, toast_close_btn, connect_clicked(_), Msg::DismissToast);
         connect!(relm, window, connect_delete_event(_, _), return (Some(Msg::Quit), Inhibit(false)));
 
+        let detail = or_panic!(Res init::<Detail>(()));
+        leaflet.add(detail.widget());
+        connect!(detail@DetailMsg::Back, relm, Msg::ShowList);
+        connect!(detail@DetailMsg::SaveTemplate, relm, Msg::SaveTemplate);
+        let stream = relm.stream().clone();
+        detail.stream().observe(move |msg| {
+            match msg {
+                DetailMsg::OpenUrl(url) => stream.emit(Msg::OpenUrl(url.clone())),
+                DetailMsg::Attach => stream.emit(Msg::AttachFile),
+                DetailMsg::OpenAttachment(attachment) => stream.emit(Msg::OpenAttachment(attachment.clone())),
+                DetailMsg::RemoveAttachment(id) => stream.emit(Msg::RemoveAttachment(*id)),
+                _ => {}
+            }
+        });
+        model.detail = Some(detail);
+
         window.show_all();
+        let editor_host = Host { window: window.clone(), deck, main_page: leaflet.clone().upcast() };
 
         let mut window = Win {
             model,
@@ -202,13 +993,43 @@ impl Widget for Win {
                 window,
                 search_bar,
                 search_entry,
+                tag_filter_bar,
+                tag_filter_list,
                 menu_btn,
                 create_btn,
                 cycle_list,
-                search_button
+                search_button,
+                toast_revealer,
+                toast_label,
+                undo_btn,
+                toast_close_btn,
+                leaflet,
+                list_page,
+                list_header,
+                select_btn,
+                selection_bar,
+                selection_label,
+                batch_tag_entry,
+                batch_tag_popover,
+                editor_host
             },
         };
+        {
+            use libhandy::LeafletExt;
+            let folded = window.widgets.leaflet.get_folded();
+            window.update(Msg::Folded(folded));
+        }
         window.load_descriptions();
+        let report = window.model.descriptions.check();
+        if !report.is_clean() {
+            window.notify(&format!("{} damaged cycles hidden, see Diagnostics", report.issues.len()), false);
+        }
+        if let Some(err) = window.model.descriptions.storage_error().map(str::to_string) {
+            window.notify(&format!("{}, showing the cycles from the database instead", err), false);
+        }
+        if let Some(err) = window.model.backup_error.take() {
+            window.notify(&err, false);
+        }
         window
     }
 }
@@ -219,20 +1040,300 @@ impl Win {
         for (id, cycle) in self.model.descriptions.entries() {
             self.append_description(id, cycle);
         }
+        self.refresh_suggestions();
     }
 
     fn append_description(&mut self, id: Uuid, description: Description) {
-        self.model.cycles.insert(id, (description.clone(), self.description_element(id, description)));
+        let element = self.description_element(id, description.clone());
+        if self.model.selecting {
+            element.stream().emit(CycleMsg::SelectionMode(true));
+        }
+        self.model.cycles.insert(id, (description, element));
+    }
+
+    fn show_description(&mut self, id: Uuid, description: Description) {
+        match self.model.cycles.get_mut(&id) {
+            Some((cached, element)) => {
+                *cached = description.clone();
+                element.stream().emit(CycleMsg::Render(description));
+            },
+            None => self.append_description(id, description)
+        }
+    }
+
+    fn remove_description(&mut self, id: Uuid) {
+        if let Some((_, element)) = self.model.cycles.remove(&id) {
+            match element.widget().get_parent() {
+                Some(row) => self.widgets.cycle_list.remove(&row),
+                None => element.widget().hide()
+            }
+        }
+    }
+
+    fn tag_summary(&self) -> Vec<(String, usize, TagStyle)> {
+        let mut counts = self.model.descriptions.tag_counts();
+        for name in self.model.tag_styles.keys() {
+            counts.entry(name.clone()).or_insert(0);
+        }
+        counts.into_iter()
+            .map(|(name, count)| {
+                let style = self.model.tag_styles.get(&name).cloned().unwrap_or_default();
+                (name, count, style)
+            })
+            .collect()
+    }
+
+    /// Asks for a file and the period it belongs to, then stores it with cycle `id`.
+    fn attach_file(&mut self, id: Uuid) {
+        let renewals = match self.model.cycles.get(&id) {
+            Some((description, _)) => description.past_renewals(chrono::Utc::now().date().naive_local()),
+            None => return
+        };
+        let dialog = gtk::FileChooserDialog::new(Some("Attach a file"), Some(&self.widgets.window), gtk::FileChooserAction::Open);
+        dialog.add_button("Cancel", gtk::ResponseType::Cancel);
+        dialog.add_button("Attach", gtk::ResponseType::Accept);
+        let period = gtk::ComboBoxText::new();
+        period.append(None, "Whole subscription");
+        for start in renewals.iter().take(PERIODS_OFFERED) {
+            period.append(None, &format!("Period from {}", self.model.preferences.format_date(*start)));
+        }
+        // The newest period is the likeliest home for a fresh invoice.
+        period.set_active(Some(if renewals.is_empty() { 0 } else { 1 }));
+        period.show();
+        dialog.set_extra_widget(&period);
+        let result = dialog.run();
+        let filename = dialog.get_filename();
+        let chosen = period.get_active().filter(|index| *index > 0)
+            .and_then(|index| renewals.get(index as usize - 1).copied());
+        dialog.close();
+        if result != gtk::ResponseType::Accept {
+            return;
+        }
+        if let Some(filename) = filename {
+            match std::fs::read(&filename) {
+                Ok(data) => {
+                    let name = filename.to_string_lossy();
+                    let attachment = self.model.descriptions.add_attachment(id, &name, &data, chosen);
+                    self.refresh_detail();
+                    self.notify(&format!("Attached “{}”", attachment.name), false);
+                },
+                Err(err) => self.notify(&format!("Could not read {}: {}", filename.display(), err), false)
+            }
+        }
+    }
+
+    /// Picks up field definitions an import or restore brought in.
+    fn reload_fields(&mut self) {
+        let definitions = fields::definitions(&self.model.descriptions);
+        if definitions == self.model.preferences.fields {
+            return;
+        }
+        self.model.preferences.fields = definitions;
+        for (_, element) in self.model.cycles.values() {
+            element.stream().emit(CycleMsg::Preferences(self.model.preferences.clone()));
+        }
+    }
+
+    fn refresh_suggestions(&mut self) {
+        let tags = self.tag_summary().into_iter().map(|(name, _, _)| name);
+        crate::tags::fill_suggestions(&self.model.tag_suggestions, tags);
+        self.refresh_filter_bar();
+        self.refresh_detail();
+        self.sync_order();
+    }
+
+    /// Brings the saved order up to date with the live cycles and applies it.
+    fn sync_order(&mut self) {
+        let before = self.model.ordering.clone();
+        self.model.ordering.sync(self.model.descriptions.entries().map(|(id, _)| id));
+        if self.model.ordering != before {
+            self.model.ordering.save(&self.model.descriptions);
+        }
+        self.reorder();
+    }
+
+    /// Asks for the shared folder sync files go in, and remembers it.
+    fn choose_sync_folder(&self) -> Option<PathBuf> {
+        let dialog = gtk::FileChooserDialog::new(Some("Choose a sync folder"), Some(&self.widgets.window), gtk::FileChooserAction::SelectFolder);
+        dialog.add_button("Cancel", gtk::ResponseType::Cancel);
+        dialog.add_button("Accept", gtk::ResponseType::Accept);
+        if let Some(folder) = sync::folder(&self.model.descriptions) {
+            dialog.set_current_folder(folder);
+        }
+        let result = dialog.run();
+        let folder = match result {
+            gtk::ResponseType::Accept => dialog.get_filename(),
+            _ => None
+        };
+        dialog.close();
+        if let Some(folder) = folder.as_ref() {
+            sync::set_folder(&self.model.descriptions, folder);
+        }
+        folder
+    }
+
+    fn encryption(&self) -> Encryption {
+        if !self.model.descriptions.can_encrypt() {
+            Encryption::Unavailable
+        } else if self.model.descriptions.is_encrypted() {
+            Encryption::On
+        } else {
+            Encryption::Off
+        }
+    }
+
+    /// The saved order, with each group sorted when a sort order other than
+    /// manual is chosen.
+    fn sorted_ids(&self) -> Vec<Uuid> {
+        if self.model.preferences.sort_order == SortOrder::Manual {
+            return self.model.ordering.ids().cloned().collect();
+        }
+        let today = chrono::Utc::now().date().naive_local();
+        let sorted = |ids: &[Uuid]| {
+            let mut ids: Vec<(Uuid, &Description)> = ids.iter()
+                .filter_map(|id| self.model.cycles.get(id).map(|(description, _)| (*id, description)))
+                .collect();
+            ids.sort_by(|(_, a), (_, b)| self.model.preferences.compare(a, b, today));
+            ids.into_iter().map(|(id, _)| id).collect::<Vec<Uuid>>()
+        };
+        let mut ids = sorted(&self.model.ordering.pinned);
+        ids.extend(sorted(&self.model.ordering.order));
+        ids
+    }
+
+    /// Moves the list rows into the saved order and updates the pin buttons.
+    fn reorder(&self) {
+        for (index, id) in self.sorted_ids().iter().enumerate() {
+            let element = match self.model.cycles.get(id) {
+                Some((_, element)) => element,
+                None => continue
+            };
+            element.stream().emit(CycleMsg::Pinned(self.model.ordering.is_pinned(*id)));
+            let row = match element.widget().get_parent().and_then(|row| row.downcast::<gtk::ListBoxRow>().ok()) {
+                Some(row) => row,
+                None => continue
+            };
+            if row.get_index() != index as i32 {
+                self.widgets.cycle_list.remove(&row);
+                self.widgets.cycle_list.insert(&row, index as i32);
+            }
+        }
+    }
+
+    /// Shows the selected cycle in the detail pane, or the placeholder once it is gone.
+    fn refresh_detail(&mut self) {
+        let detail = match self.model.detail.as_ref() {
+            Some(detail) => detail,
+            None => return
+        };
+        match self.model.selected.and_then(|id| Some((id, self.model.cycles.get(&id)?))) {
+            Some((id, (description, _))) => {
+                detail.stream().emit(DetailMsg::Show(description.clone(), self.model.tag_styles.clone(), self.model.preferences.clone()));
+                detail.stream().emit(DetailMsg::Attachments(self.model.descriptions.attachments(id)));
+            },
+            None => {
+                self.model.selected = None;
+                detail.stream().emit(DetailMsg::Clear);
+            }
+        }
+    }
+
+    /// Rebuilds the tag chips above the list, dropping selections for tags
+    /// no cycle uses anymore.
+    fn refresh_filter_bar(&mut self) {
+        let totals = filter::tag_totals(self.model.cycles.values().map(|(description, _)| description));
+        self.model.filter.tags.retain(|tag| totals.contains_key(tag));
+        for child in self.widgets.tag_filter_list.get_children() {
+            self.widgets.tag_filter_list.remove(&child);
+        }
+        for (name, total) in totals {
+            let label = if total.monthly > 0. {
+                format!("{} ({} · {}/mo)", name, total.count, self.model.preferences.format_price(total.monthly))
+            } else {
+                format!("{} ({})", name, total.count)
+            };
+            let button = gtk::ToggleButton::with_label(&label);
+            button.set_active(self.model.filter.tags.contains(&name));
+            connect!(self.model.relm, button, connect_toggled(btn), Msg::ToggleTag(name.clone(), btn.get_active()));
+            self.widgets.tag_filter_list.pack_start(&button, false, true, 0);
+        }
+        self.widgets.tag_filter_list.show_all();
+        self.widgets.tag_filter_bar.set_visible(!self.widgets.tag_filter_list.get_children().is_empty());
+        self.apply_filter();
+    }
+
+    fn apply_filter(&self) {
+        for (description, element) in self.model.cycles.values() {
+            element.widget().set_visible(self.model.filter.matches(description));
+        }
+    }
+
+    fn refresh_tags(&mut self) {
+        self.model.tag_styles = self.model.descriptions.tag_styles();
+        self.refresh_suggestions();
+        for (_, element) in self.model.cycles.values() {
+            element.stream().emit(CycleMsg::TagStyles(self.model.tag_styles.clone()));
+        }
+        if let Some(manager) = self.model.tag_manager.as_ref() {
+            manager.stream().emit(TagManagerMsg::Refresh(self.tag_summary()));
+        }
+    }
+
+    fn refresh_cycles(&mut self, ids: Vec<Uuid>) {
+        for id in ids {
+            if let Some(description) = self.model.descriptions.get(id) {
+                self.show_description(id, description);
+            }
+        }
+    }
+
+    fn selected_ids(&self) -> Vec<Uuid> {
+        self.model.selection.iter().cloned().collect()
+    }
+
+    fn refresh_selection(&self) {
+        self.widgets.selection_label.set_text(&format!("{} selected", self.model.selection.len()));
+    }
+
+    /// Records a batch action and leaves selection mode, unless nothing changed.
+    fn finish_batch(&mut self, action: Action) {
+        let changed = match &action {
+            Action::Changed(entries) | Action::DeletedMany(entries) | Action::Archived(entries) => !entries.is_empty(),
+            _ => true
+        };
+        if changed {
+            self.record(action);
+        }
+        self.widgets.select_btn.set_active(false);
+    }
+
+    fn record(&mut self, action: Action) {
+        self.refresh_suggestions();
+        self.notify(&action.message(), true);
+        self.model.undo.push(action);
+    }
+
+    fn notify(&mut self, message: &str, undoable: bool) {
+        self.widgets.toast_label.set_text(message);
+        self.widgets.undo_btn.set_visible(undoable);
+        self.widgets.toast_revealer.set_reveal_child(true);
+        self.model.toast_serial += 1;
+        let serial = self.model.toast_serial;
+        relm::timeout(self.model.relm.stream(), TOAST_TIMEOUT, move || Msg::HideToast(serial));
     }
 
     fn description_element(&self, id: Uuid, description: Description) -> Component<Cycle> {
-        let element = or_panic!(Res init::<Cycle>((id, description)));
+        let element = or_panic!(Res init::<Cycle>((id, description, self.model.tag_styles.clone(), self.model.tag_suggestions.clone(), self.widgets.editor_host.clone(), self.model.preferences.clone())));
         self.widgets.cycle_list.add(element.widget());
         let stream = self.model.relm.stream().clone();
         element.stream().observe(move |msg| {
             match msg {
                 CycleMsg::Delete(a) => stream.emit(Msg::Delete(*a)),
                 CycleMsg::Updated((id, data)) => stream.emit(Msg::Updated((*id, data.clone()))),
+                CycleMsg::Duplicated(data) => stream.emit(Msg::Add(data.clone())),
+                CycleMsg::Selected(id, selected) => stream.emit(Msg::SelectCycle(*id, *selected)),
+                CycleMsg::Pin(id, pinned) => stream.emit(Msg::Pin(*id, *pinned)),
+                CycleMsg::Dropped(id, target) => stream.emit(Msg::Reorder(*id, *target)),
                 _ => {}
             }
         });
@@ -240,9 +1341,7 @@ impl Win {
     }
 }
 
-fn tags_contain_value(tags: &Vec<String>, value: &str) -> bool {
-    for tag in tags {
-        if tag.to_lowercase().contains(&value.to_lowercase()) { return true }
-    }
-    false
+/// The saved backup settings, with the retention taken from the preferences.
+fn backup_settings(db: &Database, preferences: &Preferences) -> BackupSettings {
+    BackupSettings { retention: preferences.backup_retention, ..BackupSettings::load(db) }
 }
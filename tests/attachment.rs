@@ -0,0 +1,72 @@
+#![allow(non_snake_case)]
+mod common;
+
+use Cycles::{Attachment, Blob, Database, format_size};
+use common::netflix;
+use chrono::NaiveDate;
+
+const INVOICE: &[u8] = b"%PDF-1.4 invoice for April";
+
+#[test]
+fn attachments_are_listed_per_cycle_and_period() {
+    let db = Database::temporary();
+    let id = db.append(netflix());
+    let other = db.append(netflix());
+    let april = NaiveDate::from_ymd(2021, 4, 4);
+    let may = NaiveDate::from_ymd(2021, 5, 4);
+    let contract = db.add_attachment(id, "/home/me/contract.pdf", b"contract", None);
+    let invoice = db.add_attachment(id, "invoice.pdf", INVOICE, Some(april));
+    let later = db.add_attachment(id, "invoice-may.pdf", INVOICE, Some(may));
+    db.add_attachment(other, "unrelated.pdf", INVOICE, None);
+
+    assert_eq!(contract.name, "contract.pdf");
+    assert_eq!(invoice.size, INVOICE.len() as u64);
+    assert_eq!(db.attachments(id), vec![later.clone(), invoice.clone(), contract]);
+    assert_eq!(db.attachment_data(invoice.id), Some(INVOICE.to_vec()));
+
+    db.remove_attachment(later.id);
+    assert_eq!(db.attachment_data(later.id), None);
+    assert_eq!(db.attachments(id).len(), 2);
+}
+
+#[test]
+fn purging_a_cycle_drops_its_attachments() {
+    let db = Database::temporary();
+    let id = db.append(netflix());
+    let invoice = db.add_attachment(id, "invoice.pdf", INVOICE, None);
+    db.remove(id);
+    assert_eq!(db.attachments(id), vec![invoice.clone()]);
+    db.purge(id);
+    assert!(db.attachments(id).is_empty());
+    assert_eq!(db.attachment_data(invoice.id), None);
+}
+
+#[test]
+fn backups_carry_attachments() {
+    let mut db = Database::temporary();
+    let id = db.append(netflix());
+    let invoice = db.add_attachment(id, "invoice.pdf", INVOICE, None);
+    db.set_passphrase(Some("correct horse")).unwrap();
+    assert_eq!(db.attachment_data(invoice.id), Some(INVOICE.to_vec()));
+
+    let bundle = db.bundle(true);
+    assert_eq!(bundle.attachments, vec![(invoice.clone(), Blob(INVOICE.to_vec()))]);
+    let restored = Database::temporary();
+    restored.replace(&bundle);
+    assert_eq!(restored.attachments(id), vec![invoice.clone()]);
+    assert_eq!(restored.attachment_data(invoice.id), Some(INVOICE.to_vec()));
+}
+
+#[test]
+fn blobs_are_written_as_byte_strings() {
+    let data = vec![200u8; 1000];
+    let encoded = serde_cbor::to_vec(&Blob(data.clone())).unwrap();
+    assert!(encoded.len() < 1010);
+    assert_eq!(serde_cbor::from_slice::<Blob>(&encoded).unwrap(), Blob(data));
+
+    let attachment = Attachment::new(uuid::Uuid::new_v4(), "../../etc/passwd", 0, NaiveDate::from_ymd(2021, 4, 4), None);
+    assert_eq!(attachment.name, "passwd");
+    assert_eq!(format_size(512), "512 B");
+    assert_eq!(format_size(2048), "2 KB");
+    assert_eq!(format_size(3 * 1024 * 1024), "3.0 MB");
+}
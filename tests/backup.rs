@@ -0,0 +1,109 @@
+#![allow(non_snake_case)]
+mod common;
+
+use Cycles::{Backups, BackupSettings, Database, ExportError, Frequency};
+use common::netflix;
+use chrono::{Duration, NaiveDate};
+
+use std::path::PathBuf;
+
+fn scratch_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("cycles-backup-{}", uuid::Uuid::new_v4()))
+}
+
+#[test]
+fn backup_only_when_due() {
+    let dir = scratch_dir();
+    let backups = Backups::new(&dir);
+    let db = Database::temporary();
+    db.append(netflix());
+    let settings = BackupSettings { frequency: Frequency::Weekly, retention: 3 };
+    let day = NaiveDate::from_ymd(2021, 05, 01);
+
+    assert!(backups.run_if_due(&db, &settings, day).unwrap().is_some());
+    assert!(backups.run_if_due(&db, &settings, day + Duration::days(6)).unwrap().is_none());
+    assert!(backups.run_if_due(&db, &settings, day + Duration::days(7)).unwrap().is_some());
+
+    let list = backups.list();
+    assert_eq!(list.len(), 2);
+    assert_eq!(list[0].date, day + Duration::days(7));
+    assert_eq!(list[0].entries, 1);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn backup_rotation_keeps_newest() {
+    let dir = scratch_dir();
+    let backups = Backups::new(&dir);
+    let db = Database::temporary();
+    let settings = BackupSettings { frequency: Frequency::Daily, retention: 3 };
+    let day = NaiveDate::from_ymd(2021, 05, 01);
+    for offset in 0..5 {
+        backups.run_if_due(&db, &settings, day + Duration::days(offset)).unwrap();
+    }
+    let dates: Vec<_> = backups.list().into_iter().map(|backup| backup.date).collect();
+    assert_eq!(dates, vec![
+        day + Duration::days(4),
+        day + Duration::days(3),
+        day + Duration::days(2)
+    ]);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn restore_backup() {
+    let dir = scratch_dir();
+    let backups = Backups::new(&dir);
+    let db = Database::temporary();
+    let id = db.append(netflix());
+    backups.write(&db, NaiveDate::from_ymd(2021, 05, 01)).unwrap();
+    db.purge(id);
+    let tombstone = db.change(id).unwrap();
+    let extra = db.append(netflix());
+    db.archive(&[extra]);
+    std::thread::sleep(std::time::Duration::from_millis(5));
+
+    db.replace(&backups.list()[0].read().unwrap());
+    let entries: Vec<_> = db.entries().collect();
+    assert_eq!(entries, vec![(id, netflix())]);
+    assert_eq!(db.archived().count(), 0);
+    let restored = db.change(id).unwrap();
+    assert!(!restored.deleted);
+    assert!(restored.wins_over(&tombstone));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn backup_settings_round_trip() {
+    let db = Database::temporary();
+    assert_eq!(BackupSettings::load(&db), BackupSettings::default());
+    let settings = BackupSettings { frequency: Frequency::Monthly, retention: 12 };
+    settings.save(&db);
+    let loaded = BackupSettings::load(&db);
+    assert_eq!(loaded.frequency, Frequency::Monthly);
+    assert_eq!(loaded.retention, BackupSettings::default().retention);
+}
+
+#[test]
+fn damaged_backups_are_listed_but_not_read() {
+    let dir = scratch_dir();
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("cycles-2021-05-01.cbor"), b"not cbor").unwrap();
+    let list = Backups::new(&dir).list();
+    assert_eq!(list.len(), 1);
+    assert_eq!(list[0].entries, 0);
+    assert_eq!(list[0].read(), Err(ExportError::Damaged));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(list[0].read(), Err(ExportError::Unreadable));
+}
+
+#[test]
+fn unwritable_backup_dir_is_an_error() {
+    let dir = scratch_dir();
+    std::fs::write(&dir, b"a file, not a directory").unwrap();
+    let settings = BackupSettings { frequency: Frequency::Daily, retention: 3 };
+    let result = Backups::new(&dir).run_if_due(&Database::temporary(), &settings, NaiveDate::from_ymd(2021, 05, 01));
+    assert!(result.is_err());
+    std::fs::remove_file(dir).unwrap();
+}
@@ -0,0 +1,199 @@
+#![allow(non_snake_case)]
+mod common;
+
+use Cycles::{Attachment, Blob, Database, Description, Export, FieldDefinition, FieldKind, FieldValue, Trashed, field_definitions, set_field_definitions};
+use common::netflix;
+use chrono::{Utc, Duration, NaiveDate};
+
+#[test]
+fn remove_moves_to_trash() {
+    let db = Database::temporary();
+    let id = db.append(netflix());
+    db.remove(id);
+    assert_eq!(db.get(id), None);
+    assert_eq!(db.entries().count(), 0);
+    let trash: Vec<_> = db.trash().collect();
+    assert_eq!(trash.len(), 1);
+    assert_eq!(trash[0].0, id);
+    assert_eq!(trash[0].1.description, netflix());
+}
+
+#[test]
+fn restore_from_trash() {
+    let db = Database::temporary();
+    let id = db.append(netflix());
+    db.remove(id);
+    assert_eq!(db.restore(id), Some(netflix()));
+    assert_eq!(db.get(id), Some(netflix()));
+    assert_eq!(db.trash().count(), 0);
+    assert_eq!(db.restore(id), None);
+}
+
+#[test]
+fn purge_deletes_forever() {
+    let db = Database::temporary();
+    let live = db.append(netflix());
+    let trashed = db.append(netflix());
+    db.remove(trashed);
+    db.purge(live);
+    db.purge(trashed);
+    assert_eq!(db.entries().count(), 0);
+    assert_eq!(db.trash().count(), 0);
+}
+
+#[test]
+fn purge_expired_trash() {
+    let db = Database::temporary();
+    let today = Utc::now().date().naive_local();
+    let id = db.append(netflix());
+    db.remove(id);
+    assert_eq!(db.purge_expired(today + Duration::days(30)), 0);
+    assert_eq!(db.purge_expired(today + Duration::days(31)), 1);
+    assert_eq!(db.trash().count(), 0);
+}
+
+#[test]
+fn export_round_trip() {
+    let db = Database::temporary();
+    let live = db.append(netflix());
+    let trashed = db.append(netflix());
+    db.remove(trashed);
+
+    let mut plain = vec![];
+    db.export_to_writer(&mut plain, false);
+    let imported = Database::temporary().import_from_reader(&plain[..]);
+    assert_eq!(imported.cycles, vec![(live, netflix())]);
+    assert!(imported.trash.is_empty());
+
+    let mut bundle = vec![];
+    db.export_to_writer(&mut bundle, true);
+    let other = Database::temporary();
+    let imported = other.import_from_reader(&bundle[..]);
+    assert_eq!(imported.cycles, vec![(live, netflix())]);
+    assert_eq!(imported.trash.len(), 1);
+    assert_eq!(other.restore(trashed), Some(netflix()));
+}
+
+#[test]
+fn exports_carry_custom_fields() {
+    let db = Database::temporary();
+    let licences = FieldDefinition { name: "Licences".into(), kind: FieldKind::Number };
+    set_field_definitions(&db, &[licences.clone()]);
+    let mut description = netflix();
+    description.fields.insert("Licences".into(), FieldValue::Number(25.));
+    let id = db.append(description.clone());
+
+    let mut bundle = vec![];
+    db.export_to_writer(&mut bundle, false);
+    let other = Database::temporary();
+    let contract = FieldDefinition { name: "Contract".into(), kind: FieldKind::Text };
+    set_field_definitions(&other, &[contract.clone()]);
+    other.import_from_reader(&bundle[..]);
+    assert_eq!(other.get(id), Some(description));
+    assert_eq!(field_definitions(&other), vec![contract, licences]);
+}
+
+#[test]
+fn imported_cycles_leave_the_trash_and_archive() {
+    let db = Database::temporary();
+    let trashed = db.append(netflix());
+    db.remove(trashed);
+    let archived = db.append(netflix());
+    db.archive(&[archived]);
+    let spotify = Description { label: "Spotify".into(), ..netflix() };
+    db.merge(&Export { cycles: vec![(trashed, spotify.clone()), (archived, spotify.clone())], ..Export::default() });
+    assert_eq!(db.trash().count(), 0);
+    assert_eq!(db.archived().count(), 0);
+    assert_eq!(db.entries().count(), 2);
+
+    let deleted = Trashed { deleted: NaiveDate::from_ymd(2021, 04, 4), description: netflix() };
+    db.merge(&Export { trash: vec![(trashed, deleted)], archive: vec![(archived, netflix())], ..Export::default() });
+    assert_eq!(db.entries().count(), 0);
+    assert_eq!(db.trash().count(), 1);
+    assert_eq!(db.archived().count(), 1);
+    assert!(db.change(trashed).unwrap().deleted);
+}
+
+#[test]
+fn unmerge_reverts_every_part_of_an_import() {
+    let db = Database::temporary();
+    let live = db.append(netflix());
+    let new = uuid::Uuid::new_v4();
+    let invoice = Attachment::new(new, "invoice.pdf", 3, NaiveDate::from_ymd(2021, 04, 4), None);
+    let export = Export {
+        cycles: vec![(new, Description { label: "Spotify".into(), ..netflix() })],
+        trash: vec![(live, Trashed { deleted: NaiveDate::from_ymd(2021, 04, 4), description: netflix() })],
+        fields: vec![FieldDefinition { name: "Licences".into(), kind: FieldKind::Number }],
+        attachments: vec![(invoice, Blob(b"pdf".to_vec()))],
+        ..Export::default()
+    };
+    let merged = db.merge(&export);
+    assert_eq!(db.get(live), None);
+    assert_eq!(db.attachments(new).len(), 1);
+
+    db.unmerge(&merged);
+    assert_eq!(db.entries().collect::<Vec<_>>(), vec![(live, netflix())]);
+    assert_eq!(db.trash().count(), 0);
+    assert!(field_definitions(&db).is_empty());
+    assert!(db.attachments(new).is_empty());
+    assert!(!db.change(live).unwrap().deleted);
+    assert!(db.change(new).unwrap().deleted);
+}
+
+#[test]
+fn import_trash_entry() {
+    let db = Database::temporary();
+    let id = uuid::Uuid::new_v4();
+    db.trash_insert(id, Trashed { deleted: NaiveDate::from_ymd(2021, 04, 4), description: netflix() });
+    assert_eq!(db.purge_expired(NaiveDate::from_ymd(2021, 05, 5)), 1);
+}
+
+#[test]
+fn batch_remove_and_update() {
+    let db = Database::temporary();
+    let a = db.append(netflix());
+    let b = db.append(netflix());
+    let missing = uuid::Uuid::new_v4();
+
+    let previous = db.update_many(&[a, b, missing], |description| {
+        description.color = Some("rgb(1,2,3)".into());
+        true
+    });
+    assert_eq!(previous.len(), 2);
+    assert_eq!(db.get(a).unwrap().color.as_deref(), Some("rgb(1,2,3)"));
+    assert!(db.update_many(&[a], |description| description.remove_tag("video")).is_empty());
+
+    let removed = db.remove_many(&[a, missing]);
+    assert_eq!(removed.len(), 1);
+    assert_eq!(db.get(a), None);
+    assert_eq!(db.trash().count(), 1);
+    assert!(db.get(b).is_some());
+}
+
+#[test]
+fn archive_round_trip() {
+    let db = Database::temporary();
+    let id = db.append(netflix());
+    assert_eq!(db.archive(&[id]), vec![(id, netflix())]);
+    assert_eq!(db.entries().count(), 0);
+
+    let mut bundle = vec![];
+    db.export_to_writer(&mut bundle, true);
+    let other = Database::temporary();
+    assert_eq!(other.import_from_reader(&bundle[..]).archive, vec![(id, netflix())]);
+
+    assert_eq!(db.unarchive(id), Some(netflix()));
+    assert_eq!(db.archived().count(), 0);
+    assert_eq!(db.get(id), Some(netflix()));
+    assert_eq!(db.unarchive(id), None);
+}
+
+#[test]
+fn export_selected() {
+    let db = Database::temporary();
+    let a = db.append(netflix());
+    db.append(netflix());
+    let mut data = vec![];
+    db.export_selected_to_writer(&mut data, &[a]);
+    assert_eq!(Database::temporary().import_from_reader(&data[..]).cycles, vec![(a, netflix())]);
+}
@@ -0,0 +1,221 @@
+#![allow(non_snake_case)]
+mod common;
+
+use Cycles::{CalDavError, Collection, Database, Description, FieldValue, Request, Response, Transport, from_ical, sync_caldav, to_ical};
+use chrono::NaiveDate;
+use uuid::Uuid;
+
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+
+const HOST: &str = "http://localhost";
+const URL: &str = "http://localhost/calendars/cycles/";
+
+fn netflix() -> Description {
+    Description {
+        tags: vec!["stream".into(), "tv, film".into()],
+        label: "Netflix; the \"good\" plan".into(),
+        description: Some("Shared with\nthe family".into()),
+        color: Some("#e50914".into()),
+        notes: Some("Cancel before the trial ends; ask about the family plan".into()),
+        url: Some("https://www.netflix.com/account".into()),
+        account: Some("me@example.com".into()),
+        payment: Some("Visa, ending 1234".into()),
+        fields: vec![
+            ("Cost centre".to_string(), FieldValue::Text("MKT; 204, Berlin".into())),
+            ("Licences".to_string(), FieldValue::Number(5.)),
+            ("Signed: \"on\" ^date".to_string(), FieldValue::Date(NaiveDate::from_ymd(2021, 3, 1)))
+        ].into_iter().collect(),
+        ..common::netflix()
+    }
+}
+
+/// Keeps events in memory and answers like a CalDAV collection, etags included.
+#[derive(Default)]
+struct MockServer {
+    events: RefCell<BTreeMap<String, (String, String)>>,
+    serial: RefCell<u32>,
+    /// Answers uploads without an `ETag` header, as some servers do.
+    etagless: Cell<bool>,
+    requests: RefCell<Vec<Request>>
+}
+
+impl MockServer {
+
+    fn next_etag(&self) -> String {
+        *self.serial.borrow_mut() += 1;
+        format!("\"{}\"", self.serial.borrow())
+    }
+
+    /// Changes an event the way another client would.
+    fn edit(&self, id: Uuid, description: &Description) {
+        let etag = self.next_etag();
+        self.events.borrow_mut().insert(format!("{}{}.ics", URL, id), (etag, to_ical(id, description)));
+    }
+
+    fn requests(&self, method: &str) -> Vec<Request> {
+        self.requests.borrow().iter().filter(|request| request.method == method).cloned().collect()
+    }
+
+    fn stored(&self, id: Uuid) -> Option<Description> {
+        let events = self.events.borrow();
+        let (_, data) = events.get(&format!("{}{}.ics", URL, id))?;
+        from_ical(data).map(|(_, description)| description)
+    }
+
+    fn multistatus(&self) -> String {
+        let responses: Vec<String> = self.events.borrow().iter().map(|(href, (etag, data))| format!(
+            "<d:response><d:href>{}</d:href><d:propstat><d:prop><d:getetag>{}</d:getetag>\
+             <cal:calendar-data>{}</cal:calendar-data></d:prop><d:status>HTTP/1.1 200 OK</d:status></d:propstat></d:response>",
+            href.trim_start_matches(HOST), etag.replace('"', "&quot;"), data.replace('&', "&amp;").replace('<', "&lt;").replace('\r', "&#13;")
+        )).collect();
+        format!("<?xml version=\"1.0\"?><d:multistatus xmlns:d=\"DAV:\" xmlns:cal=\"urn:ietf:params:xml:ns:caldav\">{}</d:multistatus>", responses.concat())
+    }
+}
+
+impl Transport for MockServer {
+
+    fn send(&self, request: Request) -> Result<Response, CalDavError> {
+        self.requests.borrow_mut().push(request.clone());
+        let header = |name| request.headers.iter().find(|(key, _)| *key == name).map(|(_, value)| value.clone());
+        let current = self.events.borrow().get(&request.url).map(|(etag, _)| etag.clone());
+        let precondition = match (header("If-Match"), header("If-None-Match")) {
+            (Some(etag), _) => current.as_ref() == Some(&etag),
+            (_, Some(_)) => current.is_none(),
+            _ => true
+        };
+        let response = |status, etag, body| Ok(Response { status, etag, body });
+        match request.method {
+            "REPORT" => response(207, None, self.multistatus()),
+            "PUT" if !precondition => response(412, None, String::new()),
+            "PUT" => {
+                let etag = self.next_etag();
+                self.events.borrow_mut().insert(request.url, (etag.clone(), request.body));
+                let etag = Some(etag).filter(|_| !self.etagless.get());
+                response(if current.is_some() { 204 } else { 201 }, etag, String::new())
+            },
+            "DELETE" if current.is_none() => response(404, None, String::new()),
+            "DELETE" if !precondition => response(412, None, String::new()),
+            "DELETE" => {
+                self.events.borrow_mut().remove(&request.url);
+                response(204, None, String::new())
+            },
+            _ => response(405, None, String::new())
+        }
+    }
+}
+
+fn tick() {
+    std::thread::sleep(std::time::Duration::from_millis(5));
+}
+
+#[test]
+fn ical_round_trip() {
+    let id = Uuid::new_v4();
+    let data = to_ical(id, &netflix());
+    assert!(data.contains("RRULE:FREQ=MONTHLY;INTERVAL=1"));
+    assert!(data.contains("URL:https://www.netflix.com/account"));
+    assert!(data.lines().all(|line| line.len() <= 76));
+    assert_eq!(from_ical(&data), Some((id, netflix())));
+}
+
+#[test]
+fn pushes_and_pulls_through_the_server() {
+    let server = MockServer::default();
+    let (phone, laptop) = (Database::temporary(), Database::temporary());
+    let id = phone.append(netflix());
+    sync_caldav(&phone, &Collection::new(URL, &server)).unwrap();
+    assert_eq!(server.stored(id), Some(netflix()));
+
+    let summary = sync_caldav(&laptop, &Collection::new(URL, &server)).unwrap();
+    assert_eq!(summary.changed, vec![id]);
+    assert_eq!(laptop.get(id), Some(netflix()));
+
+    let cheaper = Description { price: Some(9.99), ..netflix() };
+    server.edit(id, &cheaper);
+    sync_caldav(&phone, &Collection::new(URL, &server)).unwrap();
+    assert_eq!(phone.get(id), Some(cheaper));
+}
+
+#[test]
+fn deletions_go_both_ways() {
+    let server = MockServer::default();
+    let db = Database::temporary();
+    let (kept, dropped) = (db.append(netflix()), db.append(netflix()));
+    sync_caldav(&db, &Collection::new(URL, &server)).unwrap();
+
+    tick();
+    db.remove(dropped);
+    sync_caldav(&db, &Collection::new(URL, &server)).unwrap();
+    assert_eq!(server.stored(dropped), None);
+
+    server.events.borrow_mut().clear();
+    sync_caldav(&db, &Collection::new(URL, &server)).unwrap();
+    assert_eq!(db.get(kept), None);
+    assert_eq!(db.trash().count(), 2);
+}
+
+#[test]
+fn etag_mismatch_keeps_local_and_lists_a_conflict() {
+    let server = MockServer::default();
+    let db = Database::temporary();
+    let id = db.append(netflix());
+    sync_caldav(&db, &Collection::new(URL, &server)).unwrap();
+
+    tick();
+    let local = Description { label: "Netflix 4K".into(), ..netflix() };
+    db.update(id, local.clone());
+    let remote = Description { price: Some(15.49), ..netflix() };
+    server.edit(id, &remote);
+    let summary = sync_caldav(&db, &Collection::new(URL, &server)).unwrap();
+    assert_eq!(summary.conflicts, 1);
+    assert_eq!(db.get(id), Some(local.clone()));
+    assert_eq!(server.stored(id), Some(local));
+    let conflict = db.conflicts().next().unwrap();
+    assert_eq!(conflict.other, Some(remote));
+}
+
+#[test]
+fn uses_the_listed_href() {
+    let server = MockServer::default();
+    let db = Database::temporary();
+    let id = Uuid::new_v4();
+    let href = format!("{}from-another-client.ics", URL);
+    server.events.borrow_mut().insert(href.clone(), ("\"1\"".into(), to_ical(id, &netflix())));
+    sync_caldav(&db, &Collection::new(URL, &server)).unwrap();
+    assert_eq!(db.get(id), Some(netflix()));
+
+    tick();
+    let cheaper = Description { price: Some(9.99), ..netflix() };
+    db.update(id, cheaper.clone());
+    sync_caldav(&db, &Collection::new(URL, &server)).unwrap();
+    assert_eq!(server.events.borrow().keys().cloned().collect::<Vec<_>>(), vec![href.clone()]);
+    assert_eq!(from_ical(&server.events.borrow()[&href].1), Some((id, cheaper)));
+
+    tick();
+    db.remove(id);
+    sync_caldav(&db, &Collection::new(URL, &server)).unwrap();
+    assert!(server.events.borrow().is_empty());
+    assert!(server.requests("DELETE").iter().all(|request| request.url == href));
+}
+
+#[test]
+fn missing_etags_are_never_sent_empty() {
+    let server = MockServer::default();
+    server.etagless.set(true);
+    let db = Database::temporary();
+    let id = db.append(netflix());
+    let collection = Collection::new(URL, &server);
+    sync_caldav(&db, &collection).unwrap();
+
+    tick();
+    let cheaper = Description { price: Some(9.99), ..netflix() };
+    db.update(id, cheaper.clone());
+    let summary = sync_caldav(&db, &collection).unwrap();
+    assert_eq!((summary.conflicts, summary.skipped), (0, 0));
+    assert_eq!(server.stored(id), Some(cheaper));
+    let puts = server.requests("PUT");
+    assert_eq!(puts.len(), 2);
+    let if_match: Vec<_> = puts[1].headers.iter().filter(|(name, _)| *name == "If-Match").collect();
+    assert_eq!(if_match, vec![&("If-Match", "\"1\"".to_string())]);
+}
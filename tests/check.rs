@@ -0,0 +1,94 @@
+#![allow(non_snake_case)]
+mod common;
+
+use Cycles::{Database, Description, Problem};
+use common::netflix;
+use uuid::Uuid;
+
+use std::path::PathBuf;
+
+fn scratch_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("cycles-check-{}", Uuid::new_v4()))
+}
+
+fn damaged_database(path: &PathBuf) -> Uuid {
+    let db = sled::open(path).unwrap();
+    let cycles = db.open_tree("cycles").unwrap();
+    let good = Uuid::new_v4();
+    cycles.insert(good.as_bytes(), serde_cbor::to_vec(&netflix()).unwrap()).unwrap();
+    cycles.insert(Uuid::new_v4().as_bytes(), &b"not cbor"[..]).unwrap();
+    cycles.insert(&b"short"[..], serde_cbor::to_vec(&netflix()).unwrap()).unwrap();
+    let zero = Description { interval_multiplier: 0, label: " ".into(), ..netflix() };
+    cycles.insert(Uuid::new_v4().as_bytes(), serde_cbor::to_vec(&zero).unwrap()).unwrap();
+    db.flush().unwrap();
+    good
+}
+
+#[test]
+fn check_reports_damaged_records() {
+    let path = scratch_dir();
+    let good = damaged_database(&path);
+    let db = Database::open(&path);
+
+    let entries: Vec<Uuid> = db.entries().map(|(id, _)| id).collect();
+    assert!(entries.contains(&good));
+
+    let report = db.check();
+    assert_eq!(report.checked, 4);
+    assert_eq!(report.issues.len(), 3);
+    let problems: Vec<Problem> = report.issues.iter().flat_map(|x| x.problems.clone()).collect();
+    assert!(problems.contains(&Problem::InvalidKey));
+    assert!(problems.contains(&Problem::InvalidMultiplier(0)));
+    assert!(problems.contains(&Problem::EmptyLabel));
+    assert!(problems.iter().any(|x| matches!(x, Problem::Undecodable(_))));
+    drop(db);
+    std::fs::remove_dir_all(path).unwrap();
+}
+
+#[test]
+fn repair_quarantines_damaged_records() {
+    let path = scratch_dir();
+    let good = damaged_database(&path);
+    let db = Database::open(&path);
+
+    let report = db.repair();
+    assert_eq!(report.issues.len(), 3);
+    assert!(db.check().is_clean());
+    assert_eq!(db.get(good), Some(netflix()));
+
+    let quarantined: Vec<_> = db.quarantined().collect();
+    assert_eq!(quarantined.len(), 3);
+    assert!(quarantined.iter().any(|x| x.value == b"not cbor".to_vec()));
+    drop(db);
+    std::fs::remove_dir_all(path).unwrap();
+}
+
+#[test]
+fn check_clean_database() {
+    let db = Database::temporary();
+    db.append(netflix());
+    let report = db.check();
+    assert!(report.is_clean());
+    assert_eq!(report.summary(), "Checked 1 cycles, found 0 problems");
+}
+
+#[test]
+fn damaged_records_are_skipped_not_fatal() {
+    let path = scratch_dir();
+    let db = sled::open(&path).unwrap();
+    let (live, trashed) = (Uuid::new_v4(), Uuid::new_v4());
+    db.open_tree("cycles").unwrap().insert(live.as_bytes(), &b"not cbor"[..]).unwrap();
+    db.open_tree("trash").unwrap().insert(trashed.as_bytes(), &b"not cbor"[..]).unwrap();
+    db.flush().unwrap();
+    drop(db);
+    let db = Database::open(&path);
+
+    assert_eq!(db.get(live), None);
+    assert!(db.remove_many(&[live]).is_empty());
+    assert!(db.archive(&[live]).is_empty());
+    assert_eq!(db.trash().count(), 0);
+    assert_eq!(db.restore(trashed), None);
+    assert_eq!(db.repair().issues.len(), 1);
+    drop(db);
+    std::fs::remove_dir_all(path).unwrap();
+}
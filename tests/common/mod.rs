@@ -0,0 +1,21 @@
+use Cycles::{Description, Interval};
+use chrono::NaiveDate;
+
+/// The cycle most tests start from, varied with struct update syntax.
+pub fn netflix() -> Description {
+    Description {
+        tags: vec!["stream".into()],
+        label: "Netflix".into(),
+        description: None,
+        color: None,
+        start: NaiveDate::from_ymd(2021, 04, 4),
+        interval: Interval::Monthly,
+        interval_multiplier: 1,
+        price: Some(12.99),
+        notes: None,
+        url: None,
+        account: None,
+        payment: None,
+        fields: Default::default()
+    }
+}
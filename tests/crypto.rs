@@ -0,0 +1,127 @@
+#![allow(non_snake_case)]
+mod common;
+
+use Cycles::{Database, ExportError, Lock, TagStyle};
+use common::netflix;
+use uuid::Uuid;
+
+use std::path::PathBuf;
+
+fn scratch_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("cycles-crypto-{}", Uuid::new_v4()))
+}
+
+#[test]
+fn lock_only_opens_with_its_passphrase() {
+    let (lock, key) = Lock::new("correct horse");
+    assert_eq!(lock.unlock("correct horse"), Some(key.clone()));
+    assert_eq!(lock.unlock("battery staple"), None);
+    let sealed = key.seal(b"secret");
+    assert_ne!(sealed, b"secret".to_vec());
+    assert_eq!(key.open(&sealed), Ok(b"secret".to_vec()));
+}
+
+#[test]
+fn passphrase_can_be_set_changed_and_removed() {
+    let mut db = Database::temporary();
+    let id = db.append(netflix());
+    db.set_passphrase(Some("correct horse")).unwrap();
+    assert!(db.is_encrypted());
+    assert!(!db.is_locked());
+    assert!(db.check_passphrase("correct horse"));
+    assert!(!db.check_passphrase("battery staple"));
+    assert_eq!(db.get(id), Some(netflix()));
+
+    db.set_passphrase(Some("battery staple")).unwrap();
+    assert!(db.check_passphrase("battery staple"));
+    assert_eq!(db.entries().collect::<Vec<_>>(), vec![(id, netflix())]);
+
+    db.set_passphrase(None).unwrap();
+    assert!(!db.is_encrypted());
+    assert_eq!(db.get(id), Some(netflix()));
+}
+
+#[test]
+fn tags_and_settings_are_sealed_too() {
+    let path = scratch_dir();
+    let mut db = Database::open(&path);
+    db.append(netflix());
+    db.set_tag_style("stream", &TagStyle { color: Some("red".into()), icon: None });
+    db.set_setting("templates", &vec!["Gym".to_string()]);
+    db.set_passphrase(Some("correct horse")).unwrap();
+    drop(db);
+
+    let raw = sled::open(&path).unwrap();
+    assert!(raw.open_tree("tags").unwrap().iter().keys().all(|key| &*key.unwrap() != b"stream"));
+    drop(raw);
+    let mut db = Database::open(&path);
+    assert!(db.is_locked());
+    assert!(db.tag_styles().is_empty());
+    assert_eq!(db.setting::<Vec<String>>("templates"), None);
+    assert!(db.unlock("correct horse"));
+    assert_eq!(db.tag_styles()["stream"].color.as_deref(), Some("red"));
+    assert_eq!(db.setting::<Vec<String>>("templates"), Some(vec!["Gym".to_string()]));
+    drop(db);
+    std::fs::remove_dir_all(path).unwrap();
+}
+
+#[test]
+fn unreadable_records_stop_a_passphrase_change() {
+    let path = scratch_dir();
+    let mut db = Database::open(&path);
+    let id = db.append(netflix());
+    db.set_passphrase(Some("correct horse")).unwrap();
+    drop(db);
+
+    let raw = sled::open(&path).unwrap();
+    raw.open_tree("cycles").unwrap().insert(Uuid::new_v4().as_bytes(), &b"not sealed"[..]).unwrap();
+    raw.flush().unwrap();
+    drop(raw);
+    let mut db = Database::open(&path);
+    assert!(db.unlock("correct horse"));
+    assert!(db.set_passphrase(None).is_err());
+    assert!(db.check_passphrase("correct horse"));
+    assert_eq!(db.get(id), Some(netflix()));
+    drop(db);
+    std::fs::remove_dir_all(path).unwrap();
+}
+
+#[test]
+fn sealed_exports_need_the_key() {
+    let mut db = Database::temporary();
+    let id = db.append(netflix());
+    db.set_passphrase(Some("correct horse")).unwrap();
+    let mut sealed = vec![];
+    db.export_to_writer(&mut sealed, false);
+    assert_eq!(Cycles::read_export(&sealed[..], None), Err(ExportError::Locked));
+    let export = Cycles::read_export(&sealed[..], db.key()).unwrap();
+    assert_eq!(export.cycles, vec![(id, netflix())]);
+
+    let mut plain = vec![];
+    db.export_plain_to_writer(&mut plain, false);
+    assert_eq!(Cycles::read_export(&plain[..], None).unwrap().cycles, vec![(id, netflix())]);
+}
+
+#[test]
+fn protected_exports_open_with_their_password() {
+    let db = Database::temporary();
+    let id = db.append(netflix());
+    let mut protected = vec![];
+    db.export_protected_to_writer(&mut protected, false, "usb stick");
+    assert_eq!(Cycles::read_export(&protected[..], None), Err(ExportError::Locked));
+    assert_eq!(Cycles::read_protected_export(&protected[..], "wrong"), Err(ExportError::Locked));
+    let export = Cycles::read_protected_export(&protected[..], "usb stick").unwrap();
+    assert_eq!(export.cycles, vec![(id, netflix())]);
+
+    let mut plain = vec![];
+    db.export_to_writer(&mut plain, false);
+    assert_eq!(Cycles::read_protected_export(&plain[..], "anything").unwrap().cycles, vec![(id, netflix())]);
+}
+
+#[test]
+fn newer_sealed_versions_are_refused() {
+    #[derive(serde::Serialize)]
+    struct Sealed { version: u32, salt: Vec<u8>, sealed: Vec<u8> }
+    let future = serde_cbor::to_vec(&Sealed { version: 99, salt: vec![0; 16], sealed: vec![0; 64] }).unwrap();
+    assert_eq!(Cycles::read_protected_export(&future[..], "usb stick"), Err(ExportError::Unsupported(99)));
+}
@@ -1,5 +1,5 @@
 #![allow(non_snake_case)]
-use Cycles::{Description, Interval, RenderArgs};
+use Cycles::{Description, Interval, Phase, RenderArgs};
 use chrono::NaiveDate;
 
 fn monday() -> Description {
@@ -10,28 +10,44 @@ fn monday() -> Description {
         color: None,
         start: NaiveDate::from_ymd(2021, 04, 4),
         interval: Interval::Weekly,
-        interval_multiplier: 1
+        interval_multiplier: 1,
+        price: None,
+        notes: None,
+        url: None,
+        account: None,
+        payment: None,
+        fields: Default::default()
     }
 }
+
+fn monthly(start: NaiveDate) -> Description {
+    Description {
+        label: "Monthly".into(),
+        start,
+        interval: Interval::Monthly,
+        ..monday()
+    }
+}
+
 #[test]
 fn render_small() {
     let monday = monday();
     let datetime = NaiveDate::from_ymd(2021, 04, 5);
     assert_eq!(RenderArgs {
+        phase: Phase::Running,
         start_date: NaiveDate::from_ymd(2021, 04, 4),
         end_date: NaiveDate::from_ymd(2021, 04, 11),
         remaining_days: 6,
-        bar_length: 6.0,
-        bar_value: 1.
+        progress: 1. / 7.
     }, monday.render_arguments(Some(datetime)));
 
     let datetime = NaiveDate::from_ymd(2021, 04, 6);
     assert_eq!(RenderArgs {
+        phase: Phase::Running,
         start_date: NaiveDate::from_ymd(2021, 04, 4),
         end_date: NaiveDate::from_ymd(2021, 04, 11),
         remaining_days: 5,
-        bar_length: 6.0,
-        bar_value: 2.
+        progress: 2. / 7.
     }, monday.render_arguments(Some(datetime)));
 }
 
@@ -40,10 +56,134 @@ fn render_large() {
     let monday = monday();
     let datetime = NaiveDate::from_ymd(2021, 04, 13);
     assert_eq!(RenderArgs {
+        phase: Phase::Running,
         start_date: NaiveDate::from_ymd(2021, 04, 11),
         end_date: NaiveDate::from_ymd(2021, 04, 18),
         remaining_days: 5,
-        bar_length: 6.0,
-        bar_value: 2.
+        progress: 2. / 7.
     }, monday.render_arguments(Some(datetime)));
 }
+
+#[test]
+fn render_start_day() {
+    let monday = monday();
+    let args = monday.render_arguments(Some(monday.start));
+    assert_eq!(args.phase, Phase::Running);
+    assert_eq!(args.start_date, monday.start);
+    assert_eq!(args.remaining_days, 7);
+    assert_eq!(args.progress, 0.);
+}
+
+#[test]
+fn render_renewal_day() {
+    let monday = monday();
+    let last_day = monday.render_arguments(Some(NaiveDate::from_ymd(2021, 04, 10)));
+    assert_eq!(last_day.end_date, NaiveDate::from_ymd(2021, 04, 11));
+    assert_eq!(last_day.remaining_days, 1);
+    assert_eq!(last_day.progress, 6. / 7.);
+
+    let renewal = monday.render_arguments(Some(NaiveDate::from_ymd(2021, 04, 11)));
+    assert_eq!(renewal.start_date, NaiveDate::from_ymd(2021, 04, 11));
+    assert_eq!(renewal.end_date, NaiveDate::from_ymd(2021, 04, 18));
+    assert_eq!(renewal.remaining_days, 7);
+    assert_eq!(renewal.progress, 0.);
+}
+
+#[test]
+fn render_future_start() {
+    let monday = monday();
+    let args = monday.render_arguments(Some(NaiveDate::from_ymd(2021, 03, 30)));
+    assert_eq!(RenderArgs {
+        phase: Phase::Pending,
+        start_date: NaiveDate::from_ymd(2021, 04, 4),
+        end_date: NaiveDate::from_ymd(2021, 04, 11),
+        remaining_days: 5,
+        progress: 0.
+    }, args);
+    assert_eq!(args.remaining_text(), "starts in 5 days");
+
+    let tomorrow = monday.render_arguments(Some(NaiveDate::from_ymd(2021, 04, 3)));
+    assert_eq!(tomorrow.remaining_text(), "starts tomorrow");
+}
+
+#[test]
+fn render_multiplier() {
+    let mut fortnight = monday();
+    fortnight.interval_multiplier = 2;
+    let args = fortnight.render_arguments(Some(NaiveDate::from_ymd(2021, 04, 17)));
+    assert_eq!(args.start_date, NaiveDate::from_ymd(2021, 04, 4));
+    assert_eq!(args.end_date, NaiveDate::from_ymd(2021, 04, 18));
+    assert_eq!(args.remaining_days, 1);
+
+    let args = fortnight.render_arguments(Some(NaiveDate::from_ymd(2021, 04, 18)));
+    assert_eq!(args.start_date, NaiveDate::from_ymd(2021, 04, 18));
+    assert_eq!(args.end_date, NaiveDate::from_ymd(2021, 05, 02));
+    assert_eq!(args.remaining_days, 14);
+    assert_eq!(args.progress, 0.);
+}
+
+#[test]
+fn render_zero_multiplier() {
+    let mut broken = monday();
+    broken.interval_multiplier = 0;
+    let args = broken.render_arguments(Some(NaiveDate::from_ymd(2021, 04, 5)));
+    assert_eq!(args.end_date, NaiveDate::from_ymd(2021, 04, 11));
+    assert_eq!(args.remaining_days, 6);
+}
+
+#[test]
+fn render_monthly() {
+    let cycle = monthly(NaiveDate::from_ymd(2021, 01, 15));
+    let args = cycle.render_arguments(Some(NaiveDate::from_ymd(2021, 03, 01)));
+    assert_eq!(args.start_date, NaiveDate::from_ymd(2021, 02, 15));
+    assert_eq!(args.end_date, NaiveDate::from_ymd(2021, 03, 15));
+    assert_eq!(args.remaining_days, 14);
+    assert_eq!(args.progress, 14. / 28.);
+
+    let renewal = cycle.render_arguments(Some(NaiveDate::from_ymd(2021, 03, 15)));
+    assert_eq!(renewal.start_date, NaiveDate::from_ymd(2021, 03, 15));
+    assert_eq!(renewal.remaining_days, 31);
+    assert_eq!(renewal.progress, 0.);
+}
+
+#[test]
+fn render_monthly_end_of_month() {
+    let cycle = monthly(NaiveDate::from_ymd(2021, 01, 31));
+    let args = cycle.render_arguments(Some(NaiveDate::from_ymd(2021, 02, 28)));
+    assert_eq!(args.start_date, NaiveDate::from_ymd(2021, 02, 28));
+    assert_eq!(args.end_date, NaiveDate::from_ymd(2021, 03, 31));
+
+    let args = cycle.render_arguments(Some(NaiveDate::from_ymd(2021, 02, 27)));
+    assert_eq!(args.start_date, NaiveDate::from_ymd(2021, 01, 31));
+    assert_eq!(args.end_date, NaiveDate::from_ymd(2021, 02, 28));
+    assert_eq!(args.remaining_days, 1);
+}
+
+#[test]
+fn render_yearly_leap_day() {
+    let cycle = Description {
+        start: NaiveDate::from_ymd(2020, 02, 29),
+        interval: Interval::Yearly,
+        ..monday()
+    };
+    let args = cycle.render_arguments(Some(NaiveDate::from_ymd(2021, 03, 01)));
+    assert_eq!(args.start_date, NaiveDate::from_ymd(2021, 02, 28));
+    assert_eq!(args.end_date, NaiveDate::from_ymd(2022, 02, 28));
+    assert_eq!(args.remaining_days, 364);
+}
+
+#[test]
+fn upcoming_renewals() {
+    let d = |m, day| NaiveDate::from_ymd(2021, m, day);
+    let cycle = monthly(d(1, 31));
+    assert_eq!(cycle.upcoming_renewals(d(3, 1), 3), vec![d(3, 31), d(4, 30), d(5, 31)]);
+    assert_eq!(cycle.upcoming_renewals(d(1, 1), 2), vec![d(1, 31), d(2, 28)]);
+}
+
+#[test]
+fn past_renewals() {
+    let d = |m, day| NaiveDate::from_ymd(2021, m, day);
+    let cycle = monthly(d(1, 31));
+    assert_eq!(cycle.past_renewals(d(3, 31)), vec![d(3, 31), d(2, 28), d(1, 31)]);
+    assert!(cycle.past_renewals(d(1, 30)).is_empty());
+}
@@ -0,0 +1,94 @@
+#![allow(non_snake_case)]
+mod common;
+
+use Cycles::{Description, FieldValue, Filter, Interval, TagMode, tag_totals};
+
+fn cycle(label: &str, tags: &[&str], interval: Interval, price: Option<f64>) -> Description {
+    Description {
+        tags: tags.iter().map(|x| x.to_string()).collect(),
+        label: label.into(),
+        description: Some(format!("{} subscription", label)),
+        interval,
+        price,
+        ..common::netflix()
+    }
+}
+
+fn selected(tags: &[&str], mode: TagMode) -> Filter {
+    Filter {
+        tags: tags.iter().map(|x| x.to_string()).collect(),
+        mode,
+        ..Filter::default()
+    }
+}
+
+#[test]
+fn empty_filter_matches_everything() {
+    assert!(Filter::default().matches(&cycle("Netflix", &[], Interval::Monthly, None)));
+}
+
+#[test]
+fn query_searches_label_description_and_tags() {
+    let netflix = cycle("Netflix", &["video"], Interval::Monthly, None);
+    let query = |q: &str| Filter { query: q.into(), ..Filter::default() };
+    assert!(query("netf").matches(&netflix));
+    assert!(query("SUBSCRIPTION").matches(&netflix));
+    assert!(query("vid").matches(&netflix));
+    assert!(!query("music").matches(&netflix));
+}
+
+#[test]
+fn query_searches_custom_fields() {
+    let mut netflix = cycle("Netflix", &[], Interval::Monthly, None);
+    netflix.fields.insert("Cost centre".into(), FieldValue::Text("MKT-204".into()));
+    netflix.fields.insert("Licences".into(), FieldValue::Number(25.));
+    let query = |q: &str| Filter { query: q.into(), ..Filter::default() };
+    assert!(query("mkt").matches(&netflix));
+    assert!(query("25").matches(&netflix));
+    assert!(!query("cost centre").matches(&netflix));
+}
+
+#[test]
+fn tag_modes() {
+    let netflix = cycle("Netflix", &["stream", "video"], Interval::Monthly, None);
+    let spotify = cycle("Spotify", &["stream", "music"], Interval::Monthly, None);
+
+    let all = selected(&["stream", "video"], TagMode::All);
+    assert!(all.matches(&netflix));
+    assert!(!all.matches(&spotify));
+
+    let any = selected(&["video", "music"], TagMode::Any);
+    assert!(any.matches(&netflix));
+    assert!(any.matches(&spotify));
+    assert!(!any.matches(&cycle("Dropbox", &["cloud"], Interval::Yearly, None)));
+}
+
+#[test]
+fn query_and_tags_combine() {
+    let netflix = cycle("Netflix", &["stream"], Interval::Monthly, None);
+    let spotify = cycle("Spotify", &["stream"], Interval::Monthly, None);
+    let filter = Filter { query: "spot".into(), ..selected(&["stream"], TagMode::All) };
+    assert!(!filter.matches(&netflix));
+    assert!(filter.matches(&spotify));
+}
+
+#[test]
+fn totals_per_tag() {
+    let cycles = vec![
+        cycle("Netflix", &["stream"], Interval::Monthly, Some(10.)),
+        cycle("Spotify", &["stream", "music"], Interval::Yearly, Some(120.)),
+        cycle("Radio", &["music"], Interval::Weekly, None)
+    ];
+    let totals = tag_totals(&cycles);
+    assert_eq!(totals["stream"].count, 2);
+    assert!((totals["stream"].monthly - 20.).abs() < 1e-9);
+    assert_eq!(totals["music"].count, 2);
+    assert!((totals["music"].monthly - 10.).abs() < 1e-9);
+}
+
+#[test]
+fn monthly_cost_spreads_prices() {
+    let weekly = Description { interval_multiplier: 2, ..cycle("Paper", &[], Interval::Weekly, Some(3.)) };
+    assert!((weekly.monthly_cost().unwrap() - 6.5).abs() < 1e-9);
+    assert_eq!(cycle("Free", &[], Interval::Monthly, None).monthly_cost(), None);
+}
@@ -0,0 +1,66 @@
+#![allow(non_snake_case)]
+mod common;
+
+use Cycles::{Database, Ordering};
+use common::netflix;
+use uuid::Uuid;
+
+fn ids(n: usize) -> Vec<Uuid> {
+    (0..n).map(|_| Uuid::new_v4()).collect()
+}
+
+#[test]
+fn sync_appends_and_forgets() {
+    let id = ids(3);
+    let mut order = Ordering { pinned: vec![id[0]], order: vec![id[1]] };
+    order.sync(vec![id[1], id[2]]);
+    assert_eq!(order, Ordering { pinned: vec![], order: vec![id[1], id[2]] });
+}
+
+#[test]
+fn pinning_moves_between_groups() {
+    let id = ids(3);
+    let mut order = Ordering::default();
+    order.sync(id.clone());
+    order.set_pinned(id[2], true);
+    assert!(order.is_pinned(id[2]));
+    assert_eq!(order.ids().cloned().collect::<Vec<_>>(), vec![id[2], id[0], id[1]]);
+    order.set_pinned(id[2], false);
+    assert_eq!(order.ids().cloned().collect::<Vec<_>>(), vec![id[2], id[0], id[1]]);
+    assert!(!order.is_pinned(id[2]));
+}
+
+#[test]
+fn move_before_joins_target_group() {
+    let id = ids(4);
+    let mut order = Ordering::default();
+    order.sync(id.clone());
+    order.move_before(id[3], id[1]);
+    assert_eq!(order.order, vec![id[0], id[3], id[1], id[2]]);
+
+    order.set_pinned(id[0], true);
+    order.move_before(id[2], id[0]);
+    assert_eq!(order.pinned, vec![id[2], id[0]]);
+    assert_eq!(order.order, vec![id[3], id[1]]);
+
+    order.move_before(id[1], Uuid::new_v4());
+    assert_eq!(order.order, vec![id[3], id[1]]);
+}
+
+#[test]
+fn ordering_survives_export() {
+    let db = Database::temporary();
+    let a = db.append(netflix());
+    let b = db.append(netflix());
+    let mut order = Ordering { pinned: vec![b], order: vec![a] };
+    order.save(&db);
+
+    let mut data = vec![];
+    db.export_to_writer(&mut data, false);
+    let other = Database::temporary();
+    other.import_from_reader(&data[..]);
+    assert_eq!(Ordering::load(&other), order);
+
+    order.sync(vec![a]);
+    assert_eq!(order.ids().count(), 1);
+}
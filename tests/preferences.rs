@@ -0,0 +1,67 @@
+#![allow(non_snake_case)]
+mod common;
+
+use Cycles::{Description, Preferences, SortOrder};
+use chrono::NaiveDate;
+
+fn cycle(label: &str, day: u32, price: Option<f64>) -> Description {
+    Description {
+        tags: vec![],
+        label: label.into(),
+        start: NaiveDate::from_ymd(2021, 04, day),
+        price,
+        ..common::netflix()
+    }
+}
+
+#[test]
+fn invalid_date_format_falls_back() {
+    let date = NaiveDate::from_ymd(2021, 04, 9);
+    let mut prefs = Preferences { date_format: "%d.%m.".into(), ..Preferences::default() };
+    assert_eq!(prefs.format_date(date), "09.04.");
+    prefs.date_format = "%Q".into();
+    assert_eq!(prefs.format_date(date), "04/09");
+}
+
+#[test]
+fn price_includes_currency() {
+    let mut prefs = Preferences::default();
+    assert_eq!(prefs.format_price(9.5), "9.50");
+    prefs.currency = "EUR".into();
+    assert_eq!(prefs.format_price(9.5), "9.50 EUR");
+}
+
+#[test]
+fn due_soon_uses_reminder_offset() {
+    let prefs = Preferences { reminder_days: 3, ..Preferences::default() };
+    let today = NaiveDate::from_ymd(2021, 05, 2);
+    assert!(prefs.is_due_soon(&cycle("Netflix", 4, None), Some(today)));
+    assert!(!prefs.is_due_soon(&cycle("Netflix", 10, None), Some(today)));
+}
+
+#[test]
+fn due_soon_counts_the_first_start() {
+    let prefs = Preferences { reminder_days: 3, ..Preferences::default() };
+    let today = NaiveDate::from_ymd(2021, 04, 2);
+    assert!(prefs.is_due_soon(&cycle("Netflix", 4, None), Some(today)));
+    assert!(!prefs.is_due_soon(&cycle("Netflix", 20, None), Some(today)));
+}
+
+#[test]
+fn sort_orders() {
+    let today = NaiveDate::from_ymd(2021, 05, 2);
+    let mut cycles = vec![cycle("spotify", 20, Some(5.)), cycle("Netflix", 4, Some(12.)), cycle("Apple", 10, None)];
+    let labels = |cycles: &[Description]| cycles.iter().map(|c| c.label.clone()).collect::<Vec<_>>();
+
+    let mut prefs = Preferences { sort_order: SortOrder::Label, ..Preferences::default() };
+    cycles.sort_by(|a, b| prefs.compare(a, b, today));
+    assert_eq!(labels(&cycles), ["Apple", "Netflix", "spotify"]);
+
+    prefs.sort_order = SortOrder::NextRenewal;
+    cycles.sort_by(|a, b| prefs.compare(a, b, today));
+    assert_eq!(labels(&cycles), ["Netflix", "Apple", "spotify"]);
+
+    prefs.sort_order = SortOrder::Price;
+    cycles.sort_by(|a, b| prefs.compare(a, b, today));
+    assert_eq!(labels(&cycles), ["Netflix", "spotify", "Apple"]);
+}
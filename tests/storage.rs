@@ -0,0 +1,126 @@
+#![allow(non_snake_case)]
+mod common;
+
+use Cycles::{Database, Description, FieldValue, FileStore, MemoryStore, Storage, StorageError, migrate};
+use common::netflix;
+use chrono::NaiveDate;
+
+fn temp_file() -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("cycles-storage-{}.toml", uuid::Uuid::new_v4()))
+}
+
+fn round_trip(store: &dyn Storage) {
+    let id = store.append(netflix());
+    assert_eq!(store.get(id), Some(netflix()));
+    let renamed = Description { label: "Netflix HD".into(), ..netflix() };
+    store.update(id, renamed.clone());
+    assert_eq!(store.entries(), vec![(id, renamed.clone())]);
+    assert_eq!(store.remove(id), Some(renamed));
+    assert_eq!(store.remove(id), None);
+    assert!(store.entries().is_empty());
+}
+
+#[test]
+fn backends_behave_alike() {
+    round_trip(&MemoryStore::default());
+    round_trip(&Database::temporary());
+    let path = temp_file();
+    round_trip(&FileStore::open(&path).unwrap());
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn file_store_is_readable_and_reloads() {
+    let path = temp_file();
+    let id = FileStore::open(&path).unwrap().append(netflix());
+    let text = std::fs::read_to_string(&path).unwrap();
+    assert!(text.contains(&id.to_string()));
+    assert!(text.contains("label = 'Netflix'"));
+    assert_eq!(FileStore::open(&path).unwrap().entries(), vec![(id, netflix())]);
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn migrate_between_backends() {
+    let memory = MemoryStore::default();
+    let a = memory.append(netflix());
+    let b = memory.append(Description { label: "Spotify".into(), ..netflix() });
+    let db = Database::temporary();
+    assert_eq!(migrate(&memory, &db), 2);
+    let target = MemoryStore::default();
+    assert_eq!(migrate(&db, &target), 2);
+    assert_eq!(target.get(a), Some(netflix()));
+    assert_eq!(target.get(b).map(|d| d.label), Some("Spotify".into()));
+}
+
+#[test]
+fn attached_file_follows_database_changes() {
+    let path = temp_file();
+    let existing = FileStore::open(&path).unwrap().append(netflix());
+    let mut db = Database::temporary();
+    assert!(db.attach(FileStore::open(&path).unwrap()));
+    assert_eq!(db.get(existing), Some(netflix()));
+    let added = db.append(Description { label: "Spotify".into(), ..netflix() });
+    db.remove(existing);
+    let ids: Vec<_> = FileStore::open(&path).unwrap().entries().into_iter().map(|(id, _)| id).collect();
+    assert_eq!(ids, vec![added]);
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn attaching_keeps_cycles_only_in_sled() {
+    let path = temp_file();
+    let mut db = Database::temporary();
+    let kept = db.append(netflix());
+    assert!(!db.attach(FileStore::open(&path).unwrap()));
+    assert_eq!(db.get(kept), Some(netflix()));
+    assert!(!path.exists());
+
+    let added = FileStore::open(&path).unwrap().append(Description { label: "Spotify".into(), ..netflix() });
+    assert!(db.attach(FileStore::open(&path).unwrap()));
+    assert_eq!(db.get(kept), Some(netflix()));
+    let ids: Vec<_> = FileStore::open(&path).unwrap().entries().into_iter().map(|(id, _)| id).collect();
+    assert_eq!(ids.len(), 2);
+    assert!(ids.contains(&kept) && ids.contains(&added));
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn file_store_keeps_custom_fields() {
+    let path = temp_file();
+    let mut fields = std::collections::BTreeMap::new();
+    fields.insert("Contract".to_string(), FieldValue::Text("A-1042".into()));
+    fields.insert("Seats".to_string(), FieldValue::Number(3.));
+    fields.insert("Signed".to_string(), FieldValue::Date(NaiveDate::from_ymd(2021, 3, 1)));
+    fields.insert("Portal".to_string(), FieldValue::Url("https://example.com".into()));
+    let described = Description { fields, ..netflix() };
+    let id = FileStore::open(&path).unwrap().append(described.clone());
+    assert_eq!(FileStore::open(&path).unwrap().get(id), Some(described));
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn typos_in_the_file_are_errors_not_crashes() {
+    let path = temp_file();
+    std::fs::write(&path, "[cycles.oops\nlabel = ").unwrap();
+    assert!(matches!(FileStore::open(&path), Err(StorageError::Parse(..))));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "[cycles.oops\nlabel = ");
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn cycles_removed_from_the_file_by_hand_are_trashed() {
+    let path = temp_file();
+    let mut db = Database::temporary();
+    let kept = db.append(netflix());
+    let dropped = db.append(Description { label: "Spotify".into(), ..netflix() });
+    migrate(&db, &FileStore::open(&path).unwrap());
+    assert!(db.attach(FileStore::open(&path).unwrap()));
+
+    FileStore::open(&path).unwrap().remove(dropped);
+    assert!(db.attach(FileStore::open(&path).unwrap()));
+    assert_eq!(db.get(dropped), None);
+    assert_eq!(db.trash().map(|(id, _)| id).collect::<Vec<_>>(), vec![dropped]);
+    assert_eq!(db.get(kept), Some(netflix()));
+    let _ = std::fs::remove_file(path);
+}
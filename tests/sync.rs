@@ -0,0 +1,129 @@
+#![allow(non_snake_case)]
+mod common;
+
+use Cycles::{Change, Database, Description, Record, SyncFile, merge_sync, run_sync};
+use common::netflix;
+use uuid::Uuid;
+
+fn temp_folder() -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("cycles-sync-{}", Uuid::new_v4()))
+}
+
+fn tick() {
+    std::thread::sleep(std::time::Duration::from_millis(5));
+}
+
+#[test]
+fn changes_are_tracked_with_tombstones() {
+    let db = Database::temporary();
+    let id = db.append(netflix());
+    let added = db.change(id).unwrap();
+    assert_eq!(added.device, db.device());
+    assert!(!added.deleted);
+    tick();
+    db.remove(id);
+    let removed = db.change(id).unwrap();
+    assert!(removed.deleted);
+    assert!(removed.wins_over(&added));
+    let file = SyncFile::from_database(&db);
+    assert_eq!(file.records.len(), 1);
+    assert_eq!(file.records[0].description, None);
+}
+
+#[test]
+fn edits_and_deletes_travel_through_the_folder() {
+    let folder = temp_folder();
+    let (phone, laptop) = (Database::temporary(), Database::temporary());
+    let id = phone.append(netflix());
+    run_sync(&phone, &folder).unwrap();
+    let summary = run_sync(&laptop, &folder).unwrap();
+    assert_eq!(summary.devices, 1);
+    assert_eq!(laptop.get(id), Some(netflix()));
+
+    tick();
+    laptop.remove(id);
+    run_sync(&laptop, &folder).unwrap();
+    let summary = run_sync(&phone, &folder).unwrap();
+    assert_eq!(summary.changed, vec![id]);
+    assert_eq!(summary.conflicts, 0);
+    assert_eq!(phone.get(id), None);
+    assert_eq!(phone.trash().map(|(id, _)| id).collect::<Vec<_>>(), vec![id]);
+    let _ = std::fs::remove_dir_all(folder);
+}
+
+#[test]
+fn concurrent_edits_keep_the_newest_and_list_a_conflict() {
+    let folder = temp_folder();
+    let (phone, laptop) = (Database::temporary(), Database::temporary());
+    let id = phone.append(netflix());
+    run_sync(&phone, &folder).unwrap();
+    run_sync(&laptop, &folder).unwrap();
+    run_sync(&phone, &folder).unwrap();
+
+    tick();
+    phone.update(id, Description { price: Some(15.49), ..netflix() });
+    tick();
+    laptop.update(id, Description { label: "Netflix 4K".into(), ..netflix() });
+    run_sync(&phone, &folder).unwrap();
+    let summary = run_sync(&laptop, &folder).unwrap();
+    assert_eq!(summary.conflicts, 1);
+    let newest = Description { label: "Netflix 4K".into(), ..netflix() };
+    assert_eq!(laptop.get(id), Some(newest.clone()));
+    let conflict = laptop.conflicts().next().unwrap();
+    assert_eq!(conflict.other, Some(Description { price: Some(15.49), ..netflix() }));
+
+    run_sync(&phone, &folder).unwrap();
+    assert_eq!(phone.get(id), Some(newest));
+    assert_eq!(phone.conflicts().count(), 0);
+
+    tick();
+    let taken = laptop.resolve_conflict(id, true);
+    assert_eq!(taken, Some(Description { price: Some(15.49), ..netflix() }));
+    run_sync(&laptop, &folder).unwrap();
+    run_sync(&phone, &folder).unwrap();
+    assert_eq!(phone.get(id), taken);
+    let _ = std::fs::remove_dir_all(folder);
+}
+
+#[test]
+fn ties_break_on_device_id() {
+    let db = Database::temporary();
+    let id = db.append(netflix());
+    let local = db.change(id).unwrap();
+    let other = Change { device: Uuid::from_u128(u128::MAX), ..local };
+    let file = SyncFile {
+        version: 1,
+        device: other.device,
+        records: vec![Record { id, change: other, description: None }],
+        seen: Default::default()
+    };
+    merge_sync(&db, &file);
+    assert_eq!(db.get(id), None);
+    assert_eq!(db.change(id), Some(other));
+}
+
+#[test]
+fn archiving_travels_without_a_tombstone() {
+    let folder = temp_folder();
+    let (phone, laptop) = (Database::temporary(), Database::temporary());
+    let id = phone.append(netflix());
+    run_sync(&phone, &folder).unwrap();
+    run_sync(&laptop, &folder).unwrap();
+
+    tick();
+    phone.archive(&[id]);
+    assert!(!phone.change(id).unwrap().deleted);
+    run_sync(&phone, &folder).unwrap();
+    run_sync(&laptop, &folder).unwrap();
+    assert_eq!(laptop.get(id), None);
+    assert_eq!(laptop.get_archived(id), Some(netflix()));
+    assert_eq!(laptop.trash().count(), 0);
+
+    tick();
+    laptop.unarchive(id);
+    run_sync(&laptop, &folder).unwrap();
+    run_sync(&phone, &folder).unwrap();
+    assert_eq!(phone.get(id), Some(netflix()));
+    assert_eq!(phone.archived().count(), 0);
+    let _ = std::fs::remove_dir_all(folder);
+}
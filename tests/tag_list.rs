@@ -0,0 +1,68 @@
+#![allow(non_snake_case)]
+use Cycles::{TagList, normalize_tag};
+
+fn tags(values: &[&str]) -> Vec<String> {
+    values.iter().map(|x| x.to_string()).collect()
+}
+
+#[test]
+fn starts_from_existing_tags() {
+    let list = TagList::new(tags(&["stream", "video"]));
+    assert_eq!(list.values(), tags(&["stream", "video"]));
+}
+
+#[test]
+fn add_after_delete_keeps_other_tags() {
+    let mut list = TagList::new(tags(&["a", "b", "c"]));
+    let b = list.entries()[1].0;
+    assert!(list.remove(b));
+
+    // The old editor keyed rows by `len()`, so this id collided with "c".
+    let new = list.add("d").unwrap();
+    assert!(list.entries().iter().all(|(id, tag)| *id != new || tag == "d"));
+    assert_eq!(list.values(), tags(&["a", "c", "d"]));
+
+    let mut ids: Vec<usize> = list.entries().iter().map(|(id, _)| *id).collect();
+    ids.sort();
+    ids.dedup();
+    assert_eq!(ids.len(), 3);
+}
+
+#[test]
+fn values_keep_insertion_order() {
+    let mut list = TagList::default();
+    for x in 0..20 {
+        list.add(&format!("tag{}", x));
+    }
+    let expected: Vec<String> = (0..20).map(|x| format!("tag{}", x)).collect();
+    assert_eq!(list.values(), expected);
+}
+
+#[test]
+fn normalises_case_and_whitespace() {
+    assert_eq!(normalize_tag("  Cloud   Storage "), Some("cloud storage".into()));
+    assert_eq!(normalize_tag(" \t "), None);
+
+    let mut list = TagList::default();
+    list.add(" Streaming");
+    assert_eq!(list.values(), tags(&["streaming"]));
+}
+
+#[test]
+fn rejects_duplicates_and_blanks() {
+    let mut list = TagList::new(tags(&["streaming", "Streaming"]));
+    assert_eq!(list.values(), tags(&["streaming"]));
+    assert_eq!(list.add("STREAMING "), None);
+    assert_eq!(list.add("   "), None);
+    assert!(list.contains("Streaming"));
+    assert_eq!(list.values(), tags(&["streaming"]));
+}
+
+#[test]
+fn stale_ids_are_ignored() {
+    let mut list = TagList::new(tags(&["stream"]));
+    let id = list.entries()[0].0;
+    assert!(list.remove(id));
+    assert!(!list.remove(id));
+    assert!(list.values().is_empty());
+}
@@ -0,0 +1,88 @@
+#![allow(non_snake_case)]
+mod common;
+
+use Cycles::{Database, Description, TagStyle};
+
+fn tagged(label: &str, tags: &[&str]) -> Description {
+    Description {
+        tags: tags.iter().map(|x| x.to_string()).collect(),
+        label: label.into(),
+        price: None,
+        ..common::netflix()
+    }
+}
+
+#[test]
+fn rename_tag_in_description() {
+    let mut netflix = tagged("Netflix", &["stream", "video"]);
+    assert!(netflix.rename_tag("stream", "streaming"));
+    assert_eq!(netflix.tags, vec!["streaming", "video"]);
+    assert!(!netflix.rename_tag("music", "audio"));
+}
+
+#[test]
+fn merge_tag_in_description() {
+    let mut netflix = tagged("Netflix", &["stream", "streaming"]);
+    assert!(netflix.rename_tag("stream", "streaming"));
+    assert_eq!(netflix.tags, vec!["streaming"]);
+}
+
+#[test]
+fn rename_tag_across_cycles() {
+    let db = Database::temporary();
+    let netflix = db.append(tagged("Netflix", &["stream"]));
+    let spotify = db.append(tagged("Spotify", &["music", "streaming"]));
+    let dropbox = db.append(tagged("Dropbox", &["cloud"]));
+    db.set_tag_style("stream", &TagStyle { color: Some("rgb(255,0,0)".into()), icon: None });
+
+    let mut changed = db.rename_tag("stream", "streaming");
+    changed.sort();
+    assert_eq!(changed, vec![netflix]);
+    assert_eq!(db.get(netflix).unwrap().tags, vec!["streaming"]);
+    assert_eq!(db.get(spotify).unwrap().tags, vec!["music", "streaming"]);
+    assert_eq!(db.get(dropbox).unwrap().tags, vec!["cloud"]);
+
+    let styles = db.tag_styles();
+    assert!(!styles.contains_key("stream"));
+    assert_eq!(styles["streaming"].color.as_deref(), Some("rgb(255,0,0)"));
+    assert_eq!(db.tag_counts().get("streaming"), Some(&2));
+}
+
+#[test]
+fn merge_keeps_target_style() {
+    let db = Database::temporary();
+    db.append(tagged("Netflix", &["stream"]));
+    db.set_tag_style("stream", &TagStyle { color: Some("red".into()), icon: None });
+    db.set_tag_style("video", &TagStyle { color: Some("blue".into()), icon: None });
+    db.rename_tag("stream", "video");
+    assert_eq!(db.tag_styles()["video"].color.as_deref(), Some("blue"));
+}
+
+#[test]
+fn rename_normalizes_and_merges() {
+    let db = Database::temporary();
+    let netflix = db.append(tagged("Netflix", &["stream", "video"]));
+    let spotify = db.append(tagged("Spotify", &["stream"]));
+    assert!(db.rename_tag("stream", "   ").is_empty());
+    let mut changed = db.rename_tag("stream", "  Video ");
+    changed.sort();
+    let mut expected = vec![netflix, spotify];
+    expected.sort();
+    assert_eq!(changed, expected);
+    assert_eq!(db.get(netflix).unwrap().tags, vec!["video"]);
+    assert_eq!(db.get(spotify).unwrap().tags, vec!["video"]);
+    assert_eq!(db.tag_counts().get("video"), Some(&2));
+}
+
+#[test]
+fn delete_tag_across_cycles() {
+    let db = Database::temporary();
+    let netflix = db.append(tagged("Netflix", &["stream", "video"]));
+    db.append(tagged("Dropbox", &["cloud"]));
+    db.set_tag_style("stream", &TagStyle::default());
+
+    assert_eq!(db.delete_tag("stream"), vec![netflix]);
+    assert_eq!(db.get(netflix).unwrap().tags, vec!["video"]);
+    assert!(db.tag_styles().is_empty());
+    assert_eq!(db.tag_counts().get("stream"), None);
+}
@@ -0,0 +1,57 @@
+#![allow(non_snake_case)]
+mod common;
+
+use Cycles::{Database, Description, Interval, Template};
+use chrono::NaiveDate;
+
+fn netflix() -> Description {
+    Description {
+        description: Some("Family plan".into()),
+        color: Some("rgb(229,9,20)".into()),
+        price: Some(17.99),
+        ..common::netflix()
+    }
+}
+
+#[test]
+fn bundled_catalog_parses() {
+    let templates = Template::bundled();
+    assert!(templates.len() >= 10);
+    assert!(templates.iter().all(|t| !t.label.is_empty() && t.interval_multiplier > 0));
+    let insurance = templates.iter().find(|t| t.label == "Car Insurance").unwrap();
+    assert_eq!(insurance.interval_multiplier, 6);
+    assert_eq!(insurance.price, None);
+}
+
+#[test]
+fn parse_defaults() {
+    let templates = Template::parse("[[template]]\nlabel = \"Gym\"\ninterval = \"Weekly\"\n");
+    assert_eq!(templates, vec![Template {
+        label: "Gym".into(),
+        interval: Interval::Weekly,
+        interval_multiplier: 1,
+        color: None,
+        tags: vec![],
+        price: None
+    }]);
+}
+
+#[test]
+fn template_round_trip() {
+    let description = netflix();
+    let template = Template::from(&description);
+    let start = NaiveDate::from_ymd(2022, 1, 1);
+    assert_eq!(template.describe(start), Description { description: None, start, ..description });
+}
+
+#[test]
+fn custom_templates_replace_by_label() {
+    let db = Database::temporary();
+    Template::save_custom(&db, Template::from(&netflix()));
+    Template::save_custom(&db, Template { price: Some(19.99), ..Template::from(&netflix()) });
+    let custom = Template::custom(&db);
+    assert_eq!(custom.len(), 1);
+    assert_eq!(custom[0].price, Some(19.99));
+    assert_eq!(Template::all(&db)[0], custom[0]);
+    assert_eq!(Template::all(&db).len(), Template::bundled().len() + 1);
+}
@@ -0,0 +1,46 @@
+#![allow(non_snake_case)]
+mod common;
+
+use Cycles::{Action, Attachment, Blob, Database, Export, UndoStack};
+use common::netflix;
+use chrono::NaiveDate;
+use uuid::Uuid;
+
+#[test]
+fn undo_is_last_in_first_out() {
+    let id = Uuid::new_v4();
+    let mut stack = UndoStack::default();
+    stack.push(Action::Added(id, netflix()));
+    stack.push(Action::Deleted(id, netflix()));
+    assert_eq!(stack.pop(), Some(Action::Deleted(id, netflix())));
+    assert_eq!(stack.pop(), Some(Action::Added(id, netflix())));
+    assert_eq!(stack.pop(), None);
+
+    stack.push(Action::Added(id, netflix()));
+    stack.clear();
+    assert!(stack.is_empty());
+}
+
+#[test]
+fn undo_drops_oldest_when_full() {
+    let mut stack = UndoStack::default();
+    let first = Uuid::new_v4();
+    stack.push(Action::Added(first, netflix()));
+    for _ in 0..50 {
+        stack.push(Action::Added(Uuid::new_v4(), netflix()));
+    }
+    assert_eq!(stack.len(), 50);
+    while let Some(action) = stack.pop() {
+        assert_ne!(action, Action::Added(first, netflix()));
+    }
+}
+
+#[test]
+fn undo_messages() {
+    let id = Uuid::new_v4();
+    assert_eq!(Action::Deleted(id, netflix()).message(), "Deleted “Netflix”");
+    let export = Export { cycles: vec![(id, netflix()), (Uuid::new_v4(), netflix())], ..Export::default() };
+    assert_eq!(Action::Imported(Database::temporary().merge(&export)).message(), "Imported 2 cycles");
+    let invoice = Attachment::new(id, "invoice.pdf", 3, NaiveDate::from_ymd(2021, 4, 4), None);
+    assert_eq!(Action::Detached(invoice, Blob(b"pdf".to_vec())).message(), "Removed “invoice.pdf”");
+}
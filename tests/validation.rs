@@ -0,0 +1,102 @@
+#![allow(non_snake_case)]
+use Cycles::{FieldDefinition, FieldKind, FieldValue, FormError, FormInput, Interval};
+use chrono::NaiveDate;
+
+fn input() -> FormInput {
+    FormInput {
+        label: " Netflix ".into(),
+        description: "".into(),
+        color: None,
+        tags: vec!["video".into()],
+        interval: Some(Interval::Monthly),
+        interval_multiplier: 1,
+        start: (2021, 4, 4),
+        price: 9.99,
+        notes: "".into(),
+        url: "".into(),
+        account: "".into(),
+        payment: "".into(),
+        fields: vec![]
+    }
+}
+
+#[test]
+fn valid_input_builds_description() {
+    let description = input().validate().unwrap();
+    assert_eq!(description.label, "Netflix");
+    assert_eq!(description.description, None);
+    assert_eq!(description.start, NaiveDate::from_ymd(2021, 4, 4));
+    assert_eq!(description.price, Some(9.99));
+    assert_eq!(description.tags, vec!["video".to_string()]);
+}
+
+#[test]
+fn zero_price_is_no_price() {
+    let description = FormInput { price: 0., ..input() }.validate().unwrap();
+    assert_eq!(description.price, None);
+}
+
+#[test]
+fn collects_every_error() {
+    let invalid = FormInput {
+        label: "   ".into(),
+        interval: None,
+        interval_multiplier: 0,
+        start: (2021, 2, 30),
+        price: -1.,
+        ..input()
+    };
+    assert_eq!(invalid.validate(), Err(vec![
+        FormError::EmptyLabel,
+        FormError::MissingInterval,
+        FormError::InvalidMultiplier(0),
+        FormError::InvalidDate(2021, 2, 30),
+        FormError::NegativePrice(-1.)
+    ]));
+}
+
+#[test]
+fn single_error() {
+    let invalid = FormInput { interval: None, ..input() };
+    assert_eq!(invalid.validate(), Err(vec![FormError::MissingInterval]));
+    assert_eq!(FormError::MissingInterval.to_string(), "Choose an interval");
+}
+
+#[test]
+fn account_fields_are_trimmed_and_optional() {
+    let description = FormInput {
+        notes: " Shared with family\nRenew in May ".into(),
+        url: "netflix.com/account".into(),
+        account: "  ".into(),
+        payment: "Visa 1234".into(),
+        ..input()
+    }.validate().unwrap();
+    assert_eq!(description.notes.as_deref(), Some("Shared with family\nRenew in May"));
+    assert_eq!(description.url.as_deref(), Some("https://netflix.com/account"));
+    assert_eq!(description.account, None);
+    assert_eq!(description.payment.as_deref(), Some("Visa 1234"));
+
+    let invalid = FormInput { url: "not a link".into(), ..input() };
+    assert_eq!(invalid.validate(), Err(vec![FormError::InvalidUrl("not a link".into())]));
+}
+
+#[test]
+fn custom_fields_are_parsed_by_kind() {
+    let field = |name: &str, kind| FieldDefinition { name: name.into(), kind };
+    let description = FormInput {
+        fields: vec![
+            (field("Contract", FieldKind::Text), " C-1042 ".into()),
+            (field("Licences", FieldKind::Number), "25".into()),
+            (field("Signed", FieldKind::Date), "2021-03-01".into()),
+            (field("Portal", FieldKind::Url), "".into())
+        ],
+        ..input()
+    }.validate().unwrap();
+    assert_eq!(description.fields.get("Contract"), Some(&FieldValue::Text("C-1042".into())));
+    assert_eq!(description.fields.get("Licences"), Some(&FieldValue::Number(25.)));
+    assert_eq!(description.fields.get("Signed"), Some(&FieldValue::Date(NaiveDate::from_ymd(2021, 3, 1))));
+    assert!(!description.fields.contains_key("Portal"));
+
+    let invalid = FormInput { fields: vec![(field("Licences", FieldKind::Number), "many".into())], ..input() };
+    assert_eq!(invalid.validate(), Err(vec![FormError::InvalidField("Licences".into(), FieldKind::Number)]));
+}